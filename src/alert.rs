@@ -1,22 +1,24 @@
 //! Alert subcommand — stream flashblocks, match rules, log/webhook on hits.
 
+use std::collections::HashMap;
 use std::io::Read;
 
 use chrono::Utc;
 use colored::Colorize;
 use futures_util::StreamExt;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{debug, info, warn};
+use tracing::{info, warn};
 
 use crate::decode;
-use crate::rules::{Alert, RuleEngine};
-use crate::types::FlashblockMessage;
+use crate::rules::{Alert, BlockAlert, RuleEngine, RulesConfig, Severity};
+use crate::types::{BlockState, FlashblockMessage};
+use crate::sink::Destination;
 
 fn decode_message(data: &[u8]) -> Option<String> {
-    if let Ok(text) = std::str::from_utf8(data) {
-        if text.trim_start().starts_with('{') {
-            return Some(text.to_owned());
-        }
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.trim_start().starts_with('{')
+    {
+        return Some(text.to_owned());
     }
     let mut decompressor = brotli::Decompressor::new(data, 4096);
     let mut decompressed = Vec::new();
@@ -27,8 +29,7 @@ fn decode_message(data: &[u8]) -> Option<String> {
 }
 
 pub async fn run(ws_url: &str, rules_path: &str, json_output: bool) -> eyre::Result<()> {
-    let rules_str = std::fs::read_to_string(rules_path)?;
-    let mut engine = RuleEngine::from_toml(&rules_str)?;
+    let mut engine = RuleEngine::new(RulesConfig::from_file(std::path::Path::new(rules_path))?);
 
     let rule_count = engine.config.rules.iter().filter(|r| r.enabled).count();
     info!("Loaded {} active rules from {}", rule_count, rules_path);
@@ -43,12 +44,13 @@ pub async fn run(ws_url: &str, rules_path: &str, json_output: bool) -> eyre::Res
         }
     }
 
-    // Collect webhook URLs for the HTTP client
+    // Webhooks are sent by a dispatcher so a slow receiver can't hold up the stream
     let has_webhooks = engine.config.rules.iter().any(|r| r.webhook.is_some());
-    let http_client = if has_webhooks {
-        Some(reqwest::Client::builder()
+    let dispatcher = if has_webhooks {
+        let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(5))
-            .build()?)
+            .build()?;
+        Some(crate::delivery::Dispatcher::new(std::sync::Arc::new(client), None, Default::default()))
     } else {
         None
     };
@@ -56,8 +58,10 @@ pub async fn run(ws_url: &str, rules_path: &str, json_output: bool) -> eyre::Res
     info!("Connecting to {}", ws_url);
     const RECONNECT_PAUSE: std::time::Duration = std::time::Duration::from_secs(2);
 
+    let mut batcher = Batcher::default();
+
     loop {
-        match connect_and_stream(ws_url, &mut engine, json_output, &http_client).await {
+        match connect_and_stream(ws_url, &mut engine, &mut batcher, json_output, dispatcher.as_ref()).await {
             Ok(()) => {
                 info!("Stream ended cleanly");
                 break;
@@ -75,8 +79,9 @@ pub async fn run(ws_url: &str, rules_path: &str, json_output: bool) -> eyre::Res
 async fn connect_and_stream(
     ws_url: &str,
     engine: &mut RuleEngine,
+    batcher: &mut Batcher,
     json_output: bool,
-    http_client: &Option<reqwest::Client>,
+    dispatcher: Option<&std::sync::Arc<crate::delivery::Dispatcher>>,
 ) -> eyre::Result<()> {
     let (mut ws, _) = connect_async(ws_url).await?;
 
@@ -94,6 +99,7 @@ async fn connect_and_stream(
     info!("Connected — watching for alerts...");
 
     let mut current_block: Option<u64> = None;
+    let mut block_state = BlockState::default();
     let mut alert_count = 0u64;
 
     const STALE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
@@ -138,28 +144,41 @@ async fn connect_and_stream(
             current_block = fb.block_number();
         }
 
-        // Decode each transaction and check rules
-        for tx_val in &fb.diff.transactions {
-            if let Some(tx_hex) = tx_val.as_str() {
-                if let Some(decoded) = decode::decode_raw_tx(tx_hex) {
-                    let alerts = engine.check(&decoded, block_number, fb.index);
-                    for alert in alerts {
-                        alert_count += 1;
-
-                        if json_output {
-                            if let Ok(json) = serde_json::to_string(&alert) {
-                                println!("{}", json);
-                            }
-                        } else {
-                            print_alert(&alert, alert_count);
-                        }
-
-                        // Fire webhook if configured
-                        if let Some(client) = http_client {
-                            fire_webhook(client, &engine.config, &alert).await;
-                        }
-                    }
+        // Block-scope rules first, then decode each transaction and check rules
+        block_state.update(&fb);
+        let mut alerts = engine.check_block(&block_state, fb.index);
+        alerts.extend(engine.check_balances(&fb, block_number));
+        let mut txs: Vec<_> = fb.diff.transactions.iter()
+            .filter_map(|t| t.as_str().and_then(decode::decode_raw_tx))
+            .collect();
+        decode::annotate_median_tip(&mut txs);
+        for decoded in &txs {
+            alerts.extend(engine.check(decoded, block_number, fb.index));
+        }
+        alerts.extend(engine.check_mev(&fb, &txs, block_number));
+
+        for alert in alerts {
+            alert_count += 1;
+
+            if json_output {
+                if let Ok(json) = serde_json::to_string(&alert) {
+                    println!("{}", json);
                 }
+            } else {
+                print_alert(&alert, alert_count);
+            }
+
+            // Fire webhook if configured
+            if let Some(dispatcher) = dispatcher
+                && let Some(batch) = batcher.push(&engine.config, alert, now_ms())
+            {
+                send_batch(dispatcher, &engine.config, &batch);
+            }
+        }
+
+        if let Some(dispatcher) = dispatcher {
+            for batch in batcher.due(now_ms()) {
+                send_batch(dispatcher, &engine.config, &batch);
             }
         }
     }
@@ -173,78 +192,276 @@ fn print_alert(alert: &Alert, count: u64) {
         .map(|n| n.to_string())
         .unwrap_or("?".into());
 
-    let value = if alert.tx.value_eth > 0.001 {
-        format!("{:.4} ETH", alert.tx.value_eth).green().to_string()
-    } else {
-        String::new()
+    let (value, target, action, category) = match (&alert.tx, &alert.block) {
+        (Some(tx), _) => {
+            let value = if let Some(ref f) = alert.security {
+                format!("risk {}", f.risk).red().bold().to_string()
+            } else if tx.value_eth > 0.001 {
+                format!("{:.4} ETH", tx.value_eth).green().to_string()
+            } else {
+                String::new()
+            };
+            let created = tx.contract_creation.as_ref()
+                .map(|c| format!("(create) {}", c.address.as_deref().unwrap_or("?")));
+            let target = tx.to_label.clone()
+                .or(created)
+                .unwrap_or(tx.to.clone().unwrap_or("?".into()));
+            (value, target, tx.action.as_deref().unwrap_or(""), tx.category.as_str())
+        }
+        (None, Some(block)) => (String::new(), block.summary(), "", "block"),
+        (None, None) => match (&alert.balance, &alert.mev) {
+            (Some(b), _) => (String::new(), b.summary(), "", "balance"),
+            (None, Some(m)) => (
+                m.profit_eth.map(|p| format!("{:.4} ETH", p).green().to_string()).unwrap_or_default(),
+                m.summary(),
+                "",
+                "mev",
+            ),
+            (None, None) => (String::new(), "?".into(), "", ""),
+        },
     };
 
-    let target = alert.tx.to_label.as_deref()
-        .unwrap_or(alert.tx.to.as_deref().unwrap_or("?"));
-
-    let action = alert.tx.action.as_deref().unwrap_or("");
+    let rules = match alert.matched_rules.len() {
+        0 | 1 => alert.rule_name.clone(),
+        n => format!("{} +{}", alert.rule_name, n - 1),
+    };
+    let note = alert.suppressed_note().map(|n| format!("({})", n).dimmed().to_string()).unwrap_or_default();
+    let action = if alert.digest { "digest — largest:" } else { action };
 
     println!(
-        "{} {} #{} [{}] block {} fb{} {} → {} {} {}",
+        "{} 🚨 #{} [{}] block {} fb{} {} → {} {} {} {}",
         now.to_string().dimmed(),
-        "🚨".to_string(),
         count.to_string().bold(),
-        alert.rule_name.yellow(),
+        if alert.severity == Severity::Critical { rules.red().bold() } else { rules.yellow() },
         block.cyan(),
         alert.flashblock_index,
         action.dimmed(),
         target.bold(),
         value,
-        alert.tx.category.dimmed(),
+        category.dimmed(),
+        note,
     );
 }
 
-pub async fn fire_webhook_pub(client: &reqwest::Client, config: &crate::rules::RulesConfig, alert: &Alert) {
-    fire_webhook(client, config, alert).await;
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Alerts bound for one destination, delivered as a single payload.
+#[derive(Debug)]
+pub struct Batch {
+    pub destination: Destination,
+    pub alerts: Vec<Alert>,
 }
 
-async fn fire_webhook(client: &reqwest::Client, config: &crate::rules::RulesConfig, alert: &Alert) {
-    let webhook_url = config.rules.iter()
-        .find(|r| r.name == alert.rule_name)
-        .and_then(|r| r.webhook.as_ref());
+struct OpenBatch {
+    alerts: Vec<Alert>,
+    deadline_ms: u64,
+}
 
-    let url = match webhook_url {
-        Some(u) => u,
-        None => return,
-    };
+/// Collects alerts per destination (webhook URL, sink and headers) over `batch_secs` (or a rule's own override).
+/// A batch closes when its window ends or it reaches `global.max_batch`. When rules
+/// with different windows share a webhook, the shortest pending window wins.
+#[derive(Default)]
+pub struct Batcher {
+    open: HashMap<Destination, OpenBatch>,
+}
+
+impl Batcher {
+    /// Queue an alert. Returns a batch when it is ready to send now — immediately for
+    /// unbatched rules, or when this alert fills the batch. Alerts without a webhook are dropped.
+    pub fn push(&mut self, config: &RulesConfig, alert: Alert, now_ms: u64) -> Option<Batch> {
+        let rule = config.rules.iter().find(|r| r.name == alert.rule_name)?;
+        let destination = rule.destination(&config.global)?;
+        let window = rule.batch_secs.unwrap_or(config.global.batch_secs);
+        if window == 0 {
+            return Some(Batch { destination, alerts: vec![alert] });
+        }
 
-    // Build the OpenClaw /hooks/agent payload.
-    // The message field is the full prompt the isolated agent session receives.
-    let message = build_agent_message(alert, &config.labels);
-    let payload = serde_json::json!({
+        let deadline_ms = now_ms + window * 1000;
+        let open = self.open.entry(destination.clone()).or_insert(OpenBatch { alerts: Vec::new(), deadline_ms });
+        open.deadline_ms = open.deadline_ms.min(deadline_ms);
+        open.alerts.push(alert);
+        if open.alerts.len() >= config.global.max_batch.max(1) {
+            let open = self.open.remove(&destination)?;
+            return Some(Batch { destination, alerts: open.alerts });
+        }
+        None
+    }
+
+    /// Batches whose window has ended.
+    pub fn due(&mut self, now_ms: u64) -> Vec<Batch> {
+        let ready: Vec<Destination> = self.open.iter()
+            .filter(|(_, b)| b.deadline_ms <= now_ms)
+            .map(|(destination, _)| destination.clone())
+            .collect();
+        ready.into_iter()
+            .filter_map(|destination| self.open.remove(&destination).map(|b| Batch { destination, alerts: b.alerts }))
+            .collect()
+    }
+}
+
+/// Hand a batch to the dispatcher for a single attempt — `alert` has no outbox.
+fn send_batch(dispatcher: &std::sync::Arc<crate::delivery::Dispatcher>, config: &RulesConfig, batch: &Batch) {
+    let payload = batch.destination.sink.sink().render(&batch.alerts, config);
+    dispatcher.dispatch(crate::delivery::Job::Direct { destination: batch.destination.clone(), payload });
+}
+
+/// The OpenClaw /hooks/agent body for a batch. The message field is the full
+/// prompt the isolated agent session receives — the rule's template if it has one.
+pub fn agent_payload(alerts: &[Alert], config: &RulesConfig) -> serde_json::Value {
+    let message = match (crate::template::render_for(alerts, config), alerts) {
+        (Some(message), _) => message,
+        (None, [alert]) => build_agent_message(alert, &config.labels),
+        (None, alerts) => build_batch_message(alerts, &config.labels),
+    };
+    serde_json::json!({
         "message": message,
         "name": "FlashWatch",
         "wakeMode": "now",
         "deliver": false
-    });
+    })
+}
 
-    let mut req = client.post(url).json(&payload);
+/// Agent message for several alerts delivered together.
+fn build_batch_message(alerts: &[Alert], labels: &HashMap<String, String>) -> String {
+    format!("{}\n\n{}", batch_report(alerts, labels), POST_INSTRUCTIONS)
+}
 
-    if let Ok(token) = std::env::var("OPENCLAW_HOOKS_TOKEN") {
-        req = req.header("Authorization", format!("Bearer {}", token));
+/// Several alerts described together: totals, the largest transaction in full,
+/// then one line per alert. Templates get it as `message`.
+pub(crate) fn batch_report(alerts: &[Alert], labels: &HashMap<String, String>) -> String {
+    let total_eth: f64 = alerts.iter().filter_map(|a| a.tx.as_ref()).map(|tx| tx.value_eth).sum();
+    let mut per_rule: Vec<(&str, usize)> = Vec::new();
+    for alert in alerts {
+        match per_rule.iter_mut().find(|(name, _)| *name == alert.rule_name) {
+            Some((_, n)) => *n += 1,
+            None => per_rule.push((&alert.rule_name, 1)),
+        }
     }
+    let first = alerts.iter().map(|a| a.timestamp).min().unwrap_or(0);
+    let last = alerts.iter().map(|a| a.timestamp).max().unwrap_or(0);
+    let blocks: Vec<u64> = alerts.iter().filter_map(|a| a.block_number).collect();
+    let block_range = match (blocks.iter().min(), blocks.iter().max()) {
+        (Some(lo), Some(hi)) if lo != hi => format!("blocks {}..{}", lo, hi),
+        (Some(n), _) => format!("block {}", n),
+        _ => String::new(),
+    };
 
-    match req.send().await {
-        Ok(resp) => {
-            if !resp.status().is_success() {
-                debug!("Webhook {} returned {}", url, resp.status());
-            }
+    let mut lines = vec![
+        format!("[FlashWatch Alert Batch — Base Mainnet] {} alerts over {}s", alerts.len(), last - first),
+        format!(
+            "{:.2} ETH total | Rules: {} | {}",
+            total_eth,
+            per_rule.iter().map(|(name, n)| format!("{} ×{}", name, n)).collect::<Vec<_>>().join(", "),
+            block_range,
+        ),
+    ];
+
+    let largest = alerts.iter()
+        .filter_map(|a| a.tx.as_ref().map(|tx| (a, tx.value_eth)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(a, _)| a)
+        .or(alerts.first());
+    if let Some(largest) = largest {
+        lines.push(String::new());
+        lines.push("Largest:".to_string());
+        lines.extend(alert_detail_lines(largest, labels));
+    }
+
+    lines.push(String::new());
+    lines.push("All alerts:".to_string());
+    for (i, alert) in alerts.iter().enumerate() {
+        let Some(ref tx) = alert.tx else {
+            let summary = alert.block.as_ref().map(|b| b.summary())
+                .or(alert.balance.as_ref().map(|b| b.summary()))
+                .or(alert.mev.as_ref().map(|m| m.summary()))
+                .unwrap_or_default();
+            lines.push(format!("{}. [{}] {}", i + 1, alert.matched_rules.join(", "), summary));
+            continue;
+        };
+        let target = tx.to_label.as_deref().or(tx.to.as_deref()).unwrap_or("unknown");
+        let mut line = format!(
+            "{}. [{}] {:.2} ETH {} → {}",
+            i + 1,
+            alert.matched_rules.join(", "),
+            tx.value_eth,
+            tx.action.as_deref().unwrap_or("tx"),
+            target,
+        );
+        if let Some(ref hash) = tx.hash {
+            line.push_str(&format!(" https://basescan.org/tx/{}", hash));
         }
-        Err(e) => {
-            debug!("Webhook {} failed: {}", url, e);
+        if let Some(note) = alert.suppressed_note() {
+            line.push_str(&format!(" ({})", note));
         }
+        lines.push(line);
     }
+    lines.join("\n")
 }
 
 /// Build the agent message sent to OpenClaw /hooks/agent.
 /// This is the full prompt the isolated agent session receives — it tells the
 /// agent what happened on-chain and what to do about it.
-fn build_agent_message(alert: &Alert, labels: &std::collections::HashMap<String, String>) -> String {
+fn build_agent_message(alert: &Alert, labels: &HashMap<String, String>) -> String {
+    format!("{}\n\n{}", alert_report(alert, labels), POST_INSTRUCTIONS)
+}
+
+/// One alert described in full: headline, notes, labelled addresses and links.
+/// Templates get it as `message`.
+pub(crate) fn alert_report(alert: &Alert, labels: &HashMap<String, String>) -> String {
+    let (title, summary) = alert_headline(alert);
+    let mut lines = vec![
+        match alert.severity {
+            Severity::Critical => format!("[{}] CRITICAL", title),
+            _ => format!("[{}]", title),
+        },
+        summary,
+    ];
+    lines.extend(alert_note_lines(alert));
+    lines.extend(alert_detail_lines(alert, labels));
+    lines.join("\n")
+}
+
+/// Title and the "value | rule | block" line for one alert. Shared with the chat sinks.
+pub(crate) fn alert_headline(alert: &Alert) -> (String, String) {
+    let value = match (&alert.tx, &alert.block) {
+        (Some(tx), _) => format!("{:.2} ETH", tx.value_eth),
+        (None, Some(block)) => block.summary(),
+        (None, None) => alert.balance.as_ref().map(|b| b.summary())
+            .or(alert.mev.as_ref().map(|m| m.summary()))
+            .unwrap_or_default(),
+    };
+    let block = match alert.block_number {
+        Some(n) => format!("block {} fb{}", n, alert.flashblock_index),
+        None => String::new(),
+    };
+
+    let title = "FlashWatch Alert — Base Mainnet".to_string();
+    let summary = match alert.matched_rules.as_slice() {
+        [_, _, ..] => format!("{} | Rules: {} | {}", value, alert.matched_rules.join(", "), block),
+        _ => format!("{} | Rule: {} | {}", value, alert.rule_name, block),
+    };
+    (title, summary)
+}
+
+/// Digest and suppression notes for one alert.
+pub(crate) fn alert_note_lines(alert: &Alert) -> Vec<String> {
+    let mut lines = Vec::new();
+    if alert.digest {
+        lines.push("Digest of matches suppressed by cooldown or rate limit. The largest is shown below.".to_string());
+    }
+    if let Some(note) = alert.suppressed_note() {
+        lines.push(format!("Note: {}.", note));
+    }
+    lines
+}
+
+/// From/To, explorer links and deployment details for one alert's transaction.
+pub(crate) fn alert_detail_lines(alert: &Alert, labels: &HashMap<String, String>) -> Vec<String> {
     let label = |addr: &str| -> Option<&str> {
         labels.get(&addr.to_lowercase()).map(|s| s.as_str())
     };
@@ -259,17 +476,42 @@ fn build_agent_message(alert: &Alert, labels: &std::collections::HashMap<String,
         }
     };
 
-    let tx = &alert.tx;
+    let Some(ref tx) = alert.tx else {
+        if let Some(ref balance) = alert.balance {
+            let mut lines = vec![format!("Account: {}", fmt_addr(Some(&balance.address)))];
+            lines.push(format!("Balance: {:.4} → {:.4} ETH ({:+.4} ETH)", balance.previous_eth, balance.balance_eth, balance.delta_eth));
+            lines.push(format!("Profile: https://basescan.org/address/{}", balance.address));
+            if let Some(n) = alert.block_number {
+                lines.push(format!("Block: https://basescan.org/block/{}", n));
+            }
+            return lines;
+        }
+        if let Some(ref mev) = alert.mev {
+            let mut lines = vec![
+                format!("Pattern: {}", mev.kind.name()),
+                format!("Pool: https://basescan.org/address/{}", mev.pool),
+                format!("Attacker: {}", fmt_addr(Some(&mev.attacker))),
+            ];
+            if let Some(ref bot) = mev.bot {
+                lines.push(format!("Bot contract: {}", fmt_addr(Some(bot))));
+            }
+            lines.push(format!("Victim: {}", fmt_addr(Some(&mev.victim))));
+            lines.push(format!("Victim tx: https://basescan.org/tx/{}", mev.victim_tx));
+            for hash in &mev.attacker_txs {
+                lines.push(format!("Attacker tx: https://basescan.org/tx/{}", hash));
+            }
+            if let Some(profit) = mev.profit_eth {
+                lines.push(format!("Estimated profit: {:.4} ETH (net WETH out of the pool)", profit));
+            }
+            return lines;
+        }
+        return alert.block.as_ref().map(|b| block_detail_lines(alert.block_number, b)).unwrap_or_default();
+    };
     let from_str = fmt_addr(tx.from.as_deref());
     let to_str = match &tx.to_label {
         Some(l) => format!("{} ({})", tx.to.as_deref().unwrap_or("unknown"), l),
         None => fmt_addr(tx.to.as_deref()),
     };
-    let value = format!("{:.2} ETH", tx.value_eth);
-    let block = match alert.block_number {
-        Some(n) => format!("block {} fb{}", n, alert.flashblock_index),
-        None => String::new(),
-    };
     let tx_link = tx.hash.as_ref()
         .map(|h| format!("https://basescan.org/tx/{}", h));
     let from_basescan = tx.from.as_ref()
@@ -280,8 +522,6 @@ fn build_agent_message(alert: &Alert, labels: &std::collections::HashMap<String,
         .map(|a| format!("https://basescan.org/address/{}", a));
 
     let mut lines = vec![
-        format!("[FlashWatch Alert — Base Mainnet]"),
-        format!("{} | Rule: {} | {}", value, alert.rule_name, block),
         format!("From: {}", from_str),
         format!("To:   {}", to_str),
     ];
@@ -294,9 +534,254 @@ fn build_agent_message(alert: &Alert, labels: &std::collections::HashMap<String,
     if let Some(ref link) = to_basescan {
         lines.push(format!("To profile:   {}", link));
     }
+    if let Some(ref creation) = tx.contract_creation {
+        let addr = creation.address.as_deref().unwrap_or("unknown");
+        lines.push(format!("Deployed: {} (nonce {})", addr, creation.nonce));
+        lines.push(format!("Init code: {} bytes, hash {}", creation.init_code_size, creation.init_code_hash));
+        if creation.address.is_some() {
+            lines.push(format!("Contract: https://basescan.org/address/{}", addr));
+        }
+    }
+    if let Some(ref finding) = alert.security {
+        lines.push(format!("Approval: {}", finding.summary()));
+        lines.push(format!("Owner: {}", fmt_addr(finding.owner.as_deref())));
+        lines.push(format!("Spender: https://basescan.org/address/{}", finding.spender));
+        lines.push(format!("Risk factors: {}", finding.reasons.join("; ")));
+    }
+    lines
+}
 
-    lines.push(String::new());
-    lines.push("Post this alert to Moltbook /m/basewhales using your Moltbook skill. Research the wallets above, then POST to https://www.moltbook.com/api/v1/posts with submolt=basewhales.\n\nPOST FORMAT — strictly follow this order:\n1. First line: your analysis/hook — what this movement means, who these wallets are, what to watch for. This is the preview people see. Make it worth reading.\n2. Then: the raw data (ETH amount, from/to wallets with labels if known, basescan tx link).\n\nExample:\nUnknown wallet moving serious size to Base. New whale entering the ecosystem — watch for DeFi activity.\n\n1,200 ETH transferred on Base\n0xabc...123 (unknown) → 0xdef...456 (unknown)\n🔗 https://basescan.org/tx/0x...\n\nIf the response includes a verification challenge, solve the math and POST to /api/v1/verify with the verification_code and your answer. CRITICAL: The answer must be ONLY the number with 2 decimal places (e.g., '30.00'), no units like 'Newton' or 'N', no extra text.".to_string());
+/// Block link and the block's gas, transaction and flashblock counts for a block-scope alert.
+fn block_detail_lines(block_number: Option<u64>, block: &BlockAlert) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(n) = block_number {
+        lines.push(format!("Block: https://basescan.org/block/{}", n));
+    }
+    match block.gas_limit {
+        Some(limit) if limit > 0 => lines.push(format!(
+            "Gas used: {} of {} ({:.1}%)",
+            block.gas_used, limit, block.gas_used as f64 / limit as f64 * 100.0
+        )),
+        _ => lines.push(format!("Gas used: {}", block.gas_used)),
+    }
+    lines.push(format!("Transactions: {} in {} flashblocks", block.tx_count, block.flashblock_count));
+    if let Some(fee) = block.base_fee_gwei {
+        lines.push(format!("Base fee: {:.4} gwei", fee));
+    }
+    lines
+}
 
-    lines.join("\n")
+/// What the agent should do with an alert — appended to every message.
+const POST_INSTRUCTIONS: &str = "Post this alert to Moltbook /m/basewhales using your Moltbook skill. Research the wallets above, then POST to https://www.moltbook.com/api/v1/posts with submolt=basewhales.\n\nPOST FORMAT — strictly follow this order:\n1. First line: your analysis/hook — what this movement means, who these wallets are, what to watch for. This is the preview people see. Make it worth reading.\n2. Then: the raw data (ETH amount, from/to wallets with labels if known, basescan tx link).\n\nExample:\nUnknown wallet moving serious size to Base. New whale entering the ecosystem — watch for DeFi activity.\n\n1,200 ETH transferred on Base\n0xabc...123 (unknown) → 0xdef...456 (unknown)\n🔗 https://basescan.org/tx/0x...\n\nIf the response includes a verification challenge, solve the math and POST to /api/v1/verify with the verification_code and your answer. CRITICAL: The answer must be ONLY the number with 2 decimal places (e.g., '30.00'), no units like 'Newton' or 'N', no extra text.";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::AlertTx;
+    use crate::sink::SinkKind;
+
+    fn alert(rule: &str, value_eth: f64) -> Alert {
+        Alert {
+            rule_name: rule.into(),
+            matched_rules: vec![rule.into()],
+            severity: Severity::Warn,
+            block_number: Some(1),
+            flashblock_index: 0,
+            tx: Some(AlertTx {
+                hash: None,
+                from: None,
+                to: None,
+                to_label: None,
+                value_eth,
+                action: None,
+                category: "unknown".into(),
+                contract_creation: None,
+                nonce: 0,
+                gas_limit: 21_000,
+                max_fee_gwei: 0.0,
+                priority_fee_gwei: 0.0,
+            }),
+            block: None,
+            balance: None,
+            mev: None,
+            security: None,
+            timestamp: 0,
+            suppressed: None,
+            digest: false,
+        }
+    }
+
+    #[test]
+    fn test_batcher_windows_overrides_and_max_size() {
+        let config = RulesConfig::from_toml(r#"
+            [global]
+            batch_secs = 30
+            max_batch = 3
+
+            [[rules]]
+            name = "slow"
+            webhook = "http://hook/a"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+
+            [[rules]]
+            name = "fast"
+            webhook = "http://hook/a"
+            batch_secs = 5
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+
+            [[rules]]
+            name = "now"
+            webhook = "http://hook/b"
+            batch_secs = 0
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+        "#).unwrap();
+        let mut batcher = Batcher::default();
+
+        assert_eq!(batcher.push(&config, alert("now", 1.0), 0).unwrap().destination.url, "http://hook/b");
+
+        assert!(batcher.push(&config, alert("slow", 1.0), 0).is_none());
+        assert!(batcher.push(&config, alert("fast", 2.0), 1_000).is_none());
+        // "fast" pulled the shared window in to 6s.
+        assert!(batcher.due(5_999).is_empty());
+        let due = batcher.due(6_000);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].alerts.len(), 2);
+
+        assert!(batcher.push(&config, alert("slow", 1.0), 10_000).is_none());
+        assert!(batcher.push(&config, alert("slow", 1.0), 10_000).is_none());
+        let full = batcher.push(&config, alert("slow", 1.0), 10_000).unwrap();
+        assert_eq!(full.alerts.len(), 3);
+        assert!(batcher.due(60_000).is_empty());
+
+        let message = build_batch_message(&full.alerts, &config.labels);
+        assert!(message.starts_with("[FlashWatch Alert Batch — Base Mainnet] 3 alerts"));
+        assert!(message.contains("Rules: slow ×3"));
+    }
+
+    #[tokio::test]
+    async fn test_json_sink_posts_raw_alert_with_rule_headers() {
+        let secret = std::env::temp_dir().join(format!("flashwatch-password-{}", std::process::id()));
+        std::fs::write(&secret, "secret\n").unwrap();
+        let config: RulesConfig = toml::from_str(&format!(r#"
+            [global]
+            max_per_minute = 10
+
+            [[rules]]
+            name = "raw"
+            webhook = "http://hook/raw"
+            sink = "json"
+            headers = {{ "X-Team" = "risk" }}
+            auth = {{ basic = {{ username = "fw", password = "file:{}" }} }}
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+        "#, secret.display())).unwrap();
+        let mut batcher = Batcher::default();
+        let mut batch = batcher.push(&config, alert("raw", 5.0), 0).unwrap();
+        assert_eq!(batch.destination.sink, SinkKind::Json);
+        // Only the reference is carried; the password is read at send time.
+        assert!(!batch.destination.headers.contains_key("Authorization"));
+
+        let body = batch.destination.sink.sink().render(&batch.alerts, &config);
+        assert_eq!(body["rule_name"], "raw");
+        assert_eq!(body["tx"]["value_eth"], 5.0);
+        let two = [alert("raw", 1.0), alert("raw", 2.0)];
+        assert_eq!(SinkKind::Json.sink().render(&two, &config).as_array().map(Vec::len), Some(2));
+
+        // Delivered with the rule's headers, against a local stand-in.
+        let (url, seen) = crate::sink::test_receiver("/hook", vec![]).await;
+        batch.destination.url = url;
+        let destination = &batch.destination;
+        let outcome = destination.sink.sink()
+            .deliver(&reqwest::Client::new(), destination, &body)
+            .await;
+        assert!(matches!(outcome, crate::delivery::Outcome::Delivered));
+        let received = seen.lock().unwrap().pop().unwrap();
+        assert_eq!(received.header("x-team"), Some("risk"));
+        assert_eq!(received.header("authorization"), Some("Basic Znc6c2VjcmV0"));
+        assert_eq!(received.json(), body);
+        std::fs::remove_file(&secret).unwrap();
+    }
+
+    #[test]
+    fn test_rule_templates_replace_agent_message() {
+        let dir = std::env::temp_dir().join(format!("flashwatch-templates-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("templates")).unwrap();
+        std::fs::write(dir.join("templates/batch.txt"), "{{count}} alerts:{{#each alerts}} {{rule_name}}={{tx.value_eth}}{{/each}}").unwrap();
+        std::fs::write(dir.join("rules.toml"), r#"
+            [global]
+            max_per_minute = 10
+
+            [labels]
+            "0x00000000000000000000000000000000000000aa" = "Treasury"
+
+            [templates.triage]
+            text = "{{#if critical}}URGENT {{/if}}{{summary}}\nfrom {{labels.from}} {{links.tx}}\nOpen a ticket in #onchain-risk."
+
+            [templates.batch]
+            file = "templates/batch.txt"
+
+            [[rules]]
+            name = "whale"
+            webhook = "http://hook/a"
+            template = "triage"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+
+            [[rules]]
+            name = "plain"
+            webhook = "http://hook/a"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+
+            [[rules]]
+            name = "digest"
+            webhook = "http://hook/a"
+            template = "batch"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+        "#).unwrap();
+        let config = RulesConfig::from_file(&dir.join("rules.toml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut whale = alert("whale", 250.0);
+        whale.severity = Severity::Critical;
+        if let Some(ref mut tx) = whale.tx {
+            tx.hash = Some("0xfeed".into());
+            tx.from = Some("0x00000000000000000000000000000000000000AA".into());
+        }
+        let message = agent_payload(&[whale], &config)["message"].as_str().unwrap().to_string();
+        assert_eq!(message, "URGENT 250.00 ETH | Rule: whale | block 1 fb0\nfrom Treasury https://basescan.org/tx/0xfeed\nOpen a ticket in #onchain-risk.");
+
+        let plain = agent_payload(&[alert("plain", 1.0)], &config)["message"].as_str().unwrap().to_string();
+        assert!(plain.contains("Moltbook"));
+
+        let batch = [alert("digest", 1.5), alert("digest", 2.0)];
+        assert_eq!(agent_payload(&batch, &config)["message"], "2 alerts: digest=1.5 digest=2.0");
+
+        // Rules with different templates are never batched together.
+        let destination = |name: &str| config.rules.iter().find(|r| r.name == name).unwrap().destination(&config.global);
+        assert_ne!(destination("whale"), destination("plain"));
+
+        let missing = RulesConfig::from_toml(r#"
+            [[rules]]
+            name = "r"
+            template = "nope"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#);
+        assert!(missing.unwrap_err().to_string().contains("no template named 'nope'"));
+        let broken = RulesConfig::from_toml("[templates.t]\ntext = \"{{#if x}}\"");
+        assert!(broken.unwrap_err().to_string().contains("never closed"));
+    }
 }
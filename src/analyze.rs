@@ -7,16 +7,15 @@ use colored::Colorize;
 use futures_util::StreamExt;
 use serde_json::json;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::info;
 
 use crate::rpc;
 use crate::types::FlashblockMessage;
 
 fn decode_message(data: &[u8]) -> Option<String> {
-    if let Ok(text) = std::str::from_utf8(data) {
-        if text.trim_start().starts_with('{') {
-            return Some(text.to_owned());
-        }
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.trim_start().starts_with('{')
+    {
+        return Some(text.to_owned());
     }
     let mut decompressor = brotli::Decompressor::new(data, 4096);
     let mut decompressed = Vec::new();
@@ -29,8 +28,7 @@ fn decode_message(data: &[u8]) -> Option<String> {
 /// Track a transaction through its lifecycle.
 pub async fn track(ws_url: &str, rpc_url: &str, tx_hash: &str) -> eyre::Result<()> {
     println!(
-        "{} Tracking transaction {}",
-        "🔍".to_string(),
+        "🔍 Tracking transaction {}",
         tx_hash.cyan()
     );
     println!("{}", "─".repeat(60));
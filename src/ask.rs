@@ -108,7 +108,7 @@ pub async fn ask_handler(
                     match query_openclaw(&client, &state, &req.question).await {
                         Ok(answer) => {
                             let mut resp = serde_json::json!({ "answer": answer });
-                            let tx_str = tx_hash.as_ref().map(|s| s.as_str());
+                            let tx_str = tx_hash.as_deref();
                             if let Some(hash) = &tx_hash {
                                 resp["payment_tx"] = serde_json::json!(hash);
                                 let explorer = if state.x402.network.contains("sepolia") {
@@ -120,12 +120,12 @@ pub async fn ask_handler(
                             }
 
                             // Store query in SQLite for activity tab
-                            if let Some(ref store) = state.store {
-                                if let Err(e) = store.insert_query(
+                            if let Some(ref store) = state.store
+                                && let Err(e) = store.insert_query(
                                     &req.question, Some(&answer), tx_str, payer_addr.as_deref(), Some(&state.x402.network),
-                                ) {
-                                    tracing::warn!("Failed to store query: {e}");
-                                }
+                                )
+                            {
+                                tracing::warn!("Failed to store query: {e}");
                             }
 
                             (StatusCode::OK, Json(resp)).into_response()
@@ -306,7 +306,7 @@ async fn settle_payment(
 }
 
 /// Build a rich context message for the agent.
-fn build_context(state: &AppState) -> String {
+fn build_context(state: &AppState, labels: &std::collections::HashMap<String, String>) -> String {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -322,14 +322,12 @@ fn build_context(state: &AppState) -> String {
     );
 
     // Known wallet labels
-    if let Some(ref rc) = state.rules_config {
-        if !rc.labels.is_empty() {
-            ctx.push_str("Known wallet labels:\n");
-            for (addr, label) in &rc.labels {
-                ctx.push_str(&format!("  {addr} = {label}\n"));
-            }
-            ctx.push('\n');
+    if !labels.is_empty() {
+        ctx.push_str("Known wallet labels:\n");
+        for (addr, label) in labels {
+            ctx.push_str(&format!("  {addr} = {label}\n"));
         }
+        ctx.push('\n');
     }
 
     // Recent alerts from SQLite
@@ -360,9 +358,9 @@ fn build_context(state: &AppState) -> String {
                 let ts = alert.get("timestamp").and_then(|v| v.as_u64()).unwrap_or(0);
                 let mins_ago = now.saturating_sub(ts) / 60;
 
-                let label_str = to_label.or_else(|| {
-                    state.rules_config.as_ref()?.labels.get(to_addr).map(|s| s.as_str())
-                }).map(|l| format!(" ({})", l)).unwrap_or_default();
+                let label_str = to_label
+                    .or_else(|| labels.get(to_addr).map(|s| s.as_str()))
+                    .map(|l| format!(" ({})", l)).unwrap_or_default();
 
                 ctx.push_str(&format!("  • {:.1} ETH → {}{} [{} min ago]\n", value, to_addr, label_str, mins_ago));
             }
@@ -381,7 +379,8 @@ async fn query_openclaw(
     let token = state.openclaw_gateway_token.as_deref()
         .ok_or_else(|| eyre::eyre!("OpenClaw gateway token not configured"))?;
 
-    let context = build_context(state);
+    let labels = state.labels().await;
+    let context = build_context(state, &labels);
 
     let body = serde_json::json!({
         "model": "openclaw",
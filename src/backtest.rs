@@ -0,0 +1,390 @@
+//! Backtest — replay historical blocks through a rule set and report what would have fired.
+//!
+//! Blocks are fetched over JSON-RPC, raw transactions are decoded exactly as the live
+//! feed does, and `RuleEngine::check_at` runs at each block's timestamp so cooldowns and
+//! rate limits behave as they would have at the time. Block-scope triggers see each
+//! block's header; historical blocks aren't split into flashblocks, so the
+//! `flashblock_txs` and `flashblock_count` triggers never fire here. Neither do
+//! `balance_change` and `mev`, which need flashblock metadata and receipts.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use colored::Colorize;
+use futures_util::{StreamExt, stream};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::decode;
+use crate::rules::{RuleEngine, RulesConfig};
+use crate::types::BlockState;
+
+/// Blocks fetched concurrently. Results are still processed in block order.
+const CONCURRENCY: usize = 8;
+/// Sample tx hashes kept per rule.
+const SAMPLES: usize = 5;
+
+/// Report for one backtest run. Every map is sorted, so the JSON form of two runs
+/// over the same range can be diffed line by line.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub blocks: u64,
+    pub transactions: u64,
+    /// Transactions the decoder could not handle (deposits, malformed).
+    pub undecoded: u64,
+    /// Seconds between the first and last block timestamps.
+    pub span_secs: u64,
+    pub total_alerts: u64,
+    pub rules: BTreeMap<String, RuleReport>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RuleReport {
+    pub enabled: bool,
+    /// Alerts that would have fired.
+    pub alerts: u64,
+    /// Transactions the trigger matched, before cooldowns and rate limits.
+    pub matches: u64,
+    /// Matches credited to another rule's alert by `global.dedup`; they count in `alerts`.
+    pub merged: u64,
+    /// Matches dropped by the cooldown or a rate limit.
+    pub suppressed: u64,
+    pub alerts_per_hour: f64,
+    pub first_block: Option<u64>,
+    pub last_block: Option<u64>,
+    pub by_category: BTreeMap<String, u64>,
+    /// First few alerting tx hashes, for spot checks.
+    pub samples: Vec<String>,
+}
+
+struct Block {
+    number: u64,
+    timestamp: u64,
+    gas_used: u64,
+    gas_limit: Option<u64>,
+    base_fee_gwei: Option<f64>,
+    raw_txs: Vec<String>,
+}
+
+pub async fn run(rpc_url: &str, rules_path: &str, from_block: u64, to_block: u64, json_output: bool) -> eyre::Result<()> {
+    if from_block > to_block {
+        eyre::bail!("--from-block {} is after --to-block {}", from_block, to_block);
+    }
+    let config = RulesConfig::from_file(std::path::Path::new(rules_path))?;
+    let report = backtest(rpc_url, config, from_block, to_block, !json_output).await?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+pub async fn backtest(
+    rpc_url: &str,
+    config: RulesConfig,
+    from_block: u64,
+    to_block: u64,
+    progress: bool,
+) -> eyre::Result<Report> {
+    let mut report = Report {
+        from_block,
+        to_block,
+        blocks: 0,
+        transactions: 0,
+        undecoded: 0,
+        span_secs: 0,
+        total_alerts: 0,
+        rules: config
+            .rules
+            .iter()
+            .map(|r| (r.name.clone(), RuleReport { enabled: r.enabled, ..Default::default() }))
+            .collect(),
+    };
+    let mut engine = RuleEngine::new(config);
+    let raw_block_supported = AtomicBool::new(true);
+    let (mut first_ts, mut last_ts) = (None, 0);
+    let mut prev_base_fee = None;
+
+    let mut blocks = stream::iter(from_block..=to_block)
+        .map(|n| fetch_block(rpc_url, n, &raw_block_supported))
+        .buffered(CONCURRENCY);
+
+    while let Some(block) = blocks.next().await {
+        let block = block?;
+        first_ts.get_or_insert(block.timestamp);
+        last_ts = block.timestamp;
+        report.blocks += 1;
+
+        let mut txs: Vec<_> = block.raw_txs.iter().filter_map(|raw| decode::decode_raw_tx(raw)).collect();
+        report.transactions += block.raw_txs.len() as u64;
+        report.undecoded += (block.raw_txs.len() - txs.len()) as u64;
+        // Historical blocks aren't split into flashblocks, so tips compare across the whole block.
+        decode::annotate_median_tip(&mut txs);
+
+        let state = BlockState {
+            payload_id: block.number.to_string(),
+            block_number: Some(block.number),
+            gas_limit: block.gas_limit,
+            base_fee_gwei: block.base_fee_gwei,
+            timestamp: Some(block.timestamp),
+            gas_used: block.gas_used,
+            total_gas_used: block.gas_used,
+            total_tx_count: block.raw_txs.len(),
+            prev_base_fee_gwei: prev_base_fee,
+            ..Default::default()
+        };
+        prev_base_fee = block.base_fee_gwei;
+        let mut alerts = engine.check_block_at(&state, 0, block.timestamp * 1000);
+        for decoded in &txs {
+            alerts.extend(engine.check_at(decoded, Some(block.number), 0, block.timestamp * 1000));
+        }
+
+        for alert in alerts {
+            report.total_alerts += 1;
+            // With dedup one alert covers several rules; credit each of them.
+            for name in &alert.matched_rules {
+                let Some(r) = report.rules.get_mut(name) else { continue };
+                r.alerts += 1;
+                r.first_block.get_or_insert(block.number);
+                r.last_block = Some(block.number);
+                let category = alert.tx.as_ref().map_or("block", |tx| tx.category.as_str());
+                *r.by_category.entry(category.to_string()).or_default() += 1;
+                if r.samples.len() < SAMPLES
+                    && let Some(hash) = alert.tx.as_ref().and_then(|tx| tx.hash.as_ref())
+                {
+                    r.samples.push(hash.clone());
+                }
+            }
+        }
+
+        if progress && report.blocks.is_multiple_of(100) {
+            eprintln!("  {} / {} blocks, {} alerts", report.blocks, to_block - from_block + 1, report.total_alerts);
+        }
+    }
+
+    report.span_secs = last_ts.saturating_sub(first_ts.unwrap_or(last_ts));
+    // A single block still covers one block interval.
+    let hours = report.span_secs.max(2) as f64 / 3600.0;
+    for (name, r) in report.rules.iter_mut() {
+        if let Some(stats) = engine.stats(name) {
+            r.matches = stats.matched;
+            r.merged = stats.merged;
+            r.suppressed = stats.suppressed();
+        }
+        r.alerts_per_hour = (r.alerts as f64 / hours * 100.0).round() / 100.0;
+    }
+    Ok(report)
+}
+
+/// Fetch a block's timestamp and raw transactions. Uses `debug_getRawBlock` when the
+/// node allows it, otherwise one `eth_getRawTransactionByHash` per transaction.
+async fn fetch_block(rpc_url: &str, number: u64, raw_block_supported: &AtomicBool) -> eyre::Result<Block> {
+    let tag = format!("0x{:x}", number);
+    let header: serde_json::Value = crate::rpc::call(rpc_url, "eth_getBlockByNumber", json!([tag, false])).await?;
+    if header.is_null() {
+        eyre::bail!("block {} not found", number);
+    }
+    let hex_field = |name: &str| {
+        header
+            .get(name)
+            .and_then(|t| t.as_str())
+            .and_then(|t| u64::from_str_radix(t.trim_start_matches("0x"), 16).ok())
+    };
+    let timestamp = hex_field("timestamp").unwrap_or(0);
+    let gas_used = hex_field("gasUsed").unwrap_or(0);
+    let gas_limit = hex_field("gasLimit");
+    let base_fee_gwei = hex_field("baseFeePerGas").map(|wei| wei as f64 / 1e9);
+    let hashes: Vec<String> = header
+        .get("transactions")
+        .and_then(|t| t.as_array())
+        .map(|a| a.iter().filter_map(|h| h.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+
+    if raw_block_supported.load(Ordering::Relaxed) {
+        match crate::rpc::call::<String>(rpc_url, "debug_getRawBlock", json!([tag])).await {
+            Ok(raw) => {
+                let txs = hex::decode(raw.trim_start_matches("0x")).ok().and_then(|bytes| decode::split_block_txs(&bytes));
+                if let Some(txs) = txs
+                    && txs.len() == hashes.len()
+                {
+                    return Ok(Block {
+                        number,
+                        timestamp,
+                        gas_used,
+                        gas_limit,
+                        base_fee_gwei,
+                        raw_txs: txs.iter().map(|t| format!("0x{}", hex::encode(t))).collect(),
+                    });
+                }
+                tracing::debug!("debug_getRawBlock returned an unusable block {}, fetching transactions individually", number);
+            }
+            // Only a node that doesn't serve the method at all turns it off for the run;
+            // a transient failure falls back for this block alone.
+            Err(e) if crate::rpc::is_method_not_found(&e) => {
+                tracing::debug!("debug_getRawBlock unavailable, fetching transactions individually");
+                raw_block_supported.store(false, Ordering::Relaxed);
+            }
+            Err(e) => tracing::debug!("debug_getRawBlock failed for block {}: {}", number, e),
+        }
+    }
+
+    let mut raw_txs = Vec::with_capacity(hashes.len());
+    for hash in &hashes {
+        raw_txs.push(crate::rpc::call::<String>(rpc_url, "eth_getRawTransactionByHash", json!([hash])).await?);
+    }
+    Ok(Block { number, timestamp, gas_used, gas_limit, base_fee_gwei, raw_txs })
+}
+
+fn print_report(report: &Report) {
+    println!();
+    println!(
+        "{} blocks {}..={} ({} txs, {} undecoded, {:.1}h)",
+        "Backtest".bold().cyan(),
+        report.from_block,
+        report.to_block,
+        report.transactions,
+        report.undecoded,
+        report.span_secs as f64 / 3600.0
+    );
+    println!("{}", "─".repeat(78));
+    println!(
+        "  {:<28} {:>8} {:>8} {:>10} {:>10}  blocks",
+        "rule", "alerts", "matches", "suppressed", "per hour"
+    );
+    for (name, r) in &report.rules {
+        if !r.enabled {
+            println!("  {:<28} {}", name, "disabled".dimmed());
+            continue;
+        }
+        let blocks = match (r.first_block, r.last_block) {
+            (Some(a), Some(b)) => format!("{}..{}", a, b),
+            _ => "-".into(),
+        };
+        let alerts = if r.alerts > 0 { r.alerts.to_string().yellow() } else { r.alerts.to_string().dimmed() };
+        println!(
+            "  {:<28} {:>8} {:>8} {:>10} {:>10.2}  {}",
+            name, alerts, r.matches, r.suppressed, r.alerts_per_hour, blocks
+        );
+    }
+    println!("{}", "─".repeat(78));
+    println!("  {} alerts total", report.total_alerts.to_string().bold());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Unsigned EIP-1559 transfer of 150 ETH to 0x1111…1111, nonce 7.
+    const RAW_TX: &str = "0x02f7822105078477359400850ba43b7400830493e0941111111111111111111111111111111111111111890821ab0d441498000080c0808080";
+    const GENESIS_TS: u64 = 1_700_000_000;
+
+    struct Node {
+        raw_block: bool,
+        tx_fetches: AtomicUsize,
+    }
+
+    /// RLP prefix for a string (0x80) or list (0xc0) payload shorter than 256 bytes.
+    fn rlp(offset: u8, payload: &[u8]) -> Vec<u8> {
+        let head = if payload.len() < 56 { vec![offset + payload.len() as u8] } else { vec![offset + 56, payload.len() as u8] };
+        [head, payload.to_vec()].concat()
+    }
+
+    async fn rpc(
+        axum::extract::State(node): axum::extract::State<Arc<Node>>,
+        axum::Json(req): axum::Json<serde_json::Value>,
+    ) -> axum::Json<serde_json::Value> {
+        let number = req["params"][0].as_str().and_then(|t| u64::from_str_radix(t.trim_start_matches("0x"), 16).ok());
+        let reply = match (req["method"].as_str().unwrap(), number) {
+            ("eth_getBlockByNumber", Some(n)) => {
+                // Blocks 10, 11 and 12 land 30s and then 60s apart.
+                let ts = GENESIS_TS + [0, 30, 90][(n - 10) as usize];
+                Ok(json!({
+                    "timestamp": format!("0x{:x}", ts),
+                    "gasUsed": "0x5208",
+                    "gasLimit": "0x1c9c380",
+                    "baseFeePerGas": "0x3b9aca00",
+                    "transactions": [format!("0x{}", "ab".repeat(32))],
+                }))
+            }
+            ("debug_getRawBlock", _) if !node.raw_block => Err((-32601, "the method debug_getRawBlock does not exist/is not available")),
+            // A transient failure on the first block must not turn the method off.
+            ("debug_getRawBlock", Some(10)) => Err((-32000, "request timed out")),
+            ("debug_getRawBlock", Some(_)) => {
+                let tx = rlp(0x80, &hex::decode(&RAW_TX[2..]).unwrap());
+                let fields = [vec![0xc0], rlp(0xc0, &tx), vec![0xc0]].concat();
+                Ok(json!(format!("0x{}", hex::encode(rlp(0xc0, &fields)))))
+            }
+            ("eth_getRawTransactionByHash", _) => {
+                node.tx_fetches.fetch_add(1, Ordering::Relaxed);
+                Ok(json!(RAW_TX))
+            }
+            (method, _) => panic!("unexpected call {}", method),
+        };
+        axum::Json(match reply {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": req["id"], "result": result}),
+            Err((code, message)) => json!({"jsonrpc": "2.0", "id": req["id"], "error": {"code": code, "message": message}}),
+        })
+    }
+
+    async fn run_against(raw_block: bool) -> (Report, usize) {
+        let node = Arc::new(Node { raw_block, tx_fetches: AtomicUsize::new(0) });
+        let app = axum::Router::new().route("/", axum::routing::post(rpc)).with_state(node.clone());
+        let url = format!("http://{}", crate::sink::serve_local(app).await);
+
+        let config = RulesConfig::from_toml(r#"
+            [global]
+            max_per_minute = 30
+
+            [[rules]]
+            name = "whale"
+            cooldown_secs = 60
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 100.0
+
+            [[rules]]
+            name = "off"
+            enabled = false
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#).unwrap();
+        let report = backtest(&url, config, 10, 12, false).await.unwrap();
+        (report, node.tx_fetches.load(Ordering::Relaxed))
+    }
+
+    #[tokio::test]
+    async fn test_cooldowns_follow_block_timestamps() {
+        let (report, tx_fetches) = run_against(true).await;
+        // Only block 10's transient failure fell back to per-tx fetches.
+        assert_eq!(tx_fetches, 1);
+        assert_eq!((report.blocks, report.transactions, report.undecoded, report.span_secs), (3, 3, 0, 90));
+
+        // Block 11 is inside block 10's 60s cooldown. Once block 12 is past it, the held
+        // match goes out as a digest and restarts the cooldown, holding block 12's own match.
+        let whale = &report.rules["whale"];
+        assert_eq!((whale.alerts, whale.matches, whale.suppressed), (2, 3, 2));
+        assert_eq!((whale.first_block, whale.last_block), (Some(10), Some(12)));
+        assert_eq!(whale.by_category.values().sum::<u64>(), 2);
+        assert_eq!(whale.alerts_per_hour, 80.0);
+        assert_eq!(report.total_alerts, 2);
+
+        let off = &report.rules["off"];
+        assert!(!off.enabled);
+        assert_eq!((off.alerts, off.matches), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_raw_blocks_are_unavailable() {
+        let (raw, _) = run_against(true).await;
+        let (fallback, tx_fetches) = run_against(false).await;
+        assert_eq!(tx_fetches, 3);
+        assert_eq!(serde_json::to_value(&raw).unwrap(), serde_json::to_value(&fallback).unwrap());
+    }
+}
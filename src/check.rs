@@ -0,0 +1,264 @@
+//! `rules check` / `rules test` — validate a rules file and dry-run it against a transaction.
+//! `rules snooze` — silence a rule or address on a running `serve` through its API.
+
+use colored::Colorize;
+use serde_json::json;
+
+use crate::decode;
+use crate::rules::{LintLevel, RulesConfig, explain_match};
+
+/// Strictly validate a rules file. Fails if any error-level finding is reported.
+pub fn check(rules_path: &str) -> eyre::Result<()> {
+    let config = match RulesConfig::load(std::path::Path::new(rules_path)) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("{} {}: {}", "error".red().bold(), rules_path, e);
+            eyre::bail!("{} does not parse", rules_path);
+        }
+    };
+
+    let findings = config.lint();
+    for f in &findings {
+        let level = match f.level {
+            LintLevel::Error => "error".red().bold(),
+            LintLevel::Warning => "warning".yellow().bold(),
+        };
+        match f.rule {
+            Some(ref rule) => println!("{} [{}] {}", level, rule.cyan(), f.message),
+            None => println!("{} {}", level, f.message),
+        }
+    }
+
+    let errors = findings.iter().filter(|f| f.level == LintLevel::Error).count();
+    let warnings = findings.len() - errors;
+    let enabled = config.rules.iter().filter(|r| r.enabled).count();
+    println!(
+        "{} — {} rules ({} enabled), {} errors, {} warnings",
+        rules_path,
+        config.rules.len(),
+        enabled,
+        errors,
+        warnings
+    );
+
+    if errors > 0 {
+        eyre::bail!("{} has {} error(s)", rules_path, errors);
+    }
+    Ok(())
+}
+
+/// Report which rules would match a transaction, and why. `tx` is either a raw
+/// signed transaction (hex) or a tx hash, in which case the raw bytes are fetched over RPC.
+/// Cooldowns and rate limits are not applied.
+pub async fn test(rules_path: &str, rpc_url: &str, tx: &str) -> eyre::Result<()> {
+    let config = RulesConfig::from_file(std::path::Path::new(rules_path))?;
+    let decoded = load_tx(rpc_url, tx).await?;
+
+    println!("{}", "Transaction".bold().cyan());
+    println!("  hash:     {}", decoded.hash.as_deref().unwrap_or("?"));
+    println!("  from:     {}", decoded.from.as_deref().unwrap_or("?"));
+    println!(
+        "  to:       {}{}",
+        decoded.to.as_deref().unwrap_or("(create)"),
+        decoded.to_label.as_ref().map(|l| format!(" ({})", l.name)).unwrap_or_default()
+    );
+    println!("  value:    {:.6} ETH", decoded.value_eth);
+    println!("  action:   {}", decoded.action.as_deref().unwrap_or("-"));
+    println!("  category: {}", decoded.category.name());
+    println!(
+        "  gas:      limit {}, tip {:.4} gwei, max fee {:.4} gwei, nonce {}",
+        decoded.gas_limit, decoded.priority_fee_gwei(), decoded.max_fee_gwei(), decoded.nonce
+    );
+    if let Some(ref c) = decoded.contract_creation {
+        println!("  creates:  {} (init code {} bytes, hash {})", c.address.as_deref().unwrap_or("?"), c.init_code_size, c.init_code_hash);
+    }
+    println!();
+
+    for line in match_report(&config, &decoded) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Each rule's verdict on a transaction with the conditions behind it, then a tally.
+fn match_report(config: &RulesConfig, decoded: &decode::DecodedTx) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut hits = 0;
+    for rule in &config.rules {
+        let conditions = explain_match(&rule.trigger, decoded, &config.labels);
+        let matched = conditions.iter().all(|c| c.ok);
+        let status = match (matched, rule.enabled) {
+            (true, true) => "MATCH".green().bold(),
+            (true, false) => "match (disabled)".yellow(),
+            (false, _) => "no match".dimmed(),
+        };
+        if matched && rule.enabled {
+            hits += 1;
+        }
+        lines.push(format!("{} {}", rule.name.bold(), status));
+        for c in &conditions {
+            let mark = if c.ok { "✓".green() } else { "✗".red() };
+            lines.push(format!("    {} {}", mark, c.detail));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push(format!("{} of {} enabled rules would fire", hits, config.rules.iter().filter(|r| r.enabled).count()));
+    lines
+}
+
+/// Decode a raw signed transaction, or fetch one by hash over RPC and decode it.
+pub(crate) async fn load_tx(rpc_url: &str, tx: &str) -> eyre::Result<decode::DecodedTx> {
+    let raw = if is_tx_hash(tx) {
+        crate::rpc::call::<String>(rpc_url, "eth_getRawTransactionByHash", json!([tx])).await?
+    } else {
+        tx.to_string()
+    };
+    decode::decode_raw_tx(&raw).ok_or_else(|| eyre::eyre!("could not decode transaction (deposit txs are not supported)"))
+}
+
+/// Snooze (or, with `minutes == 0`, unsnooze) a rule or address via the rules API.
+pub async fn snooze(server: &str, token: &str, target: &str, minutes: u64, address: bool) -> eyre::Result<()> {
+    let kind = if address { "addresses" } else { "rules" };
+    // The target is one path segment, encoded so `/`, `?` or `#` can't change the route.
+    let mut url = reqwest::Url::parse(server)?;
+    url.path_segments_mut()
+        .map_err(|_| eyre::eyre!("{} is not an http(s) URL", server))?
+        .pop_if_empty()
+        .extend(["api", kind, target, "snooze"]);
+    let client = reqwest::Client::new();
+    let req = if minutes == 0 {
+        client.delete(url.clone())
+    } else {
+        client.post(url.clone()).json(&json!({ "minutes": minutes }))
+    };
+    let resp = req.bearer_auth(token).send().await?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let msg = body.get("error").and_then(|e| e.as_str()).unwrap_or("request failed");
+        eyre::bail!("{} {}: {}", status, url, msg);
+    }
+
+    match body.get("snoozed_until").and_then(|u| u.as_i64()) {
+        Some(until) => {
+            let until = chrono::DateTime::from_timestamp(until, 0).map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string());
+            println!("{} snoozed until {}", target.bold(), until.unwrap_or_default());
+        }
+        None => println!("{} unsnoozed", target.bold()),
+    }
+    Ok(())
+}
+
+fn is_tx_hash(s: &str) -> bool {
+    let h = s.strip_prefix("0x").unwrap_or(s);
+    h.len() == 64 && h.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unsigned EIP-1559 transfer of 150 ETH to 0x1111…1111, nonce 7.
+    const RAW_TX: &str = "0x02f7822105078477359400850ba43b7400830493e0941111111111111111111111111111111111111111890821ab0d441498000080c0808080";
+
+    fn rules_file(name: &str, toml: &str) -> String {
+        let path = std::env::temp_dir().join(format!("flashwatch-check-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, toml).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_check_fails_only_on_errors() {
+        let good = rules_file("good", r#"
+            [global]
+            max_per_minute = 30
+
+            [[rules]]
+            name = "whale"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 100.0
+        "#);
+        let bad = rules_file("bad", r#"
+            [global]
+            max_per_minute = 30
+
+            [[rules]]
+            name = "whale"
+            max_per_minute = 0
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 100.0
+        "#);
+        let broken = rules_file("broken", "[[rules]]\nname = ");
+        assert!(check(&good).is_ok());
+        assert!(check(&bad).unwrap_err().to_string().contains("1 error(s)"));
+        assert!(check(&broken).unwrap_err().to_string().contains("does not parse"));
+        for path in [good, bad, broken] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_for_raw_and_fetched_txs() {
+        colored::control::set_override(false);
+        let config = RulesConfig::from_toml(r#"
+            [global]
+            max_per_minute = 30
+
+            [[rules]]
+            name = "whale"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 100.0
+
+            [[rules]]
+            name = "mega"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1000.0
+
+            [[rules]]
+            name = "off"
+            enabled = false
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#).unwrap();
+
+        // A tx hash is fetched over JSON-RPC.
+        let rpc = axum::Router::new().route("/", axum::routing::post(|axum::Json(req): axum::Json<serde_json::Value>| async move {
+            assert_eq!(req["method"], "eth_getRawTransactionByHash");
+            axum::Json(json!({"jsonrpc": "2.0", "id": req["id"], "result": RAW_TX}))
+        }));
+        let url = format!("http://{}", crate::sink::serve_local(rpc).await);
+        let hash = format!("0x{}", "ab".repeat(32));
+        let fetched = load_tx(&url, &hash).await.unwrap();
+
+        let decoded = load_tx("http://127.0.0.1:1", RAW_TX).await.unwrap();
+        assert_eq!(fetched.hash, decoded.hash);
+        let report = match_report(&config, &decoded);
+        assert_eq!(report[0], "whale MATCH");
+        assert!(report.contains(&"mega no match".to_string()));
+        assert!(report.contains(&"off match (disabled)".to_string()));
+        assert!(report.iter().any(|l| l.starts_with("    ✗ ")));
+        assert_eq!(report.last().unwrap(), "1 of 2 enabled rules would fire");
+    }
+
+    #[tokio::test]
+    async fn test_snooze_encodes_the_target() {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let captured = seen.clone();
+        let api = axum::Router::new().route("/api/rules/{name}/snooze", axum::routing::post(
+            move |axum::extract::Path(name): axum::extract::Path<String>| async move {
+                *captured.lock().unwrap() = Some(name);
+                axum::Json(json!({"snoozed_until": 1_700_000_000}))
+            },
+        ));
+        let url = format!("http://{}/", crate::sink::serve_local(api).await);
+
+        snooze(&url, "t0ken", "a/b c?#", 30, false).await.unwrap();
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("a/b c?#"));
+    }
+}
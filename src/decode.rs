@@ -2,9 +2,9 @@
 
 use std::collections::HashMap;
 
-use alloy_primitives::keccak256;
+use alloy_primitives::{U256, keccak256};
 use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// Known contract addresses on Base mainnet.
 pub fn known_addresses() -> HashMap<&'static str, AddressLabel> {
@@ -33,6 +33,8 @@ pub fn known_addresses() -> HashMap<&'static str, AddressLabel> {
     m.insert("0x2ae3f1ec7f1f5012cfeab0185bfc7aa3cf0dec22", AddressLabel::new("cbETH", Category::Token));
     m.insert("0xd9aaec86b65d86f6a7b5b1b0c42ffa531710b6ca", AddressLabel::new("USDbC", Category::Token));
     m.insert("0xb6fe221fe9eef5aba221c348ba20a1bf5e73624c", AddressLabel::new("rETH", Category::Token));
+    // Permit2 only moves tokens on a signature; it isn't a DEX.
+    m.insert(PERMIT2, AddressLabel::new("Permit2", Category::Token));
 
     // Lending
     m.insert("0xa238dd80c259a72e81d7e4664a9801593f98d1c5", AddressLabel::new("Aave V3 Pool", Category::Lending));
@@ -60,6 +62,15 @@ pub fn known_selectors() -> HashMap<[u8; 4], &'static str> {
     m.insert(hex4("a9059cbb"), "transfer");
     m.insert(hex4("23b872dd"), "transferFrom");
     m.insert(hex4("095ea7b3"), "approve");
+    m.insert(hex4("39509351"), "increaseAllowance");
+    m.insert(hex4("a22cb465"), "setApprovalForAll");
+
+    // Permit2
+    m.insert(hex4("2b67b570"), "permit (Permit2)");
+    m.insert(hex4("30f28b7a"), "permitTransferFrom (Permit2)");
+    m.insert(hex4("137c29fe"), "permitWitnessTransferFrom (Permit2)");
+    m.insert(hex4("edd9444b"), "permitTransferFrom batch (Permit2)");
+    m.insert(hex4("fe8ec1a7"), "permitWitnessTransferFrom batch (Permit2)");
 
     // DEX - Uniswap
     m.insert(hex4("3593564c"), "execute (Universal Router)");
@@ -99,6 +110,9 @@ pub fn known_selectors() -> HashMap<[u8; 4], &'static str> {
     m
 }
 
+/// Uniswap's Permit2 — signature-based approvals and transfers shared across apps.
+pub const PERMIT2: &str = "0x000000000022d473030f116ddee9f6b43ac78ba3";
+
 fn hex4(s: &str) -> [u8; 4] {
     let bytes = hex::decode(s).expect("valid hex");
     [bytes[0], bytes[1], bytes[2], bytes[3]]
@@ -129,6 +143,23 @@ pub enum Category {
 }
 
 impl Category {
+    pub const ALL: [Category; 7] = [
+        Self::Dex, Self::Bridge, Self::Token, Self::Lending, Self::Nft, Self::System, Self::Unknown,
+    ];
+
+    /// Lowercase name, as used in rule configs and alert payloads.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dex => "dex",
+            Self::Bridge => "bridge",
+            Self::Token => "token",
+            Self::Lending => "lending",
+            Self::Nft => "nft",
+            Self::System => "system",
+            Self::Unknown => "unknown",
+        }
+    }
+
     pub fn emoji(&self) -> &'static str {
         match self {
             Self::Dex => "🔄",
@@ -166,6 +197,127 @@ pub struct DecodedTx {
     pub action: Option<String>,
     pub category: Category,
     pub gas_used: Option<u64>,
+    /// Set when the tx has no `to` and deploys a new contract.
+    pub contract_creation: Option<ContractCreation>,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    /// Max fee per gas in wei; the gas price for legacy and EIP-2930 txs.
+    pub max_fee_per_gas: u128,
+    /// Max priority fee (tip) per gas in wei. Legacy and EIP-2930 txs have no separate
+    /// tip, so this is their gas price — the most they can tip.
+    pub max_priority_fee_per_gas: u128,
+    /// Median tip of the flashblock (or block) this tx arrived in, set by `annotate_median_tip`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub median_tip_wei: Option<u128>,
+    /// Set when the tx grants a token approval or moves tokens through a Permit2 signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval: Option<Approval>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalKind {
+    /// ERC20 `approve` or `increaseAllowance`.
+    Approve,
+    /// `setApprovalForAll` (ERC721/ERC1155) granting an operator.
+    ApprovalForAll,
+    /// Permit2 `permit` — sets an allowance from an off-chain signature.
+    Permit2Permit,
+    /// Permit2 `permitTransferFrom` — moves tokens with the owner's signature.
+    Permit2Transfer,
+}
+
+/// An approval granted, or a signature-based transfer, decoded from calldata.
+#[derive(Debug, Clone, Serialize)]
+pub struct Approval {
+    pub kind: ApprovalKind,
+    /// Token contract, when known from the calldata or the tx target.
+    pub token: Option<String>,
+    /// Account whose tokens are approved or moved.
+    pub owner: Option<String>,
+    /// Approved spender or operator; for Permit2 transfers, the recipient.
+    pub spender: String,
+    /// Amount in raw token units, in decimal. `None` for `setApprovalForAll`.
+    pub amount: Option<String>,
+    /// `type(uint256).max`, or `type(uint160).max` for Permit2 allowances.
+    pub unlimited: bool,
+}
+
+impl DecodedTx {
+    pub fn priority_fee_gwei(&self) -> f64 {
+        self.max_priority_fee_per_gas as f64 / 1e9
+    }
+
+    pub fn max_fee_gwei(&self) -> f64 {
+        self.max_fee_per_gas as f64 / 1e9
+    }
+
+    /// This tx's tip as a multiple of its flashblock's median tip.
+    pub fn tip_multiple(&self) -> Option<f64> {
+        self.median_tip_wei
+            .filter(|m| *m > 0)
+            .map(|m| self.max_priority_fee_per_gas as f64 / m as f64)
+    }
+}
+
+/// Fewest transactions a flashblock needs before its median tip means anything.
+const MIN_TXS_FOR_MEDIAN: usize = 3;
+
+fn median_tip(txs: &[DecodedTx]) -> Option<u128> {
+    if txs.len() < MIN_TXS_FOR_MEDIAN {
+        return None;
+    }
+    let mut tips: Vec<u128> = txs.iter().map(|t| t.max_priority_fee_per_gas).collect();
+    tips.sort_unstable();
+    Some(tips[tips.len() / 2])
+}
+
+/// Record the median tip of a flashblock's transactions on each of them, so fee
+/// triggers can compare a tx against its neighbours.
+pub fn annotate_median_tip(txs: &mut [DecodedTx]) {
+    let median = median_tip(txs);
+    for tx in txs {
+        tx.median_tip_wei = median;
+    }
+}
+
+/// Priority-fee distribution of a flashblock, in gwei — shown on the dashboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeSummary {
+    pub count: usize,
+    pub min_gwei: f64,
+    pub median_gwei: f64,
+    pub p90_gwei: f64,
+    pub max_gwei: f64,
+}
+
+pub fn fee_summary(txs: &[DecodedTx]) -> Option<FeeSummary> {
+    let mut tips: Vec<f64> = txs.iter().map(|t| t.priority_fee_gwei()).collect();
+    if tips.is_empty() {
+        return None;
+    }
+    tips.sort_by(f64::total_cmp);
+    let at = |q: f64| tips[((tips.len() - 1) as f64 * q).round() as usize];
+    Some(FeeSummary {
+        count: tips.len(),
+        min_gwei: tips[0],
+        median_gwei: at(0.5),
+        p90_gwei: at(0.9),
+        max_gwei: tips[tips.len() - 1],
+    })
+}
+
+/// A contract deployment — the created address plus a fingerprint of the init code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractCreation {
+    /// Address the contract lands at: keccak256(rlp([deployer, nonce]))[12..].
+    /// `None` if the deployer could not be recovered from the signature.
+    pub address: Option<String>,
+    pub deployer: Option<String>,
+    pub nonce: u64,
+    pub init_code_size: usize,
+    /// keccak256 of the init code, 0x-prefixed. Stable across redeploys of the same template.
+    pub init_code_hash: String,
 }
 
 /// Decode a raw RLP-encoded transaction.
@@ -196,26 +348,33 @@ pub fn decode_raw_tx(hex_str: &str) -> Option<DecodedTx> {
     // Legacy (type 0): [nonce, gasPrice, gasLimit, to, value, data, v, r, s]
     // Deposit (type 0x7e): different format
 
-    let (to_bytes, value_bytes, data_bytes) = match tx_type {
+    let (nonce_bytes, to_bytes, value_bytes, data_bytes) = match tx_type {
         0x02 if items.len() >= 8 => {
-            // EIP-1559: to=5, value=6, data=7
-            (items.get(5)?, items.get(6)?, items.get(7)?)
+            // EIP-1559: nonce=1, to=5, value=6, data=7
+            (items.get(1)?, items.get(5)?, items.get(6)?, items.get(7)?)
         }
         0x01 if items.len() >= 7 => {
-            // EIP-2930: to=4, value=5, data=6
-            (items.get(4)?, items.get(5)?, items.get(6)?)
+            // EIP-2930: nonce=1, to=4, value=5, data=6
+            (items.get(1)?, items.get(4)?, items.get(5)?, items.get(6)?)
         }
         0x7e => {
             // Deposit tx: skip for now
             return None;
         }
         _ if items.len() >= 6 => {
-            // Legacy: to=3, value=4, data=5
-            (items.get(3)?, items.get(4)?, items.get(5)?)
+            // Legacy: nonce=0, to=3, value=4, data=5
+            (items.first()?, items.get(3)?, items.get(4)?, items.get(5)?)
         }
         _ => return None,
     };
 
+    // (tip, fee cap, gas limit) — pre-1559 txs have a single gas price for both
+    let (tip_bytes, fee_bytes, gas_limit_bytes) = match tx_type {
+        0x02 => (items.get(2)?, items.get(3)?, items.get(4)?),
+        0x01 => (items.get(2)?, items.get(2)?, items.get(3)?),
+        _ => (items.get(1)?, items.get(1)?, items.get(2)?),
+    };
+
     let to_hex = if to_bytes.is_empty() {
         None
     } else {
@@ -232,7 +391,9 @@ pub fn decode_raw_tx(hex_str: &str) -> Option<DecodedTx> {
         .and_then(|addr| addresses.get(addr.as_str()).cloned());
 
     // Decode function selector
-    let action = if data_bytes.len() >= 4 {
+    let action = if to_bytes.is_empty() {
+        Some("contract creation".to_string())
+    } else if data_bytes.len() >= 4 {
         let mut sel = [0u8; 4];
         sel.copy_from_slice(&data_bytes[..4]);
         selectors.get(&sel).map(|s| s.to_string())
@@ -252,7 +413,22 @@ pub fn decode_raw_tx(hex_str: &str) -> Option<DecodedTx> {
     let tx_hash = format!("0x{:x}", keccak256(&bytes));
 
     // Recover sender address from signature
-    let from_addr = recover_sender(tx_type, &items, rlp_bytes);
+    let from_addr = recover_sender(tx_type, &items);
+    let approval = decode_approval(data_bytes, to_lower.as_deref(), from_addr.as_deref());
+
+    // No recipient means the data field is init code for a new contract
+    let nonce = bytes_to_u128(nonce_bytes) as u64;
+    let contract_creation = if to_bytes.is_empty() {
+        Some(ContractCreation {
+            address: from_addr.as_deref().and_then(|d| create_address(d, nonce)),
+            deployer: from_addr.clone(),
+            nonce,
+            init_code_size: data_bytes.len(),
+            init_code_hash: format!("0x{:x}", keccak256(data_bytes)),
+        })
+    } else {
+        None
+    };
 
     Some(DecodedTx {
         hash: Some(tx_hash),
@@ -264,12 +440,106 @@ pub fn decode_raw_tx(hex_str: &str) -> Option<DecodedTx> {
         action,
         category,
         gas_used: None,
+        contract_creation,
+        nonce,
+        gas_limit: bytes_to_u128(gas_limit_bytes) as u64,
+        max_fee_per_gas: bytes_to_u128(fee_bytes),
+        max_priority_fee_per_gas: bytes_to_u128(tip_bytes),
+        median_tip_wei: None,
+        approval,
     })
 }
 
+/// Decode an approval or Permit2 signature transfer from calldata. Revocations
+/// (zero amounts, `setApprovalForAll(_, false)`) return `None`.
+fn decode_approval(data: &[u8], to: Option<&str>, from: Option<&str>) -> Option<Approval> {
+    let selector = hex::encode(data.get(..4)?);
+    let args = &data[4..];
+    let word = |i: usize| args.get(i * 32..(i + 1) * 32).map(U256::from_be_slice);
+    let address = |i: usize| args.get(i * 32 + 12..(i + 1) * 32).map(|a| format!("0x{}", hex::encode(a)));
+    let is_permit2 = to == Some(PERMIT2);
+
+    let approval = match selector.as_str() {
+        "095ea7b3" | "39509351" => {
+            let amount = word(1)?;
+            if amount.is_zero() {
+                return None;
+            }
+            Approval {
+                kind: ApprovalKind::Approve,
+                token: to.map(String::from),
+                owner: from.map(String::from),
+                spender: address(0)?,
+                amount: Some(amount.to_string()),
+                unlimited: amount == U256::MAX,
+            }
+        }
+        "a22cb465" => {
+            if word(1)?.is_zero() {
+                return None;
+            }
+            Approval {
+                kind: ApprovalKind::ApprovalForAll,
+                token: to.map(String::from),
+                owner: from.map(String::from),
+                spender: address(0)?,
+                amount: None,
+                unlimited: true,
+            }
+        }
+        // permit(owner, ((token, amount, expiration, nonce), spender, sigDeadline), signature)
+        "2b67b570" if is_permit2 => {
+            let amount = word(2)?;
+            Approval {
+                kind: ApprovalKind::Permit2Permit,
+                token: address(1),
+                owner: address(0),
+                spender: address(5)?,
+                amount: Some(amount.to_string()),
+                unlimited: amount == (U256::from(1) << 160) - U256::from(1),
+            }
+        }
+        // permit(Witness)TransferFrom(((token, amount), nonce, deadline), (to, requestedAmount), owner, ...)
+        "30f28b7a" | "137c29fe" if is_permit2 => Approval {
+            kind: ApprovalKind::Permit2Transfer,
+            token: address(0),
+            owner: address(6),
+            spender: address(4)?,
+            amount: word(5).map(|a| a.to_string()),
+            unlimited: false,
+        },
+        // Batch forms: (permit offset, transferDetails offset, owner, ...); the first
+        // transfer's recipient stands for the batch.
+        "edd9444b" | "fe8ec1a7" if is_permit2 => {
+            let details = usize::try_from(word(1)?).ok()? / 32;
+            Approval {
+                kind: ApprovalKind::Permit2Transfer,
+                token: None,
+                owner: address(2),
+                spender: address(details + 1)?,
+                amount: word(details + 2).map(|a| a.to_string()),
+                unlimited: false,
+            }
+        }
+        _ => return None,
+    };
+    Some(approval)
+}
+
+/// Compute the CREATE address for a deployer and nonce: keccak256(rlp([sender, nonce]))[12..].
+pub fn create_address(deployer: &str, nonce: u64) -> Option<String> {
+    let sender = hex::decode(deployer.strip_prefix("0x").unwrap_or(deployer)).ok()?;
+    if sender.len() != 20 {
+        return None;
+    }
+    let encoded = rlp_encode_list(&[sender, u64_to_bytes(nonce)]);
+    let hash = keccak256(&encoded);
+    Some(format!("0x{}", hex::encode(&hash[12..])))
+}
+
 /// Minimal RLP list decoder — returns the items in a top-level list.
 /// Recover the sender address from the transaction signature (ecrecover).
-fn recover_sender(tx_type: u8, items: &[Vec<u8>], rlp_bytes: &[u8]) -> Option<String> {
+fn recover_sender(tx_type: u8, items: &[Vec<u8>]) -> Option<String> {
     // Extract v, r, s based on tx type
     let (v_bytes, r_bytes, s_bytes, unsigned_payload) = match tx_type {
         0x02 if items.len() >= 12 => {
@@ -292,7 +562,7 @@ fn recover_sender(tx_type: u8, items: &[Vec<u8>], rlp_bytes: &[u8]) -> Option<St
             payload.extend_from_slice(&unsigned);
             (v, r, s, payload)
         }
-        0x00 | _ if tx_type <= 0x7f && items.len() >= 9 && tx_type != 0x7e => {
+        _ if tx_type <= 0x7f && items.len() >= 9 && tx_type != 0x7e => {
             // Legacy: items 6=v, 7=r, 8=s
             let v = items.get(6)?;
             let r = items.get(7)?;
@@ -351,6 +621,37 @@ fn recover_sender(tx_type: u8, items: &[Vec<u8>], rlp_bytes: &[u8]) -> Option<St
     Some(format!("0x{}", hex::encode(&addr_hash[12..])))
 }
 
+/// Split an RLP-encoded block (as returned by `debug_getRawBlock`) into its raw
+/// transactions, in the envelope form `decode_raw_tx` expects.
+pub fn split_block_txs(block: &[u8]) -> Option<Vec<Vec<u8>>> {
+    // Block: [header, transactions, ommers, withdrawals?]
+    if *block.first()? < 0xc0 {
+        return None;
+    }
+    let (fields, _) = decode_rlp_item(block)?;
+    let (_, header_len) = decode_rlp_item(fields)?;
+    let txs = &fields[header_len..];
+    if *txs.first()? < 0xc0 {
+        return None;
+    }
+    let (payload, _) = decode_rlp_item(txs)?;
+
+    // Legacy txs are embedded as RLP lists; typed txs as byte strings holding `type || rlp`.
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < payload.len() {
+        let item = &payload[pos..];
+        let (inner, consumed) = decode_rlp_item(item)?;
+        if item[0] >= 0xc0 {
+            out.push(item[..consumed].to_vec());
+        } else {
+            out.push(inner.to_vec());
+        }
+        pos += consumed;
+    }
+    Some(out)
+}
+
 fn u64_to_bytes(val: u64) -> Vec<u8> {
     if val == 0 { return vec![]; }
     let bytes = val.to_be_bytes();
@@ -482,3 +783,114 @@ fn bytes_to_u128(bytes: &[u8]) -> u128 {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_address() {
+        let deployer = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0";
+        assert_eq!(create_address(deployer, 0).as_deref(), Some("0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"));
+        assert_eq!(create_address(deployer, 1).as_deref(), Some("0x343c43a37d37dff08ae8c4a11544c718abb4fcf8"));
+        assert_eq!(create_address("0x1234", 0), None);
+    }
+
+    #[test]
+    fn test_split_block_txs() {
+        let header = rlp_encode_list(&[vec![0xaa; 4], u64_to_bytes(1234)]);
+        let legacy = rlp_encode_list(&[vec![], vec![0x01], vec![0xbb; 20]]);
+        let typed = [vec![0x02], rlp_encode_list(&[vec![0x21, 0x05], vec![0x07]])].concat();
+
+        let mut txs = legacy.clone();
+        rlp_encode_item(&typed, &mut txs);
+        let txs = [vec![0xc0 + txs.len() as u8], txs].concat();
+        let fields = [header, txs, vec![0xc0]].concat();
+        let block = [vec![0xc0 + fields.len() as u8], fields].concat();
+
+        assert_eq!(split_block_txs(&block), Some(vec![legacy, typed]));
+        assert_eq!(split_block_txs(&[0x80]), None);
+    }
+
+    #[test]
+    fn test_fee_fields_and_median_tip() {
+        let gwei = |g: u64| u64_to_bytes(g * 1_000_000_000);
+        // EIP-1559: [chainId, nonce, tip, maxFee, gasLimit, to, value, data, accessList, v, r, s]
+        let tx = |tip: u64| {
+            let fields = rlp_encode_list(&[
+                u64_to_bytes(8453), u64_to_bytes(7), gwei(tip), gwei(50), u64_to_bytes(300_000),
+                vec![0x11; 20], vec![], vec![], vec![], vec![], vec![], vec![],
+            ]);
+            format!("0x02{}", hex::encode(fields))
+        };
+
+        let decoded = decode_raw_tx(&tx(2)).unwrap();
+        assert_eq!((decoded.nonce, decoded.gas_limit), (7, 300_000));
+        assert_eq!(decoded.priority_fee_gwei(), 2.0);
+        assert_eq!(decoded.max_fee_gwei(), 50.0);
+        assert_eq!(decoded.tip_multiple(), None);
+
+        let mut txs: Vec<_> = [1, 1, 2, 30].iter().map(|t| decode_raw_tx(&tx(*t)).unwrap()).collect();
+        annotate_median_tip(&mut txs);
+        assert_eq!(txs[3].tip_multiple(), Some(15.0));
+        let fees = fee_summary(&txs).unwrap();
+        assert_eq!((fees.min_gwei, fees.max_gwei), (1.0, 30.0));
+    }
+
+    #[test]
+    fn test_approval_selectors_and_decoding() {
+        for (signature, selector) in [
+            ("approve(address,uint256)", "095ea7b3"),
+            ("increaseAllowance(address,uint256)", "39509351"),
+            ("setApprovalForAll(address,bool)", "a22cb465"),
+            ("permit(address,((address,uint160,uint48,uint48),address,uint256),bytes)", "2b67b570"),
+            ("permitTransferFrom(((address,uint256),uint256,uint256),(address,uint256),address,bytes)", "30f28b7a"),
+        ] {
+            assert_eq!(hex::encode(&keccak256(signature)[..4]), selector, "{signature}");
+        }
+
+        let token = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+        let owner = "0x00000000000000000000000000000000000000aa";
+        let spender = [0xbb; 20];
+        let call = |selector: &str, words: &[[u8; 32]]| {
+            let mut data = hex::decode(selector).unwrap();
+            words.iter().for_each(|w| data.extend_from_slice(w));
+            data
+        };
+        let addr = |a: [u8; 20]| {
+            let mut w = [0u8; 32];
+            w[12..].copy_from_slice(&a);
+            w
+        };
+        let num = |n: U256| n.to_be_bytes::<32>();
+
+        let unlimited = decode_approval(&call("095ea7b3", &[addr(spender), num(U256::MAX)]), Some(token), Some(owner)).unwrap();
+        assert_eq!(unlimited.kind, ApprovalKind::Approve);
+        assert_eq!(unlimited.spender, format!("0x{}", "bb".repeat(20)));
+        assert_eq!((unlimited.token.as_deref(), unlimited.owner.as_deref()), (Some(token), Some(owner)));
+        assert!(unlimited.unlimited);
+        let limited = decode_approval(&call("095ea7b3", &[addr(spender), num(U256::from(5))]), Some(token), None).unwrap();
+        assert_eq!((limited.amount.as_deref(), limited.unlimited), (Some("5"), false));
+        // Revocations aren't approvals.
+        assert!(decode_approval(&call("095ea7b3", &[addr(spender), num(U256::ZERO)]), Some(token), None).is_none());
+        assert!(decode_approval(&call("a22cb465", &[addr(spender), num(U256::ZERO)]), Some(token), None).is_none());
+        let all = decode_approval(&call("a22cb465", &[addr(spender), num(U256::from(1))]), Some(token), None).unwrap();
+        assert_eq!(all.kind, ApprovalKind::ApprovalForAll);
+
+        // permitTransferFrom(((token, amount), nonce, deadline), (to, requested), owner, signature)
+        let mut token_word = [0u8; 20];
+        token_word.copy_from_slice(&hex::decode(&token[2..]).unwrap());
+        let mut owner_word = [0u8; 20];
+        owner_word[19] = 0xaa;
+        let transfer = call("30f28b7a", &[
+            addr(token_word), num(U256::from(1000)), num(U256::ZERO), num(U256::from(u64::MAX)),
+            addr(spender), num(U256::from(1000)), addr(owner_word), num(U256::from(0x100)),
+        ]);
+        let drain = decode_approval(&transfer, Some(PERMIT2), Some("0x00000000000000000000000000000000000000cc")).unwrap();
+        assert_eq!(drain.kind, ApprovalKind::Permit2Transfer);
+        assert_eq!((drain.token.as_deref(), drain.owner.as_deref()), (Some(token), Some(owner)));
+        assert_eq!(drain.amount.as_deref(), Some("1000"));
+        // The same selector on any other contract isn't Permit2.
+        assert!(decode_approval(&transfer, Some(token), None).is_none());
+    }
+}
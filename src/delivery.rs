@@ -0,0 +1,673 @@
+//! Durable webhook delivery — alerts go into a SQLite outbox and a background worker
+//! POSTs them, retrying with exponential backoff. A destination that keeps failing
+//! has its circuit opened for a while, so its backlog waits instead of burning
+//! attempts. Deliveries that run out of attempts, or are rejected with a 4xx, are
+//! dead-lettered. They stay in the outbox until redelivered by hand.
+//!
+//! POSTs run on a `Dispatcher`, off the feed's hot path. Each destination gets a
+//! bounded queue and its own few workers, so a hung receiver only backs up its own
+//! queue. A full queue never blocks the sender: outbox deliveries wait for the next
+//! pass, and direct sends (`alert`, which has no store) are dropped and counted.
+//!
+//!   GET  /api/deliveries                   — pending and dead deliveries, counts, open circuits (admin token)
+//!   GET  /api/deliveries?status=delivered  — filter by status (comma-separated)
+//!   POST /api/deliveries/{id}/redeliver    — queue a delivery again (admin token)
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::{Semaphore, mpsc};
+use tracing::{info, warn};
+
+use crate::alert::Batch;
+use crate::rules::RulesConfig;
+use crate::serve::AppState;
+use crate::sink::Destination;
+use crate::store::{AlertStore, Delivery, DeliveryStatus};
+
+/// Attempts before a delivery is dead-lettered.
+pub const MAX_ATTEMPTS: u32 = 8;
+/// Delay before the first retry; doubled after each failure up to `BACKOFF_MAX_MS`.
+pub const BACKOFF_BASE_MS: u64 = 2_000;
+pub const BACKOFF_MAX_MS: u64 = 10 * 60_000;
+/// Consecutive failures that open a destination's circuit, and how long it stays open.
+pub const CIRCUIT_THRESHOLD: u32 = 5;
+pub const CIRCUIT_OPEN_MS: u64 = 60_000;
+/// How long deliveries wait while a half-open circuit's probe is out.
+const PROBE_WAIT_MS: u64 = 5_000;
+/// How often the worker looks for due retries when nothing new was queued.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// Deliveries taken from the outbox per pass.
+const BATCH_LIMIT: usize = 50;
+/// Jobs waiting per destination before sends to it are turned away.
+pub const QUEUE_CAPACITY: usize = 256;
+/// Concurrent POSTs per destination, and across all destinations.
+pub const PER_DESTINATION: usize = 2;
+pub const MAX_IN_FLIGHT: usize = 16;
+
+/// Delay before retry number `attempts` (1 = the first retry).
+pub fn backoff_ms(attempts: u32) -> u64 {
+    BACKOFF_BASE_MS
+        .saturating_mul(1 << attempts.saturating_sub(1).min(20))
+        .min(BACKOFF_MAX_MS)
+}
+
+/// Circuit-breaker key for a URL: its scheme, host and port.
+pub fn destination(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(u) if u.has_host() => format!("{}://{}:{}", u.scheme(), u.host_str().unwrap_or(""), u.port_or_known_default().unwrap_or(0)),
+        // `exec:` commands are each their own destination.
+        _ => url.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Circuit {
+    /// Consecutive failed attempts.
+    pub failures: u32,
+    /// While in the future, deliveries to the destination wait (epoch millis).
+    pub open_until: u64,
+    /// Once `open_until` has passed, the delivery let through to test the destination,
+    /// and when it was let through.
+    #[serde(skip)]
+    probe: Option<(i64, u64)>,
+}
+
+/// Circuit state per destination.
+#[derive(Default)]
+pub struct Circuits {
+    by_destination: HashMap<String, Circuit>,
+}
+
+impl Circuits {
+    /// Until when delivery `id` to `destination` must wait, if it can't go now. While
+    /// the circuit is open, everything waits. Once it has passed, the circuit is
+    /// half-open: one delivery goes through as a probe and the rest wait for its
+    /// outcome, which closes the circuit or opens it again. A probe that never reports
+    /// back is replaced after `CIRCUIT_OPEN_MS`.
+    pub fn hold(&mut self, destination: &str, id: i64, now_ms: u64) -> Option<u64> {
+        let circuit = self.by_destination.get_mut(destination)?;
+        if circuit.open_until > now_ms {
+            return Some(circuit.open_until);
+        }
+        if circuit.failures < CIRCUIT_THRESHOLD {
+            return None;
+        }
+        match circuit.probe {
+            Some((probe, since)) if probe != id && now_ms < since.saturating_add(CIRCUIT_OPEN_MS) => Some(now_ms + PROBE_WAIT_MS),
+            Some((probe, _)) if probe == id => None,
+            _ => {
+                circuit.probe = Some((id, now_ms));
+                None
+            }
+        }
+    }
+
+    pub fn record_success(&mut self, destination: &str) {
+        self.by_destination.remove(destination);
+    }
+
+    /// Count a failure. Returns `true` if it opened the circuit.
+    pub fn record_failure(&mut self, destination: &str, now_ms: u64) -> bool {
+        let circuit = self.by_destination.entry(destination.to_string()).or_default();
+        circuit.failures += 1;
+        if circuit.failures >= CIRCUIT_THRESHOLD {
+            circuit.open_until = now_ms + CIRCUIT_OPEN_MS;
+            circuit.probe = None;
+            return true;
+        }
+        false
+    }
+
+    /// Destinations with failures, open or not.
+    pub fn snapshot(&self) -> Vec<(String, Circuit)> {
+        let mut out: Vec<_> = self.by_destination.iter().map(|(d, c)| (d.clone(), c.clone())).collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+}
+
+/// What became of one POST.
+#[derive(Debug)]
+pub enum Outcome {
+    Delivered,
+    /// Network error, timeout, 408, 429 or 5xx — worth another try.
+    Retry(String),
+    /// Any other non-2xx status — retrying won't help.
+    Rejected(String),
+}
+
+/// POST a webhook body as JSON with the given headers. With a secret, the exact
+/// bytes sent are signed (see `signing`).
+pub async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &[(String, String)],
+    secret: Option<&str>,
+    payload: &serde_json::Value,
+) -> Outcome {
+    let body = match serde_json::to_vec(payload) {
+        Ok(body) => body,
+        Err(e) => return Outcome::Rejected(e.to_string()),
+    };
+    let mut req = client.post(url).header("Content-Type", "application/json");
+    for (name, value) in headers {
+        req = req.header(name, value);
+    }
+    if let Some(secret) = secret {
+        req = req.header(crate::signing::SIGNATURE_HEADER, crate::signing::sign(secret, now_ms() / 1000, &body));
+    }
+    let req = req.body(body);
+    match req.send().await {
+        Ok(resp) if resp.status().is_success() => Outcome::Delivered,
+        Ok(resp) => {
+            let status = resp.status();
+            let error = format!("HTTP {}", status);
+            if status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429 {
+                Outcome::Retry(error)
+            } else {
+                Outcome::Rejected(error)
+            }
+        }
+        Err(e) => Outcome::Retry(e.to_string()),
+    }
+}
+
+/// A batch rendered with its destination's sink, ready for the outbox.
+pub struct Rendered {
+    pub destination: Destination,
+    /// Rules with alerts in the batch, in order of first appearance.
+    pub rules: Vec<String>,
+    pub payload: serde_json::Value,
+}
+
+/// Render a batch with its destination's sink. Needs the rules config but no I/O, so
+/// callers can do it under the engine lock and store the result after releasing it.
+pub fn render(config: &RulesConfig, batch: &Batch) -> Rendered {
+    let mut rules: Vec<String> = Vec::new();
+    for alert in &batch.alerts {
+        if !rules.contains(&alert.rule_name) {
+            rules.push(alert.rule_name.clone());
+        }
+    }
+    let payload = batch.destination.sink.sink().render(&batch.alerts, config);
+    Rendered { destination: batch.destination.clone(), rules, payload }
+}
+
+/// Put a rendered batch in the outbox. The outbox worker hands it to the dispatcher.
+pub fn enqueue(store: &AlertStore, batch: &Rendered, now_ms: u64) -> eyre::Result<i64> {
+    store.enqueue_delivery(&batch.destination, &batch.rules, &batch.payload, now_ms)
+}
+
+/// A POST for the dispatcher.
+pub enum Job {
+    /// From the outbox: the outcome is recorded, and failures retried.
+    Outbox(Delivery),
+    /// Sent once, outcome only logged.
+    Direct { destination: Destination, payload: serde_json::Value },
+}
+
+impl Job {
+    fn url(&self) -> &str {
+        match self {
+            Job::Outbox(d) => &d.url,
+            Job::Direct { destination, .. } => &destination.url,
+        }
+    }
+}
+
+/// Counters for one destination's queue.
+#[derive(Default)]
+struct QueueStats {
+    queued: AtomicU64,
+    in_flight: AtomicU64,
+    sent: AtomicU64,
+    failed: AtomicU64,
+    /// Jobs turned away because the queue was full.
+    full: AtomicU64,
+}
+
+struct DestinationQueue {
+    tx: mpsc::Sender<Job>,
+    stats: Arc<QueueStats>,
+}
+
+/// Bounded per-destination queues with their own workers. See the module docs.
+pub struct Dispatcher {
+    client: Arc<reqwest::Client>,
+    store: Option<Arc<AlertStore>>,
+    circuits: Arc<std::sync::Mutex<Circuits>>,
+    in_flight: Arc<Semaphore>,
+    queues: std::sync::Mutex<HashMap<String, DestinationQueue>>,
+    /// Outbox deliveries queued or being sent, so a pass doesn't hand them out twice.
+    claimed: std::sync::Mutex<HashSet<i64>>,
+}
+
+impl Dispatcher {
+    pub fn new(
+        client: Arc<reqwest::Client>,
+        store: Option<Arc<AlertStore>>,
+        circuits: Arc<std::sync::Mutex<Circuits>>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            client,
+            store,
+            circuits,
+            in_flight: Arc::new(Semaphore::new(MAX_IN_FLIGHT)),
+            queues: std::sync::Mutex::new(HashMap::new()),
+            claimed: std::sync::Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Queue a job without waiting. Returns `false` if the destination's queue is full
+    /// (or the delivery is already queued).
+    pub fn dispatch(self: &Arc<Self>, job: Job) -> bool {
+        if let Job::Outbox(ref d) = job
+            && !self.claimed.lock().is_ok_and(|mut c| c.insert(d.id))
+        {
+            return false;
+        }
+        let id = match job {
+            Job::Outbox(ref d) => Some(d.id),
+            Job::Direct { .. } => None,
+        };
+        let dest = destination(job.url());
+        let Ok(mut queues) = self.queues.lock() else { return false };
+        let queue = queues.entry(dest.clone()).or_insert_with(|| self.spawn_queue(&dest));
+        match queue.tx.try_send(job) {
+            Ok(()) => {
+                queue.stats.queued.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                queue.stats.full.fetch_add(1, Ordering::Relaxed);
+                if let mpsc::error::TrySendError::Full(Job::Direct { destination, .. }) = e {
+                    warn!("Webhook queue for {} full — dropped alert for {}", dest, destination.url);
+                }
+                if let Some(id) = id {
+                    self.release(id);
+                }
+                false
+            }
+        }
+    }
+
+    fn spawn_queue(self: &Arc<Self>, dest: &str) -> DestinationQueue {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let rx = Arc::new(tokio::sync::Mutex::new(rx));
+        let stats = Arc::new(QueueStats::default());
+        for _ in 0..PER_DESTINATION {
+            tokio::spawn(self.clone().work(rx.clone(), stats.clone(), dest.to_string()));
+        }
+        DestinationQueue { tx, stats }
+    }
+
+    async fn work(self: Arc<Self>, rx: Arc<tokio::sync::Mutex<mpsc::Receiver<Job>>>, stats: Arc<QueueStats>, dest: String) {
+        loop {
+            let Some(job) = rx.lock().await.recv().await else { return };
+            stats.queued.fetch_sub(1, Ordering::Relaxed);
+            let Ok(_permit) = self.in_flight.acquire().await else { return };
+
+            // The circuit may have opened while this job waited.
+            if let Job::Outbox(ref d) = job
+                && let Some(until) = self.circuits.lock().ok().and_then(|mut c| c.hold(&dest, d.id, now_ms()))
+            {
+                if let Some(ref store) = self.store
+                    && let Err(e) = store.defer_delivery(d.id, until)
+                {
+                    warn!("Failed to defer delivery {}: {}", d.id, e);
+                }
+                self.release(d.id);
+                continue;
+            }
+
+            stats.in_flight.fetch_add(1, Ordering::Relaxed);
+            let outcome = match job {
+                Job::Outbox(ref d) => {
+                    let destination = d.destination();
+                    let (outcome, output) = d.sink.sink().deliver_with_output(&self.client, &destination, &d.payload).await;
+                    if let Some(ref store) = self.store
+                        && let Some(output) = output
+                        && let Err(e) = store.record_process_output(d.id, &output)
+                    {
+                        warn!("Failed to record process output for delivery {}: {}", d.id, e);
+                    }
+                    outcome
+                }
+                Job::Direct { ref destination, ref payload } => {
+                    destination.sink.sink().deliver(&self.client, destination, payload).await
+                }
+            };
+            stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+            match outcome {
+                Outcome::Delivered => stats.sent.fetch_add(1, Ordering::Relaxed),
+                _ => stats.failed.fetch_add(1, Ordering::Relaxed),
+            };
+
+            match job {
+                Job::Outbox(d) => {
+                    if let Some(ref store) = self.store
+                        && let Err(e) = record(store, &self.circuits, &d, &dest, outcome, now_ms())
+                    {
+                        warn!("Failed to record delivery {}: {}", d.id, e);
+                    }
+                    self.release(d.id);
+                }
+                Job::Direct { destination, .. } => match outcome {
+                    Outcome::Delivered => {}
+                    Outcome::Retry(e) | Outcome::Rejected(e) => warn!("Webhook {} failed: {}", destination.url, e),
+                },
+            }
+        }
+    }
+
+    fn release(&self, id: i64) {
+        if let Ok(mut claimed) = self.claimed.lock() {
+            claimed.remove(&id);
+        }
+    }
+
+    /// Queue depth, in-flight and outcome counters per destination.
+    pub fn stats(&self) -> Vec<serde_json::Value> {
+        let Ok(queues) = self.queues.lock() else { return Vec::new() };
+        let mut out: Vec<_> = queues.iter().map(|(dest, q)| json!({
+            "destination": dest,
+            "queued": q.stats.queued.load(Ordering::Relaxed),
+            "in_flight": q.stats.in_flight.load(Ordering::Relaxed),
+            "sent": q.stats.sent.load(Ordering::Relaxed),
+            "failed": q.stats.failed.load(Ordering::Relaxed),
+            "queue_full": q.stats.full.load(Ordering::Relaxed),
+        })).collect();
+        out.sort_by(|a, b| a["destination"].as_str().cmp(&b["destination"].as_str()));
+        out
+    }
+}
+
+/// Hand every delivery due at `now_ms` to the dispatcher, deferring those whose
+/// destination's circuit is open. Returns how many were queued.
+pub fn dispatch_due(store: &AlertStore, dispatcher: &Arc<Dispatcher>, now_ms: u64) -> eyre::Result<usize> {
+    let mut queued = 0;
+    for delivery in store.due_deliveries(now_ms, BATCH_LIMIT)? {
+        let open = dispatcher.circuits.lock()
+            .map_err(|e| eyre::eyre!("circuit lock poisoned: {e}"))?
+            .hold(&destination(&delivery.url), delivery.id, now_ms);
+        if let Some(until) = open {
+            store.defer_delivery(delivery.id, until)?;
+            continue;
+        }
+        if dispatcher.dispatch(Job::Outbox(delivery)) {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+fn record(
+    store: &AlertStore,
+    circuits: &std::sync::Mutex<Circuits>,
+    delivery: &Delivery,
+    dest: &str,
+    outcome: Outcome,
+    now_ms: u64,
+) -> eyre::Result<()> {
+    // Update the circuit first and write the outcome after releasing the lock, so
+    // other workers and the outbox pass aren't held up by SQLite.
+    let opened = {
+        let mut circuits = circuits.lock().map_err(|e| eyre::eyre!("circuit lock poisoned: {e}"))?;
+        match outcome {
+            // A rejection means the receiver answered, so the destination itself is up.
+            Outcome::Delivered | Outcome::Rejected(_) => {
+                circuits.record_success(dest);
+                false
+            }
+            Outcome::Retry(_) => circuits.record_failure(dest, now_ms),
+        }
+    };
+    let attempts = delivery.attempts + 1;
+    match outcome {
+        Outcome::Delivered => store.record_attempt(delivery.id, DeliveryStatus::Delivered, None, None, now_ms),
+        Outcome::Rejected(error) => {
+            warn!("Webhook {} rejected delivery {}: {} — dead-lettered", delivery.url, delivery.id, error);
+            store.record_attempt(delivery.id, DeliveryStatus::Dead, Some(&error), None, now_ms)
+        }
+        Outcome::Retry(error) => {
+            if opened {
+                warn!("Webhook destination {} failing — pausing deliveries for {}s", dest, CIRCUIT_OPEN_MS / 1000);
+            }
+            if attempts >= MAX_ATTEMPTS {
+                warn!("Webhook {} failed delivery {} {} times: {} — dead-lettered", delivery.url, delivery.id, attempts, error);
+                store.record_attempt(delivery.id, DeliveryStatus::Dead, Some(&error), None, now_ms)
+            } else {
+                let next = now_ms + backoff_ms(attempts);
+                warn!("Webhook {} failed delivery {} (attempt {}): {}", delivery.url, delivery.id, attempts, error);
+                store.record_attempt(delivery.id, DeliveryStatus::Pending, Some(&error), Some(next), now_ms)
+            }
+        }
+    }
+}
+
+/// Outbox worker: hands whatever is due to the dispatcher, waking early when
+/// `AppState::outbox_wake` is notified about a new delivery.
+pub async fn run(state: Arc<AppState>, dispatcher: Arc<Dispatcher>) {
+    let Some(ref store) = state.store else { return };
+    loop {
+        if let Err(e) = dispatch_due(store, &dispatcher, now_ms()) {
+            warn!("Outbox pass failed: {}", e);
+        }
+        tokio::select! {
+            _ = state.outbox_wake.notified() => {}
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Lists deliveries with their URLs and payloads, which can carry tokens, so it
+/// needs the admin token like redelivery does.
+pub async fn deliveries_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(e) = crate::rules_api::check_admin(&state, &headers) {
+        return e.into_response();
+    }
+    let Some(ref store) = state.store else {
+        return Json(json!({"error": "no store configured"})).into_response();
+    };
+    let statuses = match params.get("status") {
+        None => vec![DeliveryStatus::Pending, DeliveryStatus::Dead],
+        Some(list) => match list.split(',').map(|s| DeliveryStatus::parse(s.trim())).collect::<Option<Vec<_>>>() {
+            Some(s) => s,
+            None => {
+                let error = json!({"error": format!("unknown status in '{list}' (use pending, delivered, dead)")});
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        },
+    };
+    let limit = params.get("limit").and_then(|v| v.parse().ok()).unwrap_or(100usize).min(1000);
+    let now = now_ms();
+    let circuits: Vec<serde_json::Value> = match state.circuits.lock() {
+        Ok(c) => c.snapshot().into_iter()
+            .map(|(dest, c)| json!({"destination": dest, "failures": c.failures, "open": c.open_until > now, "open_until": c.open_until}))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    let queues = state.dispatcher.as_ref().map(|d| d.stats()).unwrap_or_default();
+    match (store.deliveries(&statuses, limit), store.delivery_counts()) {
+        (Ok(deliveries), Ok(counts)) => Json(json!({
+            "counts": counts,
+            "circuits": circuits,
+            "queues": queues,
+            "deliveries": deliveries,
+        })).into_response(),
+        (Err(e), _) | (_, Err(e)) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+pub async fn redeliver_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(e) = crate::rules_api::check_admin(&state, &headers) {
+        return e.into_response();
+    }
+    let Some(ref store) = state.store else {
+        return (StatusCode::NOT_FOUND, Json(json!({"error": "no store configured"}))).into_response();
+    };
+    let result = store.redeliver(id, now_ms()).and_then(|found| if found { store.delivery(id) } else { Ok(None) });
+    match result {
+        Ok(Some(delivery)) => {
+            info!("Delivery {} to {} queued again", id, delivery.url);
+            state.outbox_wake.notify_one();
+            Json(json!(delivery)).into_response()
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": format!("no delivery {id}")}))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_circuits() {
+        assert_eq!((backoff_ms(1), backoff_ms(2), backoff_ms(3)), (2_000, 4_000, 8_000));
+        assert_eq!(backoff_ms(30), BACKOFF_MAX_MS);
+        assert_eq!(destination("http://127.0.0.1:18789/hooks/agent"), "http://127.0.0.1:18789");
+        assert_eq!(destination("https://example.com/a?b"), "https://example.com:443");
+
+        let mut circuits = Circuits::default();
+        for _ in 1..CIRCUIT_THRESHOLD {
+            assert!(!circuits.record_failure("d", 0));
+        }
+        assert!(circuits.record_failure("d", 1_000));
+        let reopen = 1_000 + CIRCUIT_OPEN_MS;
+        assert_eq!(circuits.hold("d", 1, 1_000), Some(reopen));
+
+        // Half-open: delivery 1 probes, the rest wait for it, and a failure reopens.
+        assert_eq!(circuits.hold("d", 1, reopen), None);
+        assert_eq!(circuits.hold("d", 2, reopen), Some(reopen + PROBE_WAIT_MS));
+        assert_eq!(circuits.hold("d", 1, reopen + 10), None);
+        assert!(circuits.record_failure("d", reopen + 100));
+        assert_eq!(circuits.hold("d", 1, reopen + 100), Some(reopen + 100 + CIRCUIT_OPEN_MS));
+
+        // The next probe succeeds and closes the circuit for everyone.
+        let reopen = reopen + 100 + CIRCUIT_OPEN_MS;
+        assert_eq!(circuits.hold("d", 2, reopen), None);
+        assert!(circuits.hold("d", 3, reopen).is_some());
+        // A probe that never reports back is replaced.
+        assert_eq!(circuits.hold("d", 3, reopen + CIRCUIT_OPEN_MS), None);
+        circuits.record_success("d");
+        assert_eq!(circuits.hold("d", 4, reopen + CIRCUIT_OPEN_MS), None);
+        assert!(circuits.snapshot().is_empty());
+    }
+
+    /// Local stand-in receiver that answers with each status in `statuses` in turn,
+    /// then 200. A status of 0 never answers.
+    async fn receiver(statuses: Vec<u16>) -> String {
+        crate::sink::test_receiver("/hook", statuses).await.0
+    }
+
+    fn openclaw(url: &str) -> Destination {
+        Destination { url: url.to_string(), sink: Default::default(), headers: Default::default(), auth: None, signing_secret: None, template: None }
+    }
+
+    /// Wait for the dispatcher to record attempt number `attempts` of a delivery.
+    async fn attempted(store: &AlertStore, id: i64, attempts: u32) -> Delivery {
+        for _ in 0..200 {
+            let delivery = store.delivery(id).unwrap().unwrap();
+            if delivery.attempts >= attempts {
+                return delivery;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("delivery {id} never reached attempt {attempts}");
+    }
+
+    #[tokio::test]
+    async fn test_outbox_retries_then_delivers_or_dead_letters() {
+        let store = Arc::new(AlertStore::open(std::path::Path::new(":memory:")).unwrap());
+        let circuits = Arc::new(std::sync::Mutex::new(Circuits::default()));
+        let dispatcher = Dispatcher::new(Arc::new(reqwest::Client::new()), Some(store.clone()), circuits);
+        let body = json!({"message": "whale"});
+
+        let url = receiver(vec![503, 200]).await;
+        let id = store.enqueue_delivery(&openclaw(&url), &["whale-transfer".into()], &body, 0).unwrap();
+        assert_eq!(dispatch_due(&store, &dispatcher, 0).unwrap(), 1);
+        let retry = attempted(&store, id, 1).await;
+        assert_eq!((retry.status, retry.attempts), (DeliveryStatus::Pending, 1));
+        assert_eq!(retry.next_attempt_ms, retry.updated_ms + BACKOFF_BASE_MS);
+        assert_eq!(retry.last_error.as_deref(), Some("HTTP 503 Service Unavailable"));
+        // Not due yet, then delivered.
+        assert_eq!(dispatch_due(&store, &dispatcher, retry.updated_ms).unwrap(), 0);
+        dispatch_due(&store, &dispatcher, retry.next_attempt_ms).unwrap();
+        assert_eq!(attempted(&store, id, 2).await.status, DeliveryStatus::Delivered);
+
+        // A 4xx is dead-lettered at once; redelivery queues it again.
+        let url = receiver(vec![404]).await;
+        let id = store.enqueue_delivery(&openclaw(&url), &["r".into()], &body, 0).unwrap();
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        assert_eq!(attempted(&store, id, 1).await.status, DeliveryStatus::Dead);
+        assert_eq!(store.deliveries(&[DeliveryStatus::Dead], 10).unwrap().len(), 1);
+        assert!(store.redeliver(id, 0).unwrap());
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        assert_eq!(attempted(&store, id, 1).await.status, DeliveryStatus::Delivered);
+        assert_eq!(store.delivery_counts().unwrap()["delivered"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_hung_destination_only_fills_its_own_queue() {
+        let store = Arc::new(AlertStore::open(std::path::Path::new(":memory:")).unwrap());
+        let circuits = Arc::new(std::sync::Mutex::new(Circuits::default()));
+        let dispatcher = Dispatcher::new(Arc::new(reqwest::Client::new()), Some(store.clone()), circuits);
+        let hung = receiver(vec![0; PER_DESTINATION + QUEUE_CAPACITY + 1]).await;
+        let direct = || Job::Direct { destination: openclaw(&hung), payload: json!({}) };
+
+        // Never waits: once the workers are stuck and the queue is full, sends are turned away.
+        let accepted = (0..PER_DESTINATION + QUEUE_CAPACITY + 10).filter(|_| dispatcher.dispatch(direct())).count();
+        assert!(accepted <= PER_DESTINATION + QUEUE_CAPACITY + 1);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        while dispatcher.dispatch(direct()) {}
+        let stats = dispatcher.stats();
+        assert_eq!(stats[0]["in_flight"], PER_DESTINATION as u64);
+        assert!(stats[0]["queue_full"].as_u64().unwrap() > 0);
+
+        // Other destinations are unaffected.
+        let url = receiver(vec![]).await;
+        let id = store.enqueue_delivery(&openclaw(&url), &["r".into()], &json!({}), 0).unwrap();
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        assert_eq!(attempted(&store, id, 1).await.status, DeliveryStatus::Delivered);
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_need_the_admin_token() {
+        let list = |state: AppState, token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("authorization", format!("Bearer {token}").parse().unwrap());
+            deliveries_handler(State(Arc::new(state)), Query(HashMap::new()), headers)
+        };
+        assert_eq!(list(AppState::for_tests(None, None), "t0ken").await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(list(AppState::for_tests(None, Some("t0ken")), "other").await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(list(AppState::for_tests(None, Some("t0ken")), "t0ken").await.status(), StatusCode::OK);
+    }
+}
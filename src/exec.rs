@@ -0,0 +1,542 @@
+//! Local commands as alert sinks — the `exec` sink.
+//!
+//! A rule with `sink = "exec"` hands its alerts to a program on this machine,
+//! written as the webhook:
+//!
+//!   exec:/usr/local/bin/on-alert                          — run once per alert (or batch)
+//!   exec:/usr/local/bin/on-alert?arg=--quiet&arg=ops      — with arguments, in order
+//!   exec:/usr/local/bin/consumer?mode=stream&timeout=5    — one long-running process
+//!
+//! Alerts are written to the program's stdin as JSON lines, one `Alert` per line.
+//! In `once` mode (the default) the program runs for each delivery, stdin closes
+//! after the last line, and it must exit within `timeout` seconds (default 10).
+//! Exit 0 delivers, 75 (`EX_TEMPFAIL`) retries, and any other status dead-letters
+//! it. A timeout or signal is retried. The exit status and the tail of stderr are
+//! stored with the delivery.
+//!
+//! In `stream` mode one process per webhook is kept running, and a delivery is done
+//! once its lines are written to the pipe. A process that exits is reported on the
+//! next delivery, which is retried with a fresh one. One that doesn't take its
+//! lines within `timeout` is killed. One whose webhook is dropped from the rules
+//! is stopped on reload.
+//!
+//! At most `MAX_PROCESSES` once-mode commands run at a time, and each command at
+//! most `delivery::PER_DESTINATION`. Commands can only be configured in the rules
+//! file, never through the rules API.
+
+use std::collections::{BTreeSet, HashMap};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStderr, ChildStdin, Command};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::delivery::Outcome;
+use crate::rules::{Alert, RulesConfig};
+use crate::sink::{AlertSink, Destination, SinkKind};
+
+/// Once-mode commands running at the same time, across all rules.
+pub const MAX_PROCESSES: usize = 4;
+/// Bytes of stderr kept from a run — the end of it.
+const STDERR_MAX: usize = 4096;
+/// Exit status that asks for a retry, as in sysexits.h.
+const EX_TEMPFAIL: i32 = 75;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+/// How long to wait for stderr to close after the process has exited.
+const STDERR_GRACE: Duration = Duration::from_secs(1);
+
+static PROCESSES: Semaphore = Semaphore::const_new(MAX_PROCESSES);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecMode {
+    Once,
+    Stream,
+}
+
+/// What to run, parsed from a destination's `exec:` webhook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecTarget {
+    pub program: String,
+    pub args: Vec<String>,
+    pub mode: ExecMode,
+    pub timeout: Duration,
+}
+
+impl ExecTarget {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let url = reqwest::Url::parse(url).map_err(|e| format!("invalid exec webhook: {}", e))?;
+        if url.scheme() != "exec" {
+            return Err(format!("exec webhook must be exec:/path/to/command, not {}:", url.scheme()));
+        }
+        if url.host_str().is_some_and(|h| !h.is_empty()) {
+            return Err("exec webhook has a host — write exec:/path/to/command".to_string());
+        }
+        let program = url.path().to_string();
+        if program.is_empty() || program == "/" {
+            return Err("exec webhook names no command".to_string());
+        }
+        let (mut args, mut mode, mut timeout) = (Vec::new(), ExecMode::Once, DEFAULT_TIMEOUT_SECS);
+        for (name, value) in url.query_pairs() {
+            match name.as_ref() {
+                "arg" => args.push(value.into_owned()),
+                "mode" => mode = match value.as_ref() {
+                    "once" => ExecMode::Once,
+                    "stream" => ExecMode::Stream,
+                    other => return Err(format!("mode '{}' must be once or stream", other)),
+                },
+                "timeout" => timeout = value.parse().ok().filter(|t| *t > 0)
+                    .ok_or_else(|| format!("timeout '{}' must be a positive number of seconds", value))?,
+                other => return Err(format!("unknown exec webhook parameter '{}'", other)),
+            }
+        }
+        Ok(Self { program, args, mode, timeout: Duration::from_secs(timeout) })
+    }
+
+    fn spawn(&self) -> std::io::Result<Child> {
+        Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+    }
+}
+
+/// What a command reported, stored with its delivery.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessOutput {
+    /// `None` if it was killed by a signal, or is still running.
+    pub exit_status: Option<i32>,
+    /// The last `STDERR_MAX` bytes.
+    pub stderr: String,
+}
+
+/// Writes alerts to a local command's stdin. See the module docs.
+pub struct ExecSink;
+
+impl AlertSink for ExecSink {
+    fn render(&self, alerts: &[Alert], _config: &RulesConfig) -> Value {
+        serde_json::to_value(alerts).unwrap_or_default()
+    }
+
+    fn deliver<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        destination: &'a Destination,
+        body: &'a Value,
+    ) -> BoxFuture<'a, Outcome> {
+        Box::pin(async move { self.deliver_with_output(client, destination, body).await.0 })
+    }
+
+    fn deliver_with_output<'a>(
+        &'a self,
+        _client: &'a reqwest::Client,
+        destination: &'a Destination,
+        body: &'a Value,
+    ) -> BoxFuture<'a, (Outcome, Option<ProcessOutput>)> {
+        Box::pin(async move {
+            let target = match ExecTarget::parse(&destination.url) {
+                Ok(target) => target,
+                Err(e) => return (Outcome::Rejected(e), None),
+            };
+            let lines = json_lines(body);
+            match target.mode {
+                ExecMode::Once => run_once(&target, &lines).await,
+                ExecMode::Stream => write_stream(&destination.url, &target, &lines).await,
+            }
+        })
+    }
+}
+
+/// One alert per line; a stored payload is an array of them.
+fn json_lines(body: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    for alert in body.as_array().map(Vec::as_slice).unwrap_or(std::slice::from_ref(body)) {
+        out.extend_from_slice(alert.to_string().as_bytes());
+        out.push(b'\n');
+    }
+    out
+}
+
+/// A missing or non-executable program won't fix itself; anything else might.
+fn spawn_failed(target: &ExecTarget, e: std::io::Error) -> (Outcome, Option<ProcessOutput>) {
+    let error = format!("cannot run {}: {}", target.program, e);
+    match e.kind() {
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied => (Outcome::Rejected(error), None),
+        _ => (Outcome::Retry(error), None),
+    }
+}
+
+async fn run_once(target: &ExecTarget, lines: &[u8]) -> (Outcome, Option<ProcessOutput>) {
+    let Ok(_permit) = PROCESSES.acquire().await else {
+        return (Outcome::Retry("exec sink closed".to_string()), None);
+    };
+    let mut child = match target.spawn() {
+        Ok(child) => child,
+        Err(e) => return spawn_failed(target, e),
+    };
+    let stdin = child.stdin.take();
+    let stderr = Stderr::capture(&mut child);
+
+    let run = async {
+        if let Some(mut stdin) = stdin {
+            // A program that exits without reading closes the pipe; its status says how it went.
+            let _ = stdin.write_all(lines).await;
+        }
+        child.wait().await
+    };
+    match tokio::time::timeout(target.timeout, run).await {
+        Ok(Ok(status)) => {
+            let output = ProcessOutput { exit_status: status.code(), stderr: stderr.finish().await };
+            let outcome = match status.code() {
+                Some(0) => Outcome::Delivered,
+                Some(EX_TEMPFAIL) | None => Outcome::Retry(status.to_string()),
+                Some(_) => Outcome::Rejected(status.to_string()),
+            };
+            (outcome, Some(output))
+        }
+        Ok(Err(e)) => (Outcome::Retry(format!("waiting for {}: {}", target.program, e)), None),
+        Err(_) => {
+            let _ = child.kill().await;
+            let output = ProcessOutput { exit_status: None, stderr: stderr.finish().await };
+            (Outcome::Retry(format!("timed out after {}s", target.timeout.as_secs())), Some(output))
+        }
+    }
+}
+
+/// A stream-mode process and its stdin.
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    stderr: Stderr,
+}
+
+impl Running {
+    /// Reap the process, killing it if it is still running, and collect what it reported.
+    async fn stop(mut self, status: Option<ExitStatus>) -> ProcessOutput {
+        let status = match status {
+            Some(status) => Some(status),
+            None => {
+                let _ = self.child.start_kill();
+                tokio::time::timeout(STDERR_GRACE, self.child.wait()).await.ok().and_then(Result::ok)
+            }
+        };
+        drop(self.stdin);
+        ProcessOutput { exit_status: status.and_then(|s| s.code()), stderr: self.stderr.finish().await }
+    }
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<Running>>>;
+
+/// The running process for each stream-mode webhook.
+fn streams() -> &'static std::sync::Mutex<HashMap<String, Slot>> {
+    static STREAMS: OnceLock<std::sync::Mutex<HashMap<String, Slot>>> = OnceLock::new();
+    STREAMS.get_or_init(Default::default)
+}
+
+/// Webhooks of the config's `exec` sink rules.
+pub fn commands(config: &RulesConfig) -> BTreeSet<&str> {
+    config.rules.iter().filter(|r| r.sink == SinkKind::Exec).filter_map(|r| r.webhook.as_deref()).collect()
+}
+
+/// Stop the stream-mode processes of webhooks that `next` no longer has, and
+/// forget them. Called before `current` is swapped for `next`.
+pub fn retire_streams(current: &RulesConfig, next: &RulesConfig) {
+    let keep = commands(next);
+    let Ok(mut streams) = streams().lock() else { return };
+    for url in commands(current).into_iter().filter(|url| !keep.contains(url)) {
+        let Some(slot) = streams.remove(url) else { continue };
+        let program = ExecTarget::parse(url).map(|t| t.program).unwrap_or_else(|_| url.to_string());
+        tokio::spawn(async move {
+            if let Some(running) = slot.lock().await.take() {
+                running.stop(None).await;
+                info!("exec {} is no longer in the rules — stopped", program);
+            }
+        });
+    }
+}
+
+async fn write_stream(url: &str, target: &ExecTarget, lines: &[u8]) -> (Outcome, Option<ProcessOutput>) {
+    let slot = match streams().lock() {
+        Ok(mut streams) => streams.entry(url.to_string()).or_default().clone(),
+        Err(e) => return (Outcome::Retry(format!("exec streams lock poisoned: {e}")), None),
+    };
+    let mut slot = slot.lock().await;
+
+    let exited = slot.as_mut().and_then(|running| running.child.try_wait().ok().flatten());
+    if let Some(status) = exited
+        && let Some(running) = slot.take()
+    {
+        let output = running.stop(Some(status)).await;
+        warn!("exec {} exited ({}) — restarting it on the next attempt", target.program, status);
+        return (Outcome::Retry(format!("exited ({})", status)), Some(output));
+    }
+    let running = match slot.as_mut() {
+        Some(running) => running,
+        None => {
+            let mut child = match target.spawn() {
+                Ok(child) => child,
+                Err(e) => return spawn_failed(target, e),
+            };
+            let stderr = Stderr::capture(&mut child);
+            let Some(stdin) = child.stdin.take() else {
+                return (Outcome::Retry(format!("{} has no stdin", target.program)), None);
+            };
+            info!("exec {} started (pid {})", target.program, child.id().unwrap_or_default());
+            slot.insert(Running { child, stdin, stderr })
+        }
+    };
+
+    let write = async {
+        running.stdin.write_all(lines).await?;
+        running.stdin.flush().await
+    };
+    match tokio::time::timeout(target.timeout, write).await {
+        Ok(Ok(())) => (Outcome::Delivered, None),
+        Ok(Err(e)) => {
+            // Usually a closed pipe: the process has exited, or is about to.
+            let status = tokio::time::timeout(STDERR_GRACE, running.child.wait()).await.ok().and_then(Result::ok);
+            let error = match status {
+                Some(status) => format!("exited ({})", status),
+                None => e.to_string(),
+            };
+            let output = match slot.take() {
+                Some(running) => Some(running.stop(status).await),
+                None => None,
+            };
+            warn!("exec {} stopped taking alerts: {}", target.program, error);
+            (Outcome::Retry(error), output)
+        }
+        Err(_) => {
+            let output = match slot.take() {
+                Some(running) => Some(running.stop(None).await),
+                None => None,
+            };
+            warn!("exec {} did not read its stdin within {}s — killed", target.program, target.timeout.as_secs());
+            (Outcome::Retry(format!("did not read stdin within {}s", target.timeout.as_secs())), output)
+        }
+    }
+}
+
+/// The end of a process's stderr, read in the background so a chatty program
+/// never blocks on a full pipe.
+struct Stderr {
+    tail: Arc<std::sync::Mutex<Vec<u8>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Stderr {
+    fn capture(child: &mut Child) -> Self {
+        let tail = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reader = child.stderr.take().map(|stderr| tokio::spawn(read_tail(stderr, tail.clone())));
+        Self { tail, reader }
+    }
+
+    /// Wait briefly for the pipe to close, then take what was read.
+    async fn finish(self) -> String {
+        if let Some(reader) = self.reader {
+            let _ = tokio::time::timeout(STDERR_GRACE, reader).await;
+        }
+        let tail = self.tail.lock().map(|t| t.clone()).unwrap_or_default();
+        String::from_utf8_lossy(&tail).trim().to_string()
+    }
+}
+
+async fn read_tail(mut stderr: ChildStderr, tail: Arc<std::sync::Mutex<Vec<u8>>>) {
+    let mut buf = [0u8; 1024];
+    while let Ok(n) = stderr.read(&mut buf).await
+        && n > 0
+    {
+        let Ok(mut tail) = tail.lock() else { return };
+        tail.extend_from_slice(&buf[..n]);
+        let excess = tail.len().saturating_sub(STDERR_MAX);
+        tail.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delivery::{Circuits, Dispatcher, dispatch_due};
+    use crate::store::{AlertStore, Delivery, DeliveryStatus};
+    use serde_json::json;
+
+    /// An `exec:` webhook running a shell script.
+    fn sh(script: &str, params: &[(&str, &str)]) -> Destination {
+        let mut pairs = vec![("arg", "-c"), ("arg", script)];
+        pairs.extend_from_slice(params);
+        let url = reqwest::Url::parse_with_params("exec:/bin/sh", &pairs).unwrap();
+        Destination { url: url.to_string(), sink: SinkKind::Exec, headers: Default::default(), auth: None, signing_secret: None, template: None }
+    }
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("flashwatch-exec-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    async fn finished(store: &AlertStore, id: i64) -> Delivery {
+        for _ in 0..500 {
+            let delivery = store.delivery(id).unwrap().unwrap();
+            if delivery.attempts > 0 {
+                return delivery;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("delivery {id} was never attempted");
+    }
+
+    #[test]
+    fn test_exec_urls() {
+        let target = ExecTarget::parse("exec:/usr/local/bin/on-alert?arg=--quiet&arg=a%20b&mode=stream&timeout=5").unwrap();
+        assert_eq!(target, ExecTarget {
+            program: "/usr/local/bin/on-alert".into(),
+            args: vec!["--quiet".into(), "a b".into()],
+            mode: ExecMode::Stream,
+            timeout: Duration::from_secs(5),
+        });
+        let target = ExecTarget::parse("exec:notify-send").unwrap();
+        assert_eq!((target.program.as_str(), target.mode, target.timeout), ("notify-send", ExecMode::Once, Duration::from_secs(10)));
+
+        assert!(ExecTarget::parse("https://example.com/hook").unwrap_err().contains("exec:/path/to/command"));
+        assert!(ExecTarget::parse("exec://host/bin/x").unwrap_err().contains("has a host"));
+        assert!(ExecTarget::parse("exec:/bin/x?mode=daemon").unwrap_err().contains("once or stream"));
+        assert!(ExecTarget::parse("exec:/bin/x?timeout=0").unwrap_err().contains("positive"));
+        assert!(ExecTarget::parse("exec:/bin/x?env=1").unwrap_err().contains("unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_once_mode_stores_exit_status_and_stderr() {
+        let store = Arc::new(AlertStore::open(std::path::Path::new(":memory:")).unwrap());
+        let circuits = Arc::new(std::sync::Mutex::new(Circuits::default()));
+        let dispatcher = Dispatcher::new(Arc::new(reqwest::Client::new()), Some(store.clone()), circuits);
+        let out = scratch("once");
+        let body = json!([{"rule_name": "whale"}, {"rule_name": "dex"}]);
+
+        let ok = sh(&format!("cat > {}; echo done >&2", out.display()), &[]);
+        let id = store.enqueue_delivery(&ok, &["whale".into()], &body, 0).unwrap();
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        let delivery = finished(&store, id).await;
+        assert_eq!((delivery.status, delivery.exit_status, delivery.stderr.as_deref()), (DeliveryStatus::Delivered, Some(0), Some("done")));
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "{\"rule_name\":\"whale\"}\n{\"rule_name\":\"dex\"}\n");
+        let _ = std::fs::remove_file(&out);
+
+        // EX_TEMPFAIL is retried; any other failure is dead-lettered.
+        let id = store.enqueue_delivery(&sh("echo busy >&2; exit 75", &[]), &["r".into()], &body, 0).unwrap();
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        let delivery = finished(&store, id).await;
+        assert_eq!((delivery.status, delivery.exit_status, delivery.stderr.as_deref()), (DeliveryStatus::Pending, Some(75), Some("busy")));
+        let id = store.enqueue_delivery(&sh("echo 'bad input' >&2; exit 3", &[]), &["r".into()], &body, 0).unwrap();
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        let delivery = finished(&store, id).await;
+        assert_eq!((delivery.status, delivery.exit_status), (DeliveryStatus::Dead, Some(3)));
+        assert_eq!(delivery.last_error.as_deref(), Some("exit status: 3"));
+        let shown = serde_json::to_value(&delivery).unwrap();
+        assert_eq!((shown["exit_status"].clone(), shown["stderr"].clone()), (json!(3), json!("bad input")));
+
+        // A hung command is killed at its timeout and retried.
+        let id = store.enqueue_delivery(&sh("echo started >&2; sleep 30", &[("timeout", "1")]), &["r".into()], &body, 0).unwrap();
+        dispatch_due(&store, &dispatcher, 0).unwrap();
+        let delivery = finished(&store, id).await;
+        assert_eq!((delivery.status, delivery.exit_status, delivery.stderr.as_deref()), (DeliveryStatus::Pending, None, Some("started")));
+        assert_eq!(delivery.last_error.as_deref(), Some("timed out after 1s"));
+
+        let missing = Destination { url: "exec:/nonexistent/on-alert".into(), ..ok };
+        let (outcome, output) = ExecSink.deliver_with_output(&reqwest::Client::new(), &missing, &body).await;
+        assert!(matches!(outcome, Outcome::Rejected(ref e) if e.starts_with("cannot run /nonexistent/on-alert")), "{:?}", outcome);
+        assert_eq!(output, None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_mode_keeps_one_process_and_restarts_it() {
+        let client = reqwest::Client::new();
+        let out = scratch("stream");
+        let one = |i: u64| json!([{"index": i}]);
+
+        // Every line goes to the same process.
+        let reader = sh(&format!("echo start >> {0}; while read line; do echo \"$line\" >> {0}; done", out.display()), &[("mode", "stream")]);
+        for i in 0..3 {
+            let (outcome, output) = ExecSink.deliver_with_output(&client, &reader, &one(i)).await;
+            assert!(matches!(outcome, Outcome::Delivered), "{:?}", outcome);
+            assert_eq!(output, None);
+        }
+        for _ in 0..200 {
+            if std::fs::read_to_string(&out).unwrap_or_default().lines().count() == 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "start\n{\"index\":0}\n{\"index\":1}\n{\"index\":2}\n");
+        let _ = std::fs::remove_file(&out);
+
+        // One that exits is reported on the next delivery, then started again.
+        let quitter = sh("read line; echo 'giving up' >&2; exit 4", &[("mode", "stream")]);
+        assert!(matches!(ExecSink.deliver_with_output(&client, &quitter, &one(0)).await.0, Outcome::Delivered));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let (outcome, output) = ExecSink.deliver_with_output(&client, &quitter, &one(1)).await;
+        assert!(matches!(outcome, Outcome::Retry(ref e) if e == "exited (exit status: 4)"), "{:?}", outcome);
+        assert_eq!(output, Some(ProcessOutput { exit_status: Some(4), stderr: "giving up".into() }));
+        assert!(matches!(ExecSink.deliver_with_output(&client, &quitter, &one(1)).await.0, Outcome::Delivered));
+
+        // One that stops reading is killed once the pipe stays full past the timeout.
+        let stuck = sh("sleep 30", &[("mode", "stream"), ("timeout", "1")]);
+        let big = Value::Array((0..2000).map(|i| json!({"index": i, "pad": "x".repeat(64)})).collect());
+        let (outcome, output) = ExecSink.deliver_with_output(&client, &stuck, &big).await;
+        assert!(matches!(outcome, Outcome::Retry(ref e) if e == "did not read stdin within 1s"), "{:?}", outcome);
+        assert_eq!(output.map(|o| o.exit_status), Some(None));
+    }
+
+    #[tokio::test]
+    async fn test_reload_stops_streams_that_are_gone() {
+        let pidfile = scratch("retired");
+        let target = sh(&format!("echo $$ > {}; cat > /dev/null", pidfile.display()), &[("mode", "stream")]);
+        assert!(matches!(ExecSink.deliver_with_output(&reqwest::Client::new(), &target, &json!([{"index": 0}])).await.0, Outcome::Delivered));
+        let mut pid = String::new();
+        for _ in 0..200 {
+            pid = std::fs::read_to_string(&pidfile).unwrap_or_default().trim().to_string();
+            if !pid.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let proc = std::path::PathBuf::from(format!("/proc/{}", pid));
+        assert!(!pid.is_empty() && proc.exists());
+
+        let mut current = RulesConfig::from_toml(r#"
+            [global]
+            max_per_minute = 30
+
+            [[rules]]
+            name = "consumer"
+            sink = "exec"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 100.0
+        "#).unwrap();
+        current.rules[0].webhook = Some(target.url.clone());
+        let mut kept = current.clone();
+        kept.rules[0].name = "renamed".into();
+        retire_streams(&current, &kept);
+        assert!(streams().lock().unwrap().contains_key(&target.url));
+
+        let mut next = current.clone();
+        next.rules[0].webhook = None;
+        retire_streams(&current, &next);
+        assert!(!streams().lock().unwrap().contains_key(&target.url));
+        for _ in 0..200 {
+            if !proc.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!proc.exists(), "pid {} is still running", pid);
+        let _ = std::fs::remove_file(&pidfile);
+    }
+}
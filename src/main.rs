@@ -8,11 +8,23 @@ pub mod monitor;
 pub mod analyze;
 pub mod format;
 pub mod decode;
+pub mod mev;
+pub mod security;
 pub mod serve;
 pub mod rules;
+pub mod rules_api;
 pub mod alert;
 pub mod store;
+pub mod delivery;
+pub mod sink;
+pub mod signing;
+pub mod template;
+pub mod email;
+pub mod export;
+pub mod exec;
 pub mod ask;
+pub mod check;
+pub mod backtest;
 
 #[derive(Parser)]
 #[command(
@@ -102,6 +114,74 @@ enum Commands {
         json: bool,
     },
 
+    /// Validate rules files, dry-run them against transactions, snooze rules on a running serve
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+
+    /// Replay historical blocks through a rule set and report how many alerts it would produce
+    Backtest {
+        /// Path to rules TOML config file
+        #[arg(short = 'R', long)]
+        rules: String,
+
+        /// First block to replay
+        #[arg(long)]
+        from_block: u64,
+
+        /// Last block to replay (inclusive)
+        #[arg(long)]
+        to_block: u64,
+
+        /// Output the report as JSON (diffable between rule files)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Preview message templates
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+
+    /// Print an email digest for its last period from the alert store, or send it
+    Digest {
+        /// Name of a [digests] entry
+        name: String,
+
+        /// Path to rules TOML config file
+        #[arg(short = 'R', long, default_value = "rules.toml")]
+        rules: String,
+
+        /// Path to SQLite database with the stored alerts
+        #[arg(long, default_value = "flashwatch.db")]
+        db: String,
+
+        /// Mail it now instead of printing it
+        #[arg(long)]
+        send: bool,
+    },
+
+    /// Check a webhook's X-Flashwatch-Signature against its body
+    VerifySignature {
+        /// The rule's (or sink's) signing secret
+        #[arg(long, env = "FLASHWATCH_SIGNING_SECRET", hide_env_values = true)]
+        secret: String,
+
+        /// The X-Flashwatch-Signature header value (t=...,v1=...)
+        #[arg(long)]
+        signature: String,
+
+        /// File holding the raw request body (default: stdin)
+        #[arg(long)]
+        body: Option<String>,
+
+        /// Reject signatures older or newer than this many seconds
+        #[arg(long, default_value_t = signing::DEFAULT_TOLERANCE_SECS)]
+        tolerance_secs: u64,
+    },
+
     /// Launch web dashboard with live flashblock visualization
     Serve {
         /// Port for the web server
@@ -126,6 +206,65 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum TemplateCommand {
+    /// Print the message a rule would send for a transaction
+    Render {
+        /// Path to rules TOML config file
+        #[arg(short = 'R', long, default_value = "rules.toml")]
+        rules: String,
+
+        /// Rule whose template (or built-in message) to render
+        #[arg(long)]
+        rule: String,
+
+        /// Raw signed transaction (hex) or a transaction hash to fetch via RPC
+        #[arg(long)]
+        tx: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RulesCommand {
+    /// Strictly validate a rules file (unknown categories, protocols, actions, duplicates)
+    Check {
+        /// Path to rules TOML config file
+        rules: String,
+    },
+
+    /// Show which rules would match a transaction, and why
+    Test {
+        /// Path to rules TOML config file
+        rules: String,
+
+        /// Raw signed transaction (hex) or a transaction hash to fetch via RPC
+        #[arg(long)]
+        tx: String,
+    },
+
+    /// Snooze a rule, or an address with --address, on a running `serve`
+    Snooze {
+        /// Rule name (or address with --address)
+        target: String,
+
+        /// Minutes to snooze for (0 clears the snooze)
+        #[arg(short, long, default_value_t = 60)]
+        minutes: u64,
+
+        /// Snooze every alert from or to this address instead of a rule
+        #[arg(long)]
+        address: bool,
+
+        /// Base URL of the running `serve`
+        #[arg(long, default_value = "http://127.0.0.1:3000")]
+        server: String,
+
+        /// Admin token for the rules API
+        #[arg(long, env = "FLASHWATCH_ADMIN_TOKEN", hide_env_values = true)]
+        token: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     let cli = Cli::parse();
@@ -159,6 +298,25 @@ async fn main() -> eyre::Result<()> {
         Commands::Alert { rules, json } => {
             alert::run(&cli.url, &rules, json).await?;
         }
+        Commands::Rules { command } => match command {
+            RulesCommand::Check { rules } => check::check(&rules)?,
+            RulesCommand::Test { rules, tx } => check::test(&rules, &cli.rpc_url, &tx).await?,
+            RulesCommand::Snooze { target, minutes, address, server, token } => {
+                check::snooze(&server, &token, &target, minutes, address).await?
+            }
+        },
+        Commands::Backtest { rules, from_block, to_block, json } => {
+            backtest::run(&cli.rpc_url, &rules, from_block, to_block, json).await?;
+        }
+        Commands::Template { command: TemplateCommand::Render { rules, rule, tx } } => {
+            template::render_cmd(&rules, &cli.rpc_url, &rule, &tx).await?;
+        }
+        Commands::Digest { name, rules, db, send } => {
+            email::digest_cmd(&rules, &db, &name, send).await?;
+        }
+        Commands::VerifySignature { secret, signature, body, tolerance_secs } => {
+            signing::run_verify(&secret, &signature, body.as_deref(), tolerance_secs)?;
+        }
         Commands::Serve { port, bind, rules, db, static_dir } => {
             serve::run(&cli.url, &cli.rpc_url, &bind, port, rules.as_deref(), Some(&db), static_dir.as_deref()).await?;
         }
@@ -0,0 +1,523 @@
+//! MEV pattern detection — sandwiches, backrun arbitrage and JIT liquidity.
+//!
+//! Swap, mint and burn logs are decoded from flashblock receipts (Uniswap V2/V3 and
+//! Aerodrome pools) and kept for the current and previous flashblock, so a pattern
+//! can straddle two flashblocks. Profit is estimated from WETH moving in and out of
+//! the pools involved; pairs without a WETH leg get no estimate.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use alloy_primitives::{U256, keccak256};
+use serde::{Deserialize, Serialize};
+
+use crate::decode::{AddressLabel, DecodedTx};
+use crate::types::{FlashblockMessage, Log};
+
+/// Wrapped ETH on Base.
+const WETH: &str = "0x4200000000000000000000000000000000000006";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MevKind {
+    Sandwich,
+    Backrun,
+    Jit,
+}
+
+impl MevKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MevKind::Sandwich => "sandwich",
+            MevKind::Backrun => "backrun",
+            MevKind::Jit => "jit",
+        }
+    }
+}
+
+/// A detected MEV pattern around one victim transaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct MevFinding {
+    pub kind: MevKind,
+    pub pool: String,
+    /// Sender of the searcher's transactions.
+    pub attacker: String,
+    /// Contract the searcher's transactions call, when it isn't a known router.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bot: Option<String>,
+    /// Sender of the transaction that was sandwiched, backrun or filled by JIT liquidity.
+    pub victim: String,
+    pub victim_tx: String,
+    /// The searcher's transactions, in order.
+    pub attacker_txs: Vec<String>,
+    /// Net WETH the searcher took out of the pools involved, in ETH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_eth: Option<f64>,
+}
+
+impl MevFinding {
+    /// One-line description, e.g. "sandwich on 0xpool… by 0xbot…, est. 0.0421 ETH".
+    pub fn summary(&self) -> String {
+        let profit = match self.profit_eth {
+            Some(p) => format!(", est. {:.4} ETH", p),
+            None => String::new(),
+        };
+        format!("{} on {} by {}{}", self.kind.name(), self.pool, self.attacker, profit)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PoolEvent {
+    Swap { pool: String, zero_for_one: bool },
+    /// Concentrated liquidity added or removed; `ticks` are the raw indexed topics.
+    Mint { pool: String, owner: String, ticks: (String, String) },
+    Burn { pool: String, owner: String, ticks: (String, String) },
+}
+
+/// A transaction's pool events and WETH flows.
+#[derive(Debug, Clone)]
+struct TxEvents {
+    hash: String,
+    from: String,
+    to: Option<String>,
+    events: Vec<PoolEvent>,
+    /// WETH sent out of each address minus WETH sent to it, in ETH.
+    weth_out: HashMap<String, f64>,
+}
+
+impl TxEvents {
+    fn swaps(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.events.iter().filter_map(|e| match e {
+            PoolEvent::Swap { pool, zero_for_one } => Some((pool.as_str(), *zero_for_one)),
+            _ => None,
+        })
+    }
+
+    fn swap_on(&self, pool: &str) -> Option<bool> {
+        self.swaps().find(|(p, _)| *p == pool).map(|(_, d)| d)
+    }
+}
+
+struct Topics {
+    v2_swap: String,
+    aerodrome_swap: String,
+    v3_swap: String,
+    v3_mint: String,
+    v3_burn: String,
+    transfer: String,
+}
+
+fn topics() -> &'static Topics {
+    static TOPICS: OnceLock<Topics> = OnceLock::new();
+    TOPICS.get_or_init(|| {
+        let t = |sig: &str| format!("0x{:x}", keccak256(sig.as_bytes()));
+        Topics {
+            v2_swap: t("Swap(address,uint256,uint256,uint256,uint256,address)"),
+            aerodrome_swap: t("Swap(address,address,uint256,uint256,uint256,uint256)"),
+            v3_swap: t("Swap(address,address,int256,int256,uint160,uint128,int24)"),
+            v3_mint: t("Mint(address,address,int24,int24,uint128,uint256,uint256)"),
+            v3_burn: t("Burn(address,int24,int24,uint128,uint256,uint256)"),
+            transfer: t("Transfer(address,address,uint256)"),
+        }
+    })
+}
+
+/// The `i`th 32-byte word of a log's data.
+fn word(data: &[u8], i: usize) -> Option<U256> {
+    data.get(i * 32..(i + 1) * 32).map(U256::from_be_slice)
+}
+
+fn topic_address(topic: &str) -> Option<String> {
+    (topic.len() >= 40).then(|| format!("0x{}", &topic[topic.len() - 40..]).to_lowercase())
+}
+
+/// Decode a pool event from a log, or `None` for anything else.
+fn pool_event(log: &Log) -> Option<PoolEvent> {
+    let t = topics();
+    let topic0 = log.topics.first()?.to_lowercase();
+    let pool = log.address.to_lowercase();
+    let data = hex::decode(log.data.trim_start_matches("0x")).ok()?;
+
+    if topic0 == t.v2_swap || topic0 == t.aerodrome_swap {
+        // Both layouts carry amount0In, amount1In, amount0Out, amount1Out in data.
+        let zero_for_one = match (word(&data, 0)?.is_zero(), word(&data, 1)?.is_zero()) {
+            (false, true) => true,
+            (true, false) => false,
+            _ => return None,
+        };
+        Some(PoolEvent::Swap { pool, zero_for_one })
+    } else if topic0 == t.v3_swap {
+        // amount0 > 0: the pool received token0.
+        let amount0 = word(&data, 0)?;
+        if amount0.is_zero() {
+            return None;
+        }
+        Some(PoolEvent::Swap { pool, zero_for_one: !amount0.bit(255) })
+    } else if topic0 == t.v3_mint || topic0 == t.v3_burn {
+        let owner = topic_address(log.topics.get(1)?)?;
+        let ticks = (log.topics.get(2)?.to_lowercase(), log.topics.get(3)?.to_lowercase());
+        Some(if topic0 == t.v3_mint {
+            PoolEvent::Mint { pool, owner, ticks }
+        } else {
+            PoolEvent::Burn { pool, owner, ticks }
+        })
+    } else {
+        None
+    }
+}
+
+fn tx_events(tx: &DecodedTx, logs: &[Log]) -> Option<TxEvents> {
+    let mut events = Vec::new();
+    let mut weth_out: HashMap<String, f64> = HashMap::new();
+    let transfer = &topics().transfer;
+    for log in logs {
+        if log.address.eq_ignore_ascii_case(WETH)
+            && log.topics.first().is_some_and(|t| t.eq_ignore_ascii_case(transfer))
+            && let (Some(from), Some(to)) = (
+                log.topics.get(1).and_then(|t| topic_address(t)),
+                log.topics.get(2).and_then(|t| topic_address(t)),
+            )
+        {
+            let data = hex::decode(log.data.trim_start_matches("0x")).unwrap_or_default();
+            let eth = word(&data, 0).map_or(0.0, |v| v.saturating_to::<u128>() as f64 / 1e18);
+            *weth_out.entry(from).or_default() += eth;
+            *weth_out.entry(to).or_default() -= eth;
+        }
+        if let Some(event) = pool_event(log) {
+            events.push(event);
+        }
+    }
+    if events.is_empty() {
+        return None;
+    }
+    Some(TxEvents {
+        hash: tx.hash.clone()?,
+        from: tx.from.clone()?.to_lowercase(),
+        to: tx.to.as_ref().map(|t| t.to_lowercase()),
+        events,
+        weth_out,
+    })
+}
+
+/// Finds MEV patterns across consecutive flashblocks.
+#[derive(Default)]
+pub struct MevDetector {
+    /// Pool-touching transactions of the previous flashblock.
+    previous: Vec<TxEvents>,
+    /// Last flashblock observed, to skip replays after a reconnect.
+    last: Option<(String, u64)>,
+}
+
+impl MevDetector {
+    /// Decode a flashblock's receipt logs and report patterns completed by its
+    /// transactions. `txs` are the flashblock's decoded transactions, in order.
+    pub fn observe(&mut self, fb: &FlashblockMessage, txs: &[DecodedTx]) -> Vec<MevFinding> {
+        if let Some((ref payload, index)) = self.last
+            && *payload == fb.payload_id
+            && fb.index <= index
+        {
+            return Vec::new();
+        }
+        self.last = Some((fb.payload_id.clone(), fb.index));
+
+        let logs = fb.receipt_logs();
+        let current: Vec<TxEvents> = txs
+            .iter()
+            .filter_map(|tx| {
+                let hash = tx.hash.as_ref()?.to_lowercase();
+                tx_events(tx, logs.get(&hash)?)
+            })
+            .collect();
+
+        let start = self.previous.len();
+        let mut window = std::mem::take(&mut self.previous);
+        window.extend(current);
+        let findings = detect(&window, start);
+        self.previous = window.split_off(start);
+        findings
+    }
+}
+
+/// Patterns whose closing transaction is at or after `start` in `window`.
+fn detect(window: &[TxEvents], start: usize) -> Vec<MevFinding> {
+    let known = crate::decode::known_addresses();
+    let mut findings = Vec::new();
+    for j in start..window.len() {
+        let found = sandwich(window, j, &known).or_else(|| backrun(window, j, &known));
+        findings.extend(found);
+        findings.extend(jit(window, j, &known));
+    }
+    findings
+}
+
+/// Same searcher: same sender, or the same bot contract.
+fn same_searcher(a: &TxEvents, b: &TxEvents, known: &HashMap<&str, AddressLabel>) -> bool {
+    a.from == b.from || (a.to.is_some() && a.to == b.to && bot(a, known).is_some())
+}
+
+/// The contract a searcher's transaction calls, unless it's a known router or token.
+fn bot(tx: &TxEvents, known: &HashMap<&str, AddressLabel>) -> Option<String> {
+    tx.to.clone().filter(|to| !known.contains_key(to.as_str()))
+}
+
+/// Net WETH taken out of `pools` by `txs`, if any WETH moved through them.
+fn weth_profit<'a>(txs: impl IntoIterator<Item = &'a TxEvents>, pools: &[&str]) -> Option<f64> {
+    let flows: Vec<f64> = txs
+        .into_iter()
+        .flat_map(|tx| pools.iter().filter_map(|p| tx.weth_out.get(*p).copied()))
+        .collect();
+    (!flows.is_empty()).then(|| flows.iter().sum())
+}
+
+/// `window[j]` is the back-run: it swaps a pool against an earlier victim swap that
+/// the same searcher front-ran in the victim's direction.
+fn sandwich(window: &[TxEvents], j: usize, known: &HashMap<&str, AddressLabel>) -> Option<MevFinding> {
+    let back = &window[j];
+    for (pool, dir) in back.swaps() {
+        for v in (0..j).rev() {
+            let victim = &window[v];
+            if same_searcher(victim, back, known) || victim.swap_on(pool) != Some(!dir) {
+                continue;
+            }
+            let front = window[..v]
+                .iter()
+                .rev()
+                .find(|a| same_searcher(a, back, known) && a.swap_on(pool) == Some(!dir));
+            if let Some(front) = front {
+                return Some(MevFinding {
+                    kind: MevKind::Sandwich,
+                    pool: pool.to_string(),
+                    attacker: front.from.clone(),
+                    bot: bot(front, known),
+                    victim: victim.from.clone(),
+                    victim_tx: victim.hash.clone(),
+                    attacker_txs: vec![front.hash.clone(), back.hash.clone()],
+                    profit_eth: weth_profit([front, back], &[pool]),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// `window[j]` is a multi-pool arbitrage through a bot contract that trades against
+/// the swap just before it on one of its pools and ends up with more WETH.
+fn backrun(window: &[TxEvents], j: usize, known: &HashMap<&str, AddressLabel>) -> Option<MevFinding> {
+    let arb = &window[j];
+    let mut pools: Vec<&str> = Vec::new();
+    for (pool, _) in arb.swaps() {
+        if !pools.contains(&pool) {
+            pools.push(pool);
+        }
+    }
+    if pools.len() < 2 || bot(arb, known).is_none() {
+        return None;
+    }
+    let profit = weth_profit([arb], &pools).filter(|p| *p > 0.0)?;
+    let (victim, pool) = window[..j].iter().rev().find_map(|v| {
+        let pool = pools.iter().find(|p| v.swap_on(p).is_some())?;
+        Some((v, *pool))
+    })?;
+    if same_searcher(victim, arb, known) || victim.swap_on(pool) == arb.swap_on(pool) {
+        return None;
+    }
+    Some(MevFinding {
+        kind: MevKind::Backrun,
+        pool: pool.to_string(),
+        attacker: arb.from.clone(),
+        bot: bot(arb, known),
+        victim: victim.from.clone(),
+        victim_tx: victim.hash.clone(),
+        attacker_txs: vec![arb.hash.clone()],
+        profit_eth: Some(profit),
+    })
+}
+
+/// `window[j]` burns a position that the same owner minted on the same ticks
+/// just before another trader's swap on that pool.
+fn jit(window: &[TxEvents], j: usize, known: &HashMap<&str, AddressLabel>) -> Option<MevFinding> {
+    let burn_tx = &window[j];
+    for event in &burn_tx.events {
+        let PoolEvent::Burn { pool, owner, ticks } = event else { continue };
+        let Some(m) = (0..j).rev().find(|&m| {
+            window[m].events.iter().any(|e| {
+                matches!(e, PoolEvent::Mint { pool: p, owner: o, ticks: t } if p == pool && o == owner && t == ticks)
+            })
+        }) else {
+            continue;
+        };
+        let mint_tx = &window[m];
+        let victim = window[m + 1..j]
+            .iter()
+            .find(|v| v.swap_on(pool).is_some() && !same_searcher(v, mint_tx, known));
+        if let Some(victim) = victim {
+            return Some(MevFinding {
+                kind: MevKind::Jit,
+                pool: pool.clone(),
+                attacker: mint_tx.from.clone(),
+                bot: bot(mint_tx, known),
+                victim: victim.from.clone(),
+                victim_tx: victim.hash.clone(),
+                attacker_txs: vec![mint_tx.hash.clone(), burn_tx.hash.clone()],
+                profit_eth: weth_profit([mint_tx, burn_tx], &[pool]),
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::Category;
+
+    const POOL: &str = "0x00000000000000000000000000000000000000f1";
+    const BOT: &str = "0x00000000000000000000000000000000000000b0";
+
+    fn tx(hash: &str, from: &str, to: &str) -> DecodedTx {
+        DecodedTx {
+            hash: Some(hash.into()),
+            from: Some(from.into()),
+            to: Some(to.into()),
+            to_label: None,
+            value_wei: 0,
+            value_eth: 0.0,
+            action: None,
+            category: Category::Unknown,
+            gas_used: None,
+            contract_creation: None,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_per_gas: 0,
+            max_priority_fee_per_gas: 0,
+            median_tip_wei: None,
+            approval: None,
+        }
+    }
+
+    fn word_hex(v: i128) -> String {
+        let w = if v >= 0 { U256::from(v as u128) } else { U256::ZERO.wrapping_sub(U256::from(v.unsigned_abs())) };
+        format!("{:064x}", w)
+    }
+
+    fn topic(addr: &str) -> String {
+        format!("0x{:0>64}", addr.trim_start_matches("0x"))
+    }
+
+    fn v3_swap(pool: &str, amount0: i128) -> serde_json::Value {
+        serde_json::json!({
+            "address": pool,
+            "topics": [topics().v3_swap, topic(BOT), topic(BOT)],
+            "data": format!("0x{}{}{}", word_hex(amount0), word_hex(-amount0), "0".repeat(64 * 3)),
+        })
+    }
+
+    fn weth(from: &str, to: &str, eth: f64) -> serde_json::Value {
+        serde_json::json!({
+            "address": WETH,
+            "topics": [topics().transfer, topic(from), topic(to)],
+            "data": format!("0x{}", word_hex((eth * 1e18) as i128)),
+        })
+    }
+
+    fn liquidity(sig: &str, pool: &str, owner: &str) -> serde_json::Value {
+        let t = if sig == "mint" { &topics().v3_mint } else { &topics().v3_burn };
+        serde_json::json!({
+            "address": pool,
+            "topics": [t, topic(owner), word_hex(-10), word_hex(10)],
+            "data": format!("0x{}", "0".repeat(64 * 3)),
+        })
+    }
+
+    fn flashblock(index: u64, receipts: serde_json::Value) -> FlashblockMessage {
+        serde_json::from_value(serde_json::json!({
+            "payload_id": "p", "index": index, "diff": {},
+            "metadata": {"receipts": receipts},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sandwich_across_flashblocks_and_jit() {
+        let router = "0x2626664c2603336e57b271c5c0b26f421741e481";
+        let mut detector = MevDetector::default();
+
+        let fb0 = flashblock(0, serde_json::json!({
+            "0xa1": {"Eip1559": {"logs": [weth(BOT, POOL, 1.0), v3_swap(POOL, 100)]}},
+            "0xc1": {"Eip1559": {"logs": [v3_swap(POOL, 50)]}},
+        }));
+        let txs0 = [tx("0xa1", "0xaaaa", BOT), tx("0xc1", "0xcccc", router)];
+        assert!(detector.observe(&fb0, &txs0).is_empty());
+
+        let jit_bot = "0x00000000000000000000000000000000000000d0";
+        let jit_pool = "0x00000000000000000000000000000000000000f2";
+        let fb1 = flashblock(1, serde_json::json!({
+            "0xa2": {"Eip1559": {"logs": [v3_swap(POOL, -100), weth(POOL, BOT, 1.05)]}},
+            "0xd1": {"Eip1559": {"logs": [liquidity("mint", jit_pool, jit_bot)]}},
+            "0xe1": {"Eip1559": {"logs": [v3_swap(jit_pool, 10)]}},
+            "0xd2": {"Eip1559": {"logs": [liquidity("burn", jit_pool, jit_bot)]}},
+        }));
+        let txs1 = [
+            tx("0xa2", "0xaaaa", BOT),
+            tx("0xd1", "0xdddd", jit_bot),
+            tx("0xe1", "0xeeee", router),
+            tx("0xd2", "0xdddd", jit_bot),
+        ];
+        let findings = detector.observe(&fb1, &txs1);
+        assert_eq!(findings.len(), 2);
+
+        let sandwich = &findings[0];
+        assert_eq!(sandwich.kind, MevKind::Sandwich);
+        assert_eq!((sandwich.attacker.as_str(), sandwich.victim.as_str()), ("0xaaaa", "0xcccc"));
+        assert_eq!(sandwich.attacker_txs, ["0xa1", "0xa2"]);
+        assert!((sandwich.profit_eth.unwrap() - 0.05).abs() < 1e-9);
+        assert_eq!(sandwich.bot.as_deref(), Some(BOT));
+
+        let jit = &findings[1];
+        assert_eq!(jit.kind, MevKind::Jit);
+        assert_eq!((jit.pool.as_str(), jit.victim_tx.as_str()), (jit_pool, "0xe1"));
+        assert_eq!(jit.profit_eth, None);
+
+        // A replayed flashblock is ignored.
+        assert!(detector.observe(&fb1, &txs1).is_empty());
+    }
+
+    #[test]
+    fn test_backrun_needs_two_pools_weth_profit_and_the_opposite_direction() {
+        let router = "0x2626664c2603336e57b271c5c0b26f421741e481";
+        let pool2 = "0x00000000000000000000000000000000000000f2";
+        // The victim swaps token0 in on POOL; the arb then trades POOL the other way and
+        // closes the loop on its last pool, paying in 1 WETH and taking `weth_back` out.
+        let detect = |victim_from: &str, arb_to: &str, arb_amount0: i128, pools: &[&str], weth_back: f64| {
+            let mut arb_logs = vec![weth(BOT, pools[pools.len() - 1], 1.0)];
+            arb_logs.extend(pools.iter().map(|p| v3_swap(p, if *p == POOL { arb_amount0 } else { 60 })));
+            arb_logs.push(weth(POOL, BOT, weth_back));
+            let fb = flashblock(0, serde_json::json!({
+                "0xc1": {"Eip1559": {"logs": [v3_swap(POOL, 100)]}},
+                "0xb1": {"Eip1559": {"logs": arb_logs}},
+            }));
+            let txs = [tx("0xc1", victim_from, router), tx("0xb1", "0xbbbb", arb_to)];
+            MevDetector::default().observe(&fb, &txs)
+        };
+
+        let findings = detect("0xcccc", BOT, -80, &[POOL, pool2], 1.02);
+        assert_eq!(findings.len(), 1);
+        let backrun = &findings[0];
+        assert_eq!(backrun.kind, MevKind::Backrun);
+        assert_eq!((backrun.pool.as_str(), backrun.victim_tx.as_str()), (POOL, "0xc1"));
+        assert_eq!((backrun.attacker.as_str(), backrun.victim.as_str()), ("0xbbbb", "0xcccc"));
+        assert_eq!(backrun.attacker_txs, ["0xb1"]);
+        assert_eq!(backrun.bot.as_deref(), Some(BOT));
+        assert!((backrun.profit_eth.unwrap() - 0.02).abs() < 1e-9);
+
+        // Trading with the victim rather than against it.
+        assert!(detect("0xcccc", BOT, 80, &[POOL, pool2], 1.02).is_empty());
+        // Ending with less WETH than it started with.
+        assert!(detect("0xcccc", BOT, -80, &[POOL, pool2], 0.98).is_empty());
+        // A single pool, or a swap through a known router, isn't an arbitrage.
+        assert!(detect("0xcccc", BOT, -80, &[POOL], 1.02).is_empty());
+        assert!(detect("0xcccc", router, -80, &[POOL, pool2], 1.02).is_empty());
+        // The same searcher on both sides is rebalancing, not backrunning someone.
+        assert!(detect("0xbbbb", BOT, -80, &[POOL, pool2], 1.02).is_empty());
+    }
+}
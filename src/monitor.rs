@@ -11,10 +11,10 @@ use tracing::{debug, info};
 use crate::types::{FlashblockMessage, FlashblockMetrics};
 
 fn decode_message(data: &[u8]) -> Option<String> {
-    if let Ok(text) = std::str::from_utf8(data) {
-        if text.trim_start().starts_with('{') {
-            return Some(text.to_owned());
-        }
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.trim_start().starts_with('{')
+    {
+        return Some(text.to_owned());
     }
     let mut decompressor = brotli::Decompressor::new(data, 4096);
     let mut decompressed = Vec::new();
@@ -26,6 +26,14 @@ pub async fn call<T: serde::de::DeserializeOwned>(
     resp.result.ok_or_else(|| eyre::eyre!("Empty RPC response"))
 }
 
+/// Whether `err` is the node saying it doesn't serve the method at all, as opposed to a
+/// transient failure worth retrying on the next call.
+pub fn is_method_not_found(err: &eyre::Report) -> bool {
+    let msg = err.to_string().to_lowercase();
+    msg.starts_with("rpc error -32601:")
+        || ["method not found", "does not exist", "not supported"].iter().any(|m| msg.contains(m))
+}
+
 /// Display chain info.
 pub async fn info(rpc_url: &str) -> eyre::Result<()> {
     println!("{}", "Base Chain Info".bold().cyan());
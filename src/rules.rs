@@ -1,14 +1,22 @@
-//! Rule-based alert system — parse TOML configs and match against decoded transactions.
+//! Rule-based alert system — parse TOML configs and match against decoded transactions,
+//! the block being built (block-scope triggers), account balance changes, MEV patterns
+//! and risky token approvals.
 
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
-use crate::decode::{Category, DecodedTx};
+use crate::decode::{ApprovalKind, Category, ContractCreation, DecodedTx};
+use crate::mev::{MevDetector, MevFinding, MevKind};
+use crate::security::{AddressActivity, SecurityFinding};
+use crate::sink::{Destination, SecretRef, SinkKind, WebhookAuth};
+use crate::template::TemplateConfig;
+use crate::types::{BlockState, FlashblockMessage};
 
 /// Top-level rules config file.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RulesConfig {
     #[serde(default)]
     pub rules: Vec<Rule>,
@@ -16,11 +24,36 @@ pub struct RulesConfig {
     pub global: GlobalConfig,
     /// Known address labels. Keys are lowercase hex addresses, values are human-readable names.
     /// Add your own to rules.toml under [labels].
-    #[serde(default)]
+    #[serde(default, serialize_with = "sorted_map")]
     pub labels: HashMap<String, String>,
+    /// Message templates rules can refer to by name (see `template`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, TemplateConfig>,
+    /// Scheduled email summaries of stored alerts (see `email`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub digests: BTreeMap<String, DigestConfig>,
+}
+
+/// A periodic email of alert counts and the largest alerts, under `[digests.<name>]`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DigestConfig {
+    /// `smtp://` or `smtps://` URL with `from` and `to`, as for a rule with
+    /// `sink = "email"`, or an `env:`/`file:` reference to one.
+    pub webhook: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<WebhookAuth>,
+    /// Period in minutes. Digests go out when a period ends, on UTC boundaries.
+    #[serde(default = "default_digest_mins")]
+    pub every_mins: u64,
+    /// Rules to summarise (empty = all).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+    /// Alerts listed, largest `value_eth` first.
+    #[serde(default = "default_digest_top")]
+    pub top: usize,
 }
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 pub struct GlobalConfig {
     /// Default cooldown between fires of the same rule (seconds).
     #[serde(default = "default_cooldown")]
@@ -31,31 +64,606 @@ pub struct GlobalConfig {
     /// Batch window in seconds (0 = fire immediately).
     #[serde(default)]
     pub batch_secs: u64,
+    /// Flush a batch early once it holds this many alerts.
+    #[serde(default = "default_max_batch")]
+    pub max_batch: usize,
+    /// Merge alerts for the same tx into one and never alert on a tx hash twice.
+    #[serde(default)]
+    pub dedup: bool,
     /// Alert retention in days (auto-prune older alerts).
     #[serde(default = "default_retention")]
     pub retention_days: u64,
+    /// Signing secret per sink type, for rules without their own `signing_secret`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub signing_secrets: BTreeMap<SinkKind, SecretRef>,
+    /// Credentials per sink type, for rules without their own `auth`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub auth: BTreeMap<SinkKind, WebhookAuth>,
+}
+
+impl RulesConfig {
+    /// Parse and validate a rules file. Template files are read relative to the
+    /// working directory; use `from_file` for a rules file elsewhere.
+    pub fn from_toml(toml_str: &str) -> eyre::Result<Self> {
+        let mut config: RulesConfig = toml::from_str(toml_str)?;
+        config.load_templates(Path::new(""))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read, parse and validate a rules file, and its template files.
+    pub fn from_file(path: &Path) -> eyre::Result<Self> {
+        let config = Self::load(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read and parse a rules file and its template files, without validating.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let mut config: RulesConfig = toml::from_str(&std::fs::read_to_string(path)?)?;
+        config.load_templates(path.parent().unwrap_or(Path::new("")))?;
+        Ok(config)
+    }
+
+    fn load_templates(&mut self, base: &Path) -> eyre::Result<()> {
+        for (name, template) in &mut self.templates {
+            template.load(name, base)?;
+        }
+        Ok(())
+    }
+
+    /// Write `next`'s rules into `source`, the TOML this config was loaded from —
+    /// used when rules are edited through the API. Only the `[[rules]]` tables of
+    /// rules that were added, changed or removed are rewritten; comments, key order
+    /// and everything else in the file stay as written. New rules go at the end.
+    pub fn update_toml(&self, source: &str, next: &RulesConfig) -> eyre::Result<String> {
+        let mut doc: toml_edit::DocumentMut = source.parse()?;
+        let mut position = max_position(doc.as_table());
+        // Comments after the last table belong to it, so they go before any appended rule.
+        let mut trailing = doc.trailing().as_str().unwrap_or_default().to_string();
+        if !doc.contains_key("rules") {
+            doc.insert("rules", toml_edit::Item::ArrayOfTables(Default::default()));
+        }
+        let Some(tables) = doc.get_mut("rules").and_then(toml_edit::Item::as_array_of_tables_mut) else {
+            eyre::bail!("`rules` is not a list of [[rules]] tables");
+        };
+        let index_of = |tables: &toml_edit::ArrayOfTables, name: &str| {
+            tables.iter().position(|t| t.get("name").and_then(|n| n.as_str()) == Some(name))
+        };
+
+        for rule in self.rules.iter().filter(|r| !next.rules.iter().any(|n| n.name == r.name)) {
+            if let Some(i) = index_of(tables, &rule.name) {
+                tables.remove(i);
+            }
+        }
+        for rule in next.rules.iter().filter(|r| !self.rules.contains(r)) {
+            let mut table = toml::to_string(rule)?.parse::<toml_edit::DocumentMut>()?.as_table().clone();
+            match index_of(tables, &rule.name).and_then(|i| tables.get_mut(i)) {
+                Some(old) => {
+                    // Keep the comments above the rule and its sub-tables, and its place in the file.
+                    *table.decor_mut() = old.decor().clone();
+                    for (key, item) in table.iter_mut() {
+                        if let (Some(new), Some(old)) = (item.as_table_mut(), old.get(&key).and_then(|i| i.as_table())) {
+                            *new.decor_mut() = old.decor().clone();
+                        }
+                    }
+                    set_position(&mut table, old.position().unwrap_or(position));
+                    *old = table;
+                }
+                None => {
+                    position += 1;
+                    set_position(&mut table, position);
+                    table.decor_mut().set_prefix(format!("{}\n", std::mem::take(&mut trailing)));
+                    tables.push(table);
+                }
+            }
+        }
+        doc.set_trailing(trailing);
+        Ok(doc.to_string())
+    }
+
+    /// Reject configs that would load but can never behave as intended.
+    pub fn validate(&self) -> eyre::Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for rule in &self.rules {
+            if rule.name.trim().is_empty() {
+                eyre::bail!("rule with empty name");
+            }
+            if !rule.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+                eyre::bail!("rule name '{}' may only contain letters, digits, '_', '.' and '-'", rule.name);
+            }
+            if rule.name == "schema" {
+                eyre::bail!("'schema' is reserved for GET /api/rules/schema — pick another rule name");
+            }
+            if !seen.insert(rule.name.as_str()) {
+                eyre::bail!("duplicate rule name '{}'", rule.name);
+            }
+            match &rule.trigger {
+                Trigger::Address { address, .. } => check_address(&rule.name, address)?,
+                Trigger::ContractCreation { deployers, code_hashes } => {
+                    for d in deployers {
+                        check_address(&rule.name, d)?;
+                    }
+                    for h in code_hashes {
+                        if !is_hex_of_len(h, 32) {
+                            eyre::bail!("rule '{}': '{}' is not a 32-byte hex hash", rule.name, h);
+                        }
+                    }
+                }
+                Trigger::Gas { min_priority_fee_gwei, min_max_fee_gwei, min_gas_limit, min_tip_multiple, .. }
+                    if min_priority_fee_gwei.is_none()
+                        && min_max_fee_gwei.is_none()
+                        && min_gas_limit.is_none()
+                        && min_tip_multiple.is_none() =>
+                {
+                    eyre::bail!("rule '{}': gas trigger needs at least one fee or gas condition", rule.name);
+                }
+                Trigger::BlockGas { min_gas_used_pct } if !(*min_gas_used_pct > 0.0 && *min_gas_used_pct <= 100.0) => {
+                    eyre::bail!("rule '{}': min_gas_used_pct {} must be in (0, 100]", rule.name, min_gas_used_pct);
+                }
+                Trigger::BaseFeeChange { min_change_pct } if *min_change_pct <= 0.0 => {
+                    eyre::bail!("rule '{}': min_change_pct must be positive", rule.name);
+                }
+                Trigger::BalanceChange { min_delta_eth, .. } if *min_delta_eth <= 0.0 => {
+                    eyre::bail!("rule '{}': min_delta_eth must be positive", rule.name);
+                }
+                Trigger::Approval { min_risk, owners, .. } => {
+                    if *min_risk > 100 {
+                        eyre::bail!("rule '{}': min_risk {} must be at most 100", rule.name, min_risk);
+                    }
+                    for o in owners {
+                        check_address(&rule.name, o)?;
+                    }
+                }
+                _ => {}
+            }
+            if let Some(ref name) = rule.template
+                && !self.templates.contains_key(name)
+            {
+                eyre::bail!("rule '{}': no template named '{}'", rule.name, name);
+            }
+            if let Some((name, _)) = rule.headers.iter().find(|(k, v)| looks_secret(k) && !SecretRef::is_reference(v)) {
+                eyre::bail!("rule '{}': header '{}' looks like a credential — reference it with env:NAME or file:/path", rule.name, name);
+            }
+            if let Some(ref url) = rule.webhook
+                && !matches!(rule.sink, SinkKind::Email | SinkKind::Exec)
+                && !SecretRef::is_reference(url)
+                && url_has_credential(url)
+            {
+                eyre::bail!("rule '{}': webhook URL contains a token — reference it with env:NAME or file:/path", rule.name);
+            }
+            for value in rule.headers.values().chain(rule.webhook.as_ref()).filter(|v| SecretRef::is_reference(v)) {
+                SecretRef::parse(value).map_err(|e| eyre::eyre!("rule '{}': {}", rule.name, e))?;
+            }
+            if rule.sink == SinkKind::Exec
+                && let Some(ref url) = rule.webhook
+            {
+                crate::exec::ExecTarget::parse(url).map_err(|e| eyre::eyre!("rule '{}': {}", rule.name, e))?;
+            }
+            // An unparseable SMTP URL is reported by `lint`.
+            if rule.sink == SinkKind::Email
+                && let Some(ref url) = rule.webhook
+                && !SecretRef::is_reference(url)
+                && let Ok(mail) = crate::email::MailTarget::parse(url)
+            {
+                mail.check_login(rule.auth.is_some()).map_err(|e| eyre::eyre!("rule '{}': {}", rule.name, e))?;
+            }
+            if let Some(ref schedule) = rule.schedule {
+                if let Some([start, end]) = schedule.hours
+                    && (start > 23 || end > 24 || start == end)
+                {
+                    eyre::bail!("rule '{}': schedule hours [{}, {}] must be distinct UTC hours 0-24", rule.name, start, end);
+                }
+                if let Some(day) = schedule.days.iter().find(|d| !WEEKDAYS.contains(&d.to_lowercase().as_str())) {
+                    eyre::bail!("rule '{}': unknown schedule day '{}' (use {})", rule.name, day, WEEKDAYS.join(", "));
+                }
+            }
+        }
+        for (name, digest) in &self.digests {
+            if digest.every_mins == 0 {
+                eyre::bail!("digest '{}': every_mins must be positive", name);
+            }
+            if let Some(rule) = digest.rules.iter().find(|r| !self.rules.iter().any(|x| &x.name == *r)) {
+                eyre::bail!("digest '{}': no rule named '{}'", name, rule);
+            }
+            if matches!(digest.auth, Some(WebhookAuth::Bearer(_))) {
+                eyre::bail!("digest '{}': email auth must be basic", name);
+            }
+            if SecretRef::is_reference(&digest.webhook) {
+                SecretRef::parse(&digest.webhook).map_err(|e| eyre::eyre!("digest '{}': {}", name, e))?;
+            } else {
+                crate::email::MailTarget::parse(&digest.webhook)
+                    .and_then(|mail| mail.check_login(digest.auth.is_some()))
+                    .map_err(|e| eyre::eyre!("digest '{}': {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Human-readable summary of what changes between `self` and `next`.
+    pub fn diff(&self, next: &RulesConfig) -> Vec<String> {
+        let mut lines = Vec::new();
+        for rule in &self.rules {
+            match next.rules.iter().find(|r| r.name == rule.name) {
+                None => lines.push(format!("- rule {}", rule.name)),
+                Some(new) if new != rule => {
+                    let mut fields = Vec::new();
+                    if new.enabled != rule.enabled {
+                        fields.push(format!("enabled {} → {}", rule.enabled, new.enabled));
+                    }
+                    if new.trigger != rule.trigger {
+                        fields.push(format!("trigger {:?} → {:?}", rule.trigger, new.trigger));
+                    }
+                    if new.webhook != rule.webhook {
+                        fields.push("webhook".to_string());
+                    }
+                    if new.sink != rule.sink {
+                        fields.push(format!("sink {} → {}", rule.sink.as_str(), new.sink.as_str()));
+                    }
+                    if new.headers != rule.headers {
+                        fields.push("headers".to_string());
+                    }
+                    if new.auth != rule.auth {
+                        fields.push("auth".to_string());
+                    }
+                    if new.signing_secret != rule.signing_secret {
+                        fields.push("signing_secret".to_string());
+                    }
+                    if new.template != rule.template {
+                        fields.push(format!("template {:?} → {:?}", rule.template, new.template));
+                    }
+                    if new.cooldown_secs != rule.cooldown_secs {
+                        fields.push(format!("cooldown_secs {:?} → {:?}", rule.cooldown_secs, new.cooldown_secs));
+                    }
+                    if new.severity != rule.severity {
+                        fields.push(format!("severity {:?} → {:?}", rule.severity, new.severity));
+                    }
+                    if new.priority != rule.priority {
+                        fields.push(format!("priority {} → {}", rule.priority, new.priority));
+                    }
+                    if new.max_per_minute != rule.max_per_minute {
+                        fields.push(format!("max_per_minute {:?} → {:?}", rule.max_per_minute, new.max_per_minute));
+                    }
+                    if new.batch_secs != rule.batch_secs {
+                        fields.push(format!("batch_secs {:?} → {:?}", rule.batch_secs, new.batch_secs));
+                    }
+                    if new.schedule != rule.schedule {
+                        fields.push(format!("schedule {:?} → {:?}", rule.schedule, new.schedule));
+                    }
+                    lines.push(format!("~ rule {}: {}", rule.name, fields.join(", ")));
+                }
+                Some(_) => {}
+            }
+        }
+        for rule in &next.rules {
+            if !self.rules.iter().any(|r| r.name == rule.name) {
+                lines.push(format!("+ rule {} ({:?})", rule.name, rule.trigger));
+            }
+        }
+        if self.global != next.global {
+            lines.push(format!("~ global {:?} → {:?}", self.global, next.global));
+        }
+        for (name, template) in &next.templates {
+            match self.templates.get(name) {
+                None => lines.push(format!("+ template {}", name)),
+                Some(old) if old != template => lines.push(format!("~ template {}", name)),
+                Some(_) => {}
+            }
+        }
+        for name in self.templates.keys().filter(|n| !next.templates.contains_key(*n)) {
+            lines.push(format!("- template {}", name));
+        }
+        for (name, digest) in &next.digests {
+            match self.digests.get(name) {
+                None => lines.push(format!("+ digest {} (every {}m)", name, digest.every_mins)),
+                Some(old) if old != digest => lines.push(format!("~ digest {}", name)),
+                Some(_) => {}
+            }
+        }
+        for name in self.digests.keys().filter(|n| !next.digests.contains_key(*n)) {
+            lines.push(format!("- digest {}", name));
+        }
+        if self.labels != next.labels {
+            let added = next.labels.keys().filter(|k| !self.labels.contains_key(*k)).count();
+            let removed = self.labels.keys().filter(|k| !next.labels.contains_key(*k)).count();
+            lines.push(format!("~ labels: +{} -{}", added, removed));
+        }
+        lines
+    }
+}
+
+/// JSON schema for a rule body as accepted by the `/api/rules/{name}` endpoints.
+/// Each trigger kind is one `oneOf` branch, discriminated by `kind`.
+pub fn rule_schema() -> serde_json::Value {
+    use serde_json::json;
+
+    let eth = json!({"type": "number", "minimum": 0});
+    let strings = json!({"type": "array", "items": {"type": "string"}});
+    let address = json!({"type": "string", "pattern": "^0x[0-9a-fA-F]{40}$"});
+    let secret = json!({"type": "string", "pattern": "^(env|file):.+"});
+    let kind = |name: &str, props: serde_json::Value, required: &[&str]| {
+        let mut properties = json!({"kind": {"const": name}});
+        if let (Some(all), Some(extra)) = (properties.as_object_mut(), props.as_object()) {
+            all.extend(extra.clone());
+        }
+        let mut req = vec!["kind"];
+        req.extend_from_slice(required);
+        json!({"type": "object", "properties": properties, "required": req, "additionalProperties": false})
+    };
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "flashwatch rule",
+        "type": "object",
+        "properties": {
+            "name": {"type": "string", "pattern": "^[A-Za-z0-9_.-]+$"},
+            "enabled": {"type": "boolean", "default": true},
+            "webhook": {"type": "string", "description": "http(s) URL, or env:NAME / file:/path holding one"},
+            "sink": {"enum": crate::sink::SinkKind::ALL.map(|k| k.as_str()), "default": "openclaw"},
+            "headers": {"type": "object", "additionalProperties": {"type": "string"}},
+            "auth": {"oneOf": [
+                {"type": "object", "properties": {"bearer": secret}, "required": ["bearer"], "additionalProperties": false},
+                {"type": "object", "properties": {"basic": {
+                    "type": "object",
+                    "properties": {"username": {"type": "string"}, "password": secret},
+                    "required": ["username", "password"],
+                    "additionalProperties": false,
+                }}, "required": ["basic"], "additionalProperties": false},
+            ]},
+            "signing_secret": secret,
+            "template": {"type": "string", "description": "name of a [templates] entry"},
+            "cooldown_secs": {"type": "integer", "minimum": 0},
+            "severity": {"enum": ["info", "warn", "critical"], "default": "warn"},
+            "priority": {"type": "integer", "default": 0},
+            "max_per_minute": {"type": "integer", "minimum": 0},
+            "batch_secs": {"type": "integer", "minimum": 0},
+            "schedule": {"type": "object", "additionalProperties": false, "properties": {
+                "hours": {"type": "array", "items": {"type": "integer", "minimum": 0, "maximum": 24}, "minItems": 2, "maxItems": 2},
+                "days": {"type": "array", "items": {"enum": WEEKDAYS}},
+            }},
+            "trigger": {"oneOf": [
+                kind("eth_transfer", json!({"min_eth": eth}), &[]),
+                kind("protocol", json!({"names": strings, "categories": strings, "min_eth": eth}), &[]),
+                kind("function_call", json!({"actions": strings, "min_eth": eth}), &["actions"]),
+                kind("large_value", json!({"min_eth": eth}), &["min_eth"]),
+                kind("address", json!({"address": address, "min_eth": eth}), &["address"]),
+                kind("contract_creation", json!({
+                    "deployers": {"type": "array", "items": address},
+                    "code_hashes": {"type": "array", "items": {"type": "string", "pattern": "^0x[0-9a-fA-F]{64}$"}},
+                }), &[]),
+                kind("gas", json!({
+                    "min_priority_fee_gwei": {"type": "number", "minimum": 0},
+                    "min_max_fee_gwei": {"type": "number", "minimum": 0},
+                    "min_gas_limit": {"type": "integer", "minimum": 0},
+                    "min_tip_multiple": {"type": "number", "minimum": 0},
+                    "min_eth": eth,
+                }), &[]),
+                kind("block_gas", json!({"min_gas_used_pct": {"type": "number", "exclusiveMinimum": 0, "maximum": 100}}), &["min_gas_used_pct"]),
+                kind("flashblock_txs", json!({"min_txs": {"type": "integer", "minimum": 1}}), &["min_txs"]),
+                kind("base_fee_change", json!({"min_change_pct": {"type": "number", "exclusiveMinimum": 0}}), &["min_change_pct"]),
+                kind("flashblock_count", json!({"min_flashblocks": {"type": "integer", "minimum": 1}}), &["min_flashblocks"]),
+                kind("mev", json!({
+                    "patterns": {"type": "array", "items": {"enum": ["sandwich", "backrun", "jit"]}},
+                    "min_profit_eth": eth,
+                }), &[]),
+                kind("balance_change", json!({
+                    "min_delta_eth": {"type": "number", "exclusiveMinimum": 0},
+                    "direction": {"enum": ["any", "increase", "decrease"], "default": "any"},
+                }), &["min_delta_eth"]),
+                kind("approval", json!({
+                    "min_risk": {"type": "integer", "minimum": 0, "maximum": 100},
+                    "kinds": {"type": "array", "items": {"enum": ["approve", "approval_for_all", "permit2_permit", "permit2_transfer"]}},
+                    "owners": {"type": "array", "items": address},
+                }), &[]),
+            ]},
+        },
+        "required": ["trigger"],
+    })
+}
+
+/// Serialize labels in address order so rewritten rules files diff cleanly.
+fn sorted_map<S: serde::Serializer>(map: &HashMap<String, String>, s: S) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<std::collections::BTreeMap<_, _>>().serialize(s)
+}
+
+/// Webhook URLs whose path or userinfo is itself the credential.
+fn url_has_credential(url: &str) -> bool {
+    const TOKEN_URLS: [&str; 3] = ["hooks.slack.com/services/", "/api/webhooks/", "api.telegram.org/bot"];
+    TOKEN_URLS.iter().any(|t| url.contains(t))
+        || reqwest::Url::parse(url).is_ok_and(|u| !u.username().is_empty() || u.password().is_some())
+}
+
+/// Header names that conventionally carry credentials.
+fn looks_secret(header: &str) -> bool {
+    let h = header.to_ascii_lowercase();
+    ["auth", "token", "key", "secret", "signature"].iter().any(|w| h.contains(w))
+}
+
+/// The last position of any table in a parsed document, so appended tables sort after it.
+fn max_position(table: &toml_edit::Table) -> usize {
+    let mut max = table.position().unwrap_or(0);
+    for (_, item) in table.iter() {
+        match item {
+            toml_edit::Item::Table(t) => max = max.max(max_position(t)),
+            toml_edit::Item::ArrayOfTables(a) => max = a.iter().map(max_position).fold(max, usize::max),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Place a table and its sub-tables at `position` in the document's output order.
+fn set_position(table: &mut toml_edit::Table, position: usize) {
+    table.set_position(position);
+    for (_, item) in table.iter_mut() {
+        if let Some(t) = item.as_table_mut() {
+            set_position(t, position);
+        }
+    }
+}
+
+fn check_address(rule: &str, addr: &str) -> eyre::Result<()> {
+    if !is_hex_of_len(addr, 20) {
+        eyre::bail!("rule '{}': '{}' is not a 20-byte hex address", rule, addr);
+    }
+    Ok(())
+}
+
+/// Whether `s` is a 0x-prefixed 20-byte hex address.
+pub fn is_address(s: &str) -> bool {
+    is_hex_of_len(s, 20)
+}
+
+fn is_hex_of_len(s: &str, len: usize) -> bool {
+    s.strip_prefix("0x")
+        .and_then(|h| hex::decode(h).ok())
+        .is_some_and(|b| b.len() == len)
 }
 
 fn default_cooldown() -> u64 { 10 }
 fn default_rate_limit() -> u64 { 30 }
 fn default_retention() -> u64 { 30 }
+fn default_max_batch() -> usize { 20 }
+fn default_digest_mins() -> u64 { 60 }
+fn default_digest_top() -> usize { 10 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Rule {
     pub name: String,
     pub trigger: Trigger,
-    /// Webhook URL to POST to (optional — if absent, just logs).
+    /// Webhook URL to POST to (optional — if absent, just logs), or an `env:`/`file:`
+    /// reference to one.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub webhook: Option<String>,
+    /// How alerts are rendered for the webhook (see `sink`).
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sink: SinkKind,
+    /// Extra HTTP headers sent to the webhook. Values may be `env:`/`file:` references.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Credentials for the webhook, sent as `Authorization`; overrides `global.auth`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<WebhookAuth>,
+    /// Secret for `X-Flashwatch-Signature`; overrides `global.signing_secrets`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_secret: Option<SecretRef>,
+    /// Name of a `[templates]` entry used for the agent message instead of the built-in one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
     /// Override global cooldown for this rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cooldown_secs: Option<u64>,
     /// Whether this rule is enabled.
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// How urgent a match is. Critical rules are not held back by `global.max_per_minute`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub severity: Severity,
+    /// Evaluation order within a severity — higher goes first and gets the global budget first.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub priority: i32,
+    /// Max fires per minute for this rule alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_minute: Option<u64>,
+    /// Override `global.batch_secs` for this rule's alerts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_secs: Option<u64>,
+    /// Only evaluate the rule during these UTC hours/days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+}
+
+impl Rule {
+    /// The rule's webhook with its sink, headers and credentials, if it has one.
+    pub fn destination(&self, global: &GlobalConfig) -> Option<Destination> {
+        Some(Destination {
+            url: self.webhook.clone()?,
+            sink: self.sink,
+            headers: self.headers.clone(),
+            auth: self.auth.clone().or_else(|| global.auth.get(&self.sink).cloned()),
+            signing_secret: self.signing_secret.clone().or_else(|| global.signing_secrets.get(&self.sink).cloned()),
+            template: self.template.clone(),
+        })
+    }
+}
+
+pub const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// When a rule is active. All times are UTC.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    /// Active hours `[start, end)`. Wraps past midnight when start > end, e.g. `[22, 6]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<[u8; 2]>,
+    /// Active days, `mon`..`sun`, checked against the current UTC date. Empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+}
+
+impl Schedule {
+    pub fn active_at(&self, now_ms: u64) -> bool {
+        use chrono::{Datelike, Timelike};
+
+        let Some(t) = chrono::DateTime::from_timestamp_millis(now_ms as i64) else {
+            return true;
+        };
+        if !self.days.is_empty() {
+            let today = WEEKDAYS[t.weekday().num_days_from_monday() as usize];
+            if !self.days.iter().any(|d| d.eq_ignore_ascii_case(today)) {
+                return false;
+            }
+        }
+        match self.hours {
+            Some([start, end]) => {
+                let hour = t.hour() as u8;
+                if start < end { hour >= start && hour < end } else { hour >= start || hour < end }
+            }
+            None => true,
+        }
+    }
+}
+
+/// What a snooze silences: one rule, or every alert involving an address.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SnoozeKind {
+    Rule,
+    Address,
+}
+
+impl SnoozeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SnoozeKind::Rule => "rule",
+            SnoozeKind::Address => "address",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "rule" => Some(SnoozeKind::Rule),
+            "address" => Some(SnoozeKind::Address),
+            _ => None,
+        }
+    }
 }
 
 fn default_true() -> bool { true }
 
-#[derive(Deserialize, Debug, Clone)]
+fn is_default<T: Default + PartialEq>(v: &T) -> bool {
+    *v == T::default()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warn,
+    Critical,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum Trigger {
     /// Match ETH transfers above a threshold.
@@ -90,16 +698,262 @@ pub enum Trigger {
         #[serde(default)]
         min_eth: f64,
     },
+    /// Match contract deployments.
+    ContractCreation {
+        /// Only match deployments from these addresses (empty = any deployer).
+        #[serde(default)]
+        deployers: Vec<String>,
+        /// Only match these init-code hashes, e.g. known scam templates (empty = any code).
+        #[serde(default)]
+        code_hashes: Vec<String>,
+    },
+    /// Match on gas and fees — urgent movers, MEV bots, panicked exits.
+    /// Every condition that is set must hold.
+    Gas {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_priority_fee_gwei: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_max_fee_gwei: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_gas_limit: Option<u64>,
+        /// Tip at least this many times the flashblock's median tip.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_tip_multiple: Option<f64>,
+        #[serde(default)]
+        min_eth: f64,
+    },
+    /// Block scope: gas used so far reaches this percentage of the block gas limit.
+    BlockGas {
+        min_gas_used_pct: f64,
+    },
+    /// Block scope: a single flashblock carries at least this many transactions.
+    FlashblockTxs {
+        min_txs: usize,
+    },
+    /// Block scope: the base fee moved at least this many percent (up or down)
+    /// from the previous block.
+    BaseFeeChange {
+        min_change_pct: f64,
+    },
+    /// Block scope: the block has been built in at least this many flashblocks.
+    FlashblockCount {
+        min_flashblocks: u64,
+    },
+    /// An account's ETH balance moved by at least `min_delta_eth` between flashblocks,
+    /// from `metadata.new_account_balances` — catches internal transfers calldata can't show.
+    BalanceChange {
+        min_delta_eth: f64,
+        #[serde(default, skip_serializing_if = "is_default")]
+        direction: BalanceDirection,
+    },
+    /// Sandwiches, backrun arbitrage and JIT liquidity found in receipt logs (see `mev`).
+    Mev {
+        /// Patterns to alert on (empty = all).
+        #[serde(default)]
+        patterns: Vec<MevKind>,
+        /// Only findings with an estimated WETH profit of at least this much.
+        #[serde(default)]
+        min_profit_eth: f64,
+    },
+    /// Token approvals and Permit2 signature transfers, scored for phishing risk (see `security`).
+    Approval {
+        /// Only findings scoring at least this much (0–100).
+        #[serde(default)]
+        min_risk: u8,
+        /// Approval kinds to alert on (empty = all).
+        #[serde(default)]
+        kinds: Vec<ApprovalKind>,
+        /// Only approvals of these owners' tokens — your own wallets (empty = anyone's).
+        #[serde(default)]
+        owners: Vec<String>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceDirection {
+    #[default]
+    Any,
+    Increase,
+    Decrease,
+}
+
+impl Trigger {
+    /// Whether the trigger is evaluated per block (`RuleEngine::check_block`)
+    /// rather than per transaction.
+    pub fn is_block_scope(&self) -> bool {
+        matches!(
+            self,
+            Trigger::BlockGas { .. }
+                | Trigger::FlashblockTxs { .. }
+                | Trigger::BaseFeeChange { .. }
+                | Trigger::FlashblockCount { .. }
+        )
+    }
+
+    /// Whether the trigger is evaluated on balance changes (`RuleEngine::check_balances`).
+    pub fn is_balance_scope(&self) -> bool {
+        matches!(self, Trigger::BalanceChange { .. })
+    }
 }
 
 /// A matched alert ready to be logged/sent.
 #[derive(Debug, Clone, Serialize)]
 pub struct Alert {
+    /// The rule that fired — with `global.dedup`, the highest-ranked of `matched_rules`.
     pub rule_name: String,
+    /// Every rule that fired for this transaction.
+    pub matched_rules: Vec<String>,
+    pub severity: Severity,
     pub block_number: Option<u64>,
     pub flashblock_index: u64,
-    pub tx: AlertTx,
+    /// The matching transaction; `None` for block-scope alerts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx: Option<AlertTx>,
+    /// What a block-scope trigger measured; `None` for transaction alerts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<BlockAlert>,
+    /// The balance change a `balance_change` trigger saw.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<BalanceAlert>,
+    /// The pattern an `mev` trigger found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mev: Option<MevFinding>,
+    /// The scored approval an `approval` trigger matched; `tx.category` is then `security`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security: Option<SecurityFinding>,
     pub timestamp: u64,
+    /// Matches of this rule dropped by cooldown or rate limits since it last fired.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppressed: Option<Suppressed>,
+    /// A standalone summary of suppressed matches; `tx`, `block`, `balance` or `mev`
+    /// holds the largest of them.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub digest: bool,
+}
+
+impl Alert {
+    /// One-line note about suppressed matches, e.g. "plus 14 similar matches totalling 312 ETH suppressed".
+    pub fn suppressed_note(&self) -> Option<String> {
+        let s = self.suppressed.as_ref()?;
+        let noun = if s.count == 1 { "match" } else { "matches" };
+        if s.largest.eth().is_none() {
+            return Some(format!("plus {} similar {} suppressed", s.count, noun));
+        }
+        Some(format!("plus {} similar {} totalling {:.0} ETH suppressed", s.count, noun, s.total_eth))
+    }
+}
+
+/// What a block-scope trigger saw when it fired.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockAlert {
+    /// Trigger kind, e.g. `block_gas`.
+    pub kind: String,
+    /// Measured value and the rule's threshold, in the trigger's unit
+    /// (percent, transactions or flashblocks).
+    pub value: f64,
+    pub threshold: f64,
+    pub gas_used: u64,
+    pub gas_limit: Option<u64>,
+    pub tx_count: usize,
+    pub flashblock_count: u64,
+    pub base_fee_gwei: Option<f64>,
+}
+
+impl BlockAlert {
+    /// One-line description, e.g. "gas used 96.2% of limit (threshold 90%)".
+    pub fn summary(&self) -> String {
+        match self.kind.as_str() {
+            "block_gas" => format!("gas used {:.1}% of limit (threshold {}%)", self.value, self.threshold),
+            "flashblock_txs" => format!("{} txs in one flashblock (threshold {})", self.value, self.threshold),
+            "base_fee_change" => format!("base fee {:+.1}% vs previous block (threshold {}%)", self.value, self.threshold),
+            "flashblock_count" => format!("{} flashblocks in block (threshold {})", self.value, self.threshold),
+            kind => format!("{} {} (threshold {})", kind, self.value, self.threshold),
+        }
+    }
+}
+
+/// An account balance change seen between two flashblocks.
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceAlert {
+    pub address: String,
+    /// Name from `[labels]`, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub previous_eth: f64,
+    pub balance_eth: f64,
+    /// Signed change, `balance_eth - previous_eth`.
+    pub delta_eth: f64,
+}
+
+impl BalanceAlert {
+    /// One-line description, e.g. "0xabc… +120.00 ETH (3.00 → 123.00 ETH)".
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {:+.2} ETH ({:.2} → {:.2} ETH)",
+            self.label.as_deref().unwrap_or(&self.address),
+            self.delta_eth,
+            self.previous_eth,
+            self.balance_eth,
+        )
+    }
+}
+
+/// Running summary of a rule's suppressed matches.
+#[derive(Debug, Clone, Serialize)]
+pub struct Suppressed {
+    pub count: u64,
+    /// ETH moved by the suppressed matches; 0 for block-scope rules.
+    pub total_eth: f64,
+    /// The largest suppressed match.
+    pub largest: SuppressedMatch,
+    /// Epoch seconds of the first suppressed match.
+    pub since: u64,
+}
+
+impl Suppressed {
+    fn new(largest: SuppressedMatch, now_ms: u64) -> Self {
+        Self { count: 1, total_eth: largest.eth().unwrap_or(0.0), largest, since: now_ms / 1000 }
+    }
+
+    fn add(&mut self, m: SuppressedMatch) {
+        self.count += 1;
+        self.total_eth += m.eth().unwrap_or(0.0);
+        if m.size() > self.largest.size() {
+            self.largest = m;
+        }
+    }
+}
+
+/// A suppressed match, as its alert would have carried it. Untagged, so a
+/// transaction serializes the same as `Alert::tx`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum SuppressedMatch {
+    Tx(AlertTx),
+    Block(BlockAlert),
+    Balance(BalanceAlert),
+    Mev(MevFinding),
+}
+
+impl SuppressedMatch {
+    /// ETH the match moved; `None` for block-scope matches, which measure gas or fees.
+    fn eth(&self) -> Option<f64> {
+        match self {
+            Self::Tx(tx) => Some(tx.value_eth),
+            Self::Balance(change) => Some(change.delta_eth.abs()),
+            Self::Mev(finding) => Some(finding.profit_eth.unwrap_or(0.0)),
+            Self::Block(_) => None,
+        }
+    }
+
+    /// What "largest" compares: ETH, or the measured value of a block-scope match.
+    fn size(&self) -> f64 {
+        match self {
+            Self::Block(block) => block.value,
+            other => other.eth().unwrap_or(0.0),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -111,6 +965,12 @@ pub struct AlertTx {
     pub value_eth: f64,
     pub action: Option<String>,
     pub category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_creation: Option<ContractCreation>,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub max_fee_gwei: f64,
+    pub priority_fee_gwei: f64,
 }
 
 impl From<&DecodedTx> for AlertTx {
@@ -123,29 +983,163 @@ impl From<&DecodedTx> for AlertTx {
             value_eth: tx.value_eth,
             action: tx.action.clone(),
             category: format!("{:?}", tx.category).to_lowercase(),
+            contract_creation: tx.contract_creation.clone(),
+            nonce: tx.nonce,
+            gas_limit: tx.gas_limit,
+            max_fee_gwei: tx.max_fee_gwei(),
+            priority_fee_gwei: tx.priority_fee_gwei(),
         }
     }
 }
 
-/// Runtime state for rate limiting and cooldowns.
+/// Per-rule counters since the rule was loaded. Every match is counted exactly once:
+/// it fired, was merged into a higher-ranked rule's alert, or was suppressed for one
+/// of the listed reasons.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuleStats {
+    pub matched: u64,
+    pub fired: u64,
+    /// Matches folded into another rule's alert by `global.dedup`.
+    pub merged: u64,
+    pub suppressed_cooldown: u64,
+    pub suppressed_rule_limit: u64,
+    pub suppressed_global_limit: u64,
+}
+
+impl RuleStats {
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed_cooldown + self.suppressed_rule_limit + self.suppressed_global_limit
+    }
+}
+
+/// Runtime state for rate limiting and cooldowns. Times are epoch milliseconds,
+/// so the engine can run against wall-clock time or a backtest's block timestamps.
 pub struct RuleEngine {
     pub config: RulesConfig,
-    last_fired: HashMap<String, Instant>,
-    fires_this_minute: Vec<Instant>,
+    /// Indices into `config.rules`: severity, then priority, then file order.
+    order: Vec<usize>,
+    last_fired: HashMap<String, u64>,
+    rule_fires: HashMap<String, Vec<u64>>,
+    fires_this_minute: Vec<u64>,
+    stats: HashMap<String, RuleStats>,
+    /// Suppressed matches not yet reported, per rule.
+    pending: HashMap<String, Suppressed>,
+    /// Recently matched tx hashes (with `global.dedup`), oldest first in `seen_order`.
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    /// Snoozed rules and addresses (lowercase) → epoch seconds the snooze ends.
+    snoozes: HashMap<(SnoozeKind, String), u64>,
+    /// Block-scope rules that already matched in the block `block_payload` is building.
+    block_payload: String,
+    block_matched: HashSet<String>,
+    /// Last seen balance (wei) per lowercase address, oldest first in `balance_order`.
+    balances: HashMap<String, u128>,
+    balance_order: VecDeque<String>,
+    mev: MevDetector,
+    /// Addresses seen on the feed, for approval risk scoring. Only fed while an
+    /// `approval` rule exists.
+    activity: AddressActivity,
+}
+
+/// Tx hashes remembered for dedup. Replays after a reconnect are at most a few blocks old.
+pub const SEEN_CAPACITY: usize = 50_000;
+/// Account balances remembered for `balance_change`. The oldest are forgotten first;
+/// a forgotten account's next change only re-seeds its balance.
+pub const BALANCE_CAPACITY: usize = 200_000;
+
+/// Why a rule that matched did not fire.
+enum Hold {
+    Cooldown,
+    RuleLimit,
+    GlobalLimit,
 }
 
 impl RuleEngine {
     pub fn new(config: RulesConfig) -> Self {
         Self {
+            order: evaluation_order(&config),
             config,
             last_fired: HashMap::new(),
+            rule_fires: HashMap::new(),
             fires_this_minute: Vec::new(),
+            stats: HashMap::new(),
+            pending: HashMap::new(),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            snoozes: HashMap::new(),
+            block_payload: String::new(),
+            block_matched: HashSet::new(),
+            balances: HashMap::new(),
+            balance_order: VecDeque::new(),
+            mev: MevDetector::default(),
+            activity: AddressActivity::default(),
         }
     }
 
     pub fn from_toml(toml_str: &str) -> eyre::Result<Self> {
-        let config: RulesConfig = toml::from_str(toml_str)?;
-        Ok(Self::new(config))
+        Ok(Self::new(RulesConfig::from_toml(toml_str)?))
+    }
+
+    /// Swap in a new config, keeping cooldown state and stats for rules that still exist.
+    pub fn reload(&mut self, config: RulesConfig) {
+        let exists = |name: &String| config.rules.iter().any(|r| &r.name == name);
+        self.last_fired.retain(|name, _| exists(name));
+        self.rule_fires.retain(|name, _| exists(name));
+        self.stats.retain(|name, _| exists(name));
+        self.pending.retain(|name, _| exists(name));
+        self.order = evaluation_order(&config);
+        self.config = config;
+    }
+
+    /// Silence a rule or address until `until` (epoch seconds). Addresses are case-insensitive.
+    pub fn snooze(&mut self, kind: SnoozeKind, target: &str, until: u64) {
+        self.snoozes.insert((kind, snooze_key(kind, target)), until);
+    }
+
+    pub fn unsnooze(&mut self, kind: SnoozeKind, target: &str) -> bool {
+        self.snoozes.remove(&(kind, snooze_key(kind, target))).is_some()
+    }
+
+    /// When the snooze on a rule or address ends, if it is snoozed at `now` (epoch seconds).
+    pub fn snoozed_until(&self, kind: SnoozeKind, target: &str, now: u64) -> Option<u64> {
+        self.snoozes.get(&(kind, snooze_key(kind, target))).copied().filter(|until| *until > now)
+    }
+
+    /// Active snoozes at `now` (epoch seconds), soonest to expire first.
+    pub fn snoozes(&self, now: u64) -> Vec<(SnoozeKind, String, u64)> {
+        let mut out: Vec<_> = self.snoozes.iter()
+            .filter(|(_, until)| **until > now)
+            .map(|((kind, target), until)| (*kind, target.clone(), *until))
+            .collect();
+        out.sort_by_key(|(_, target, until)| (*until, target.clone()));
+        out
+    }
+
+    /// Whether rule `i` is evaluated at all right now: enabled, in schedule and not snoozed.
+    fn is_live(&self, i: usize, now_ms: u64) -> bool {
+        let rule = &self.config.rules[i];
+        rule.enabled
+            && rule.schedule.as_ref().is_none_or(|s| s.active_at(now_ms))
+            && self.snoozed_until(SnoozeKind::Rule, &rule.name, now_ms / 1000).is_none()
+    }
+
+    /// Remember tx hashes as already alerted — used to carry dedup across restarts.
+    pub fn mark_seen(&mut self, hashes: impl IntoIterator<Item = String>) {
+        for hash in hashes {
+            if self.seen.insert(hash.clone()) {
+                self.seen_order.push_back(hash);
+            }
+        }
+        while self.seen_order.len() > SEEN_CAPACITY {
+            if let Some(old) = self.seen_order.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+    }
+
+    /// Match and suppression counters for a rule.
+    pub fn stats(&self, rule_name: &str) -> Option<&RuleStats> {
+        self.stats.get(rule_name)
     }
 
     /// Check a decoded transaction against all rules. Returns alerts for matches.
@@ -155,67 +1149,775 @@ impl RuleEngine {
         block_number: Option<u64>,
         flashblock_index: u64,
     ) -> Vec<Alert> {
-        let now = Instant::now();
-        let epoch_secs = std::time::SystemTime::now()
+        let now_ms = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs();
+            .as_millis() as u64;
+        self.check_at(tx, block_number, flashblock_index, now_ms)
+    }
 
+    /// Like `check`, but at an explicit time (epoch millis) — used by backtests.
+    pub fn check_at(
+        &mut self,
+        tx: &DecodedTx,
+        block_number: Option<u64>,
+        flashblock_index: u64,
+        now_ms: u64,
+    ) -> Vec<Alert> {
         // Prune old fires for rate limiting
-        self.fires_this_minute.retain(|t| now.duration_since(*t) < Duration::from_secs(60));
+        self.fires_this_minute.retain(|t| now_ms.saturating_sub(*t) < 60_000);
+
+        // Score approvals against what the feed showed before this tx.
+        let mut finding = None;
+        if self.config.rules.iter().any(|r| matches!(r.trigger, Trigger::Approval { .. })) {
+            finding = crate::security::assess(tx, &self.config.labels, &self.activity);
+            self.activity.record(tx);
+        }
+
+        // Replays and snoozed addresses match nothing, but held-back digests still go out.
+        let dedup = self.config.global.dedup;
+        if dedup && tx.hash.as_ref().is_some_and(|h| self.seen.contains(h)) {
+            return self.flush_pending(block_number, flashblock_index, now_ms);
+        }
+        let snoozed = |addr: &Option<String>| {
+            addr.as_ref().is_some_and(|a| self.snoozed_until(SnoozeKind::Address, a, now_ms / 1000).is_some())
+        };
+        if snoozed(&tx.from) || snoozed(&tx.to) {
+            return self.flush_pending(block_number, flashblock_index, now_ms);
+        }
 
         let mut alerts = Vec::new();
+        let order = self.order.clone();
 
-        for rule in &self.config.rules {
-            if !rule.enabled {
+        for &i in &order {
+            let rule = &self.config.rules[i];
+            let matched = match rule.trigger {
+                Trigger::Approval { .. } => finding.as_ref().is_some_and(|f| matches_approval(&rule.trigger, f)),
+                _ => matches_rule(&rule.trigger, tx, &self.config.labels),
+            };
+            if !self.is_live(i, now_ms) || !matched {
                 continue;
             }
+            let security = matches!(rule.trigger, Trigger::Approval { .. }).then(|| finding.clone()).flatten();
+            let name = rule.name.clone();
+            self.stats.entry(name.clone()).or_default().matched += 1;
 
-            // Rate limit check
-            if self.fires_this_minute.len() as u64 >= self.config.global.max_per_minute {
-                break;
+            if let Some(hold) = self.hold(i, now_ms) {
+                self.count_hold(&name, hold);
+                self.suppress(name, SuppressedMatch::Tx(AlertTx::from(tx)), now_ms);
+                continue;
             }
 
-            // Cooldown check
-            let cooldown = rule.cooldown_secs.unwrap_or(self.config.global.cooldown_secs);
-            if let Some(last) = self.last_fired.get(&rule.name) {
-                if now.duration_since(*last) < Duration::from_secs(cooldown) {
-                    continue;
-                }
+            // With dedup, rules after the first are merged into its alert: only the first fires.
+            let fires = !dedup || alerts.is_empty();
+            if fires {
+                self.stats.entry(name.clone()).or_default().fired += 1;
+                self.record_fire(&name, now_ms);
+            } else {
+                self.stats.entry(name.clone()).or_default().merged += 1;
             }
+            let mut alert_tx = AlertTx::from(tx);
+            if security.is_some() {
+                alert_tx.category = "security".to_string();
+            }
+            alerts.push(Alert {
+                rule_name: name.clone(),
+                matched_rules: vec![name.clone()],
+                severity: self.config.rules[i].severity,
+                block_number,
+                flashblock_index,
+                tx: Some(alert_tx),
+                block: None,
+                balance: None,
+                mev: None,
+                security,
+                timestamp: now_ms / 1000,
+                suppressed: if fires { self.pending.remove(&name) } else { None },
+                digest: false,
+            });
+        }
 
-            if matches_rule(&rule.trigger, tx) {
-                self.last_fired.insert(rule.name.clone(), now);
-                self.fires_this_minute.push(now);
-
-                alerts.push(Alert {
-                    rule_name: rule.name.clone(),
-                    block_number,
-                    flashblock_index,
-                    tx: AlertTx::from(tx),
-                    timestamp: epoch_secs,
-                });
+        if dedup {
+            if !alerts.is_empty() && let Some(ref hash) = tx.hash {
+                self.mark_seen([hash.clone()]);
+            }
+            // Alerts come out in evaluation order, so the first is the highest-ranked rule.
+            if alerts.len() > 1 {
+                let rest = alerts.split_off(1);
+                let primary = &mut alerts[0];
+                for other in rest {
+                    primary.matched_rules.push(other.rule_name);
+                    primary.severity = primary.severity.max(other.severity);
+                    if primary.security.is_none() && other.security.is_some() {
+                        primary.security = other.security;
+                        primary.tx = other.tx;
+                    }
+                }
             }
         }
 
+        alerts.extend(self.flush_pending(block_number, flashblock_index, now_ms));
         alerts
     }
-}
 
-fn matches_rule(trigger: &Trigger, tx: &DecodedTx) -> bool {
-    match trigger {
-        Trigger::EthTransfer { min_eth } => {
-            tx.value_eth >= *min_eth
-                && tx.action.as_deref() == Some("ETH transfer")
+    /// Check block-scope rules against the block being built. Returns alerts for matches.
+    pub fn check_block(&mut self, block: &BlockState, flashblock_index: u64) -> Vec<Alert> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.check_block_at(block, flashblock_index, now_ms)
+    }
+
+    /// Like `check_block`, but at an explicit time (epoch millis). Each rule matches
+    /// at most once per block, however many flashblocks keep it over the threshold.
+    pub fn check_block_at(&mut self, block: &BlockState, flashblock_index: u64, now_ms: u64) -> Vec<Alert> {
+        self.fires_this_minute.retain(|t| now_ms.saturating_sub(*t) < 60_000);
+        if block.payload_id != self.block_payload {
+            self.block_payload = block.payload_id.clone();
+            self.block_matched.clear();
         }
-        Trigger::Protocol { names, categories, min_eth } => {
-            if tx.value_eth < *min_eth {
-                return false;
+
+        let mut alerts = Vec::new();
+        let order = self.order.clone();
+        for &i in &order {
+            let rule = &self.config.rules[i];
+            if self.block_matched.contains(&rule.name) || !self.is_live(i, now_ms) {
+                continue;
             }
-            let label_match = if names.is_empty() {
+            let Some(detail) = matches_block(&rule.trigger, block) else { continue };
+            let name = rule.name.clone();
+            self.block_matched.insert(name.clone());
+            self.stats.entry(name.clone()).or_default().matched += 1;
+
+            if let Some(hold) = self.hold(i, now_ms) {
+                self.count_hold(&name, hold);
+                self.suppress(name, SuppressedMatch::Block(detail), now_ms);
+                continue;
+            }
+
+            self.stats.entry(name.clone()).or_default().fired += 1;
+            self.record_fire(&name, now_ms);
+            alerts.push(Alert {
+                rule_name: name.clone(),
+                matched_rules: vec![name.clone()],
+                severity: self.config.rules[i].severity,
+                block_number: block.block_number,
+                flashblock_index,
+                tx: None,
+                block: Some(detail),
+                balance: None,
+                mev: None,
+                security: None,
+                timestamp: now_ms / 1000,
+                suppressed: self.pending.remove(&name),
+                digest: false,
+            });
+        }
+        alerts.extend(self.flush_pending(block.block_number, flashblock_index, now_ms));
+        alerts
+    }
+
+    /// Check `balance_change` rules against a flashblock's new account balances,
+    /// compared with the balances seen in earlier flashblocks.
+    pub fn check_balances(&mut self, fb: &FlashblockMessage, block_number: Option<u64>) -> Vec<Alert> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.check_balances_at(fb, block_number, now_ms)
+    }
+
+    /// Like `check_balances`, but at an explicit time (epoch millis). An account's first
+    /// sighting only records its balance. Protocol addresses the decoder knows are skipped.
+    pub fn check_balances_at(&mut self, fb: &FlashblockMessage, block_number: Option<u64>, now_ms: u64) -> Vec<Alert> {
+        self.fires_this_minute.retain(|t| now_ms.saturating_sub(*t) < 60_000);
+        let known = crate::decode::known_addresses();
+        let order = self.order.clone();
+        let mut alerts = Vec::new();
+
+        for (address, wei) in fb.new_account_balances() {
+            let previous = self.remember_balance(&address, wei);
+            let Some(previous) = previous.filter(|p| *p != wei) else { continue };
+            if known.contains_key(address.as_str())
+                || self.snoozed_until(SnoozeKind::Address, &address, now_ms / 1000).is_some()
+            {
+                continue;
+            }
+            let change = BalanceAlert {
+                label: self.config.labels.get(&address).cloned(),
+                address,
+                previous_eth: previous as f64 / 1e18,
+                balance_eth: wei as f64 / 1e18,
+                delta_eth: (wei as f64 - previous as f64) / 1e18,
+            };
+
+            for &i in &order {
+                let rule = &self.config.rules[i];
+                if !self.is_live(i, now_ms) || !matches_balance(&rule.trigger, change.delta_eth) {
+                    continue;
+                }
+                let name = rule.name.clone();
+                self.stats.entry(name.clone()).or_default().matched += 1;
+                if let Some(hold) = self.hold(i, now_ms) {
+                    self.count_hold(&name, hold);
+                    self.suppress(name, SuppressedMatch::Balance(change.clone()), now_ms);
+                    continue;
+                }
+                self.stats.entry(name.clone()).or_default().fired += 1;
+                self.record_fire(&name, now_ms);
+                alerts.push(Alert {
+                    rule_name: name.clone(),
+                    matched_rules: vec![name.clone()],
+                    severity: self.config.rules[i].severity,
+                    block_number,
+                    flashblock_index: fb.index,
+                    tx: None,
+                    block: None,
+                    balance: Some(change.clone()),
+                    mev: None,
+                    security: None,
+                    timestamp: now_ms / 1000,
+                    suppressed: self.pending.remove(&name),
+                    digest: false,
+                });
+            }
+        }
+        alerts.extend(self.flush_pending(block_number, fb.index, now_ms));
+        alerts
+    }
+
+    /// Check `mev` rules against patterns completed in this flashblock. `txs` are the
+    /// flashblock's decoded transactions, in order. Logs are only decoded while an
+    /// `mev` rule exists.
+    pub fn check_mev(&mut self, fb: &FlashblockMessage, txs: &[DecodedTx], block_number: Option<u64>) -> Vec<Alert> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.check_mev_at(fb, txs, block_number, now_ms)
+    }
+
+    /// Like `check_mev`, but at an explicit time (epoch millis).
+    pub fn check_mev_at(
+        &mut self,
+        fb: &FlashblockMessage,
+        txs: &[DecodedTx],
+        block_number: Option<u64>,
+        now_ms: u64,
+    ) -> Vec<Alert> {
+        if !self.config.rules.iter().any(|r| matches!(r.trigger, Trigger::Mev { .. })) {
+            return Vec::new();
+        }
+        self.fires_this_minute.retain(|t| now_ms.saturating_sub(*t) < 60_000);
+        let order = self.order.clone();
+        let mut alerts = Vec::new();
+
+        for finding in self.mev.observe(fb, txs) {
+            let snoozed = [&finding.attacker, &finding.victim]
+                .iter()
+                .any(|a| self.snoozed_until(SnoozeKind::Address, a, now_ms / 1000).is_some());
+            if snoozed {
+                continue;
+            }
+            for &i in &order {
+                let rule = &self.config.rules[i];
+                if !self.is_live(i, now_ms) || !matches_mev(&rule.trigger, &finding) {
+                    continue;
+                }
+                let name = rule.name.clone();
+                self.stats.entry(name.clone()).or_default().matched += 1;
+                if let Some(hold) = self.hold(i, now_ms) {
+                    self.count_hold(&name, hold);
+                    self.suppress(name, SuppressedMatch::Mev(finding.clone()), now_ms);
+                    continue;
+                }
+                self.stats.entry(name.clone()).or_default().fired += 1;
+                self.record_fire(&name, now_ms);
+                alerts.push(Alert {
+                    rule_name: name.clone(),
+                    matched_rules: vec![name.clone()],
+                    severity: self.config.rules[i].severity,
+                    block_number,
+                    flashblock_index: fb.index,
+                    tx: None,
+                    block: None,
+                    balance: None,
+                    mev: Some(finding.clone()),
+                    security: None,
+                    timestamp: now_ms / 1000,
+                    suppressed: self.pending.remove(&name),
+                    digest: false,
+                });
+            }
+        }
+        alerts.extend(self.flush_pending(block_number, fb.index, now_ms));
+        alerts
+    }
+
+    /// Store an account's latest balance, returning the one it replaces.
+    fn remember_balance(&mut self, address: &str, wei: u128) -> Option<u128> {
+        let previous = self.balances.insert(address.to_string(), wei);
+        if previous.is_none() {
+            self.balance_order.push_back(address.to_string());
+            while self.balance_order.len() > BALANCE_CAPACITY {
+                if let Some(old) = self.balance_order.pop_front() {
+                    self.balances.remove(&old);
+                }
+            }
+        }
+        previous
+    }
+
+    /// Add a held-back match to the rule's pending summary.
+    fn suppress(&mut self, rule_name: String, m: SuppressedMatch, now_ms: u64) {
+        match self.pending.get_mut(&rule_name) {
+            Some(pending) => pending.add(m),
+            None => {
+                self.pending.insert(rule_name, Suppressed::new(m, now_ms));
+            }
+        }
+    }
+
+    /// Rules with suppressed matches that are free to fire again but didn't match
+    /// this time: report what was held back as a digest carrying the largest match.
+    fn flush_pending(&mut self, block_number: Option<u64>, flashblock_index: u64, now_ms: u64) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        if self.pending.is_empty() {
+            return alerts;
+        }
+        for i in self.order.clone() {
+            let name = self.config.rules[i].name.clone();
+            if !self.is_live(i, now_ms) || !self.pending.contains_key(&name) || self.hold(i, now_ms).is_some() {
+                continue;
+            }
+            let Some(summary) = self.pending.remove(&name) else { continue };
+            self.record_fire(&name, now_ms);
+            let mut alert = Alert {
+                rule_name: name.clone(),
+                matched_rules: vec![name],
+                severity: self.config.rules[i].severity,
+                block_number,
+                flashblock_index,
+                tx: None,
+                block: None,
+                balance: None,
+                mev: None,
+                security: None,
+                timestamp: now_ms / 1000,
+                suppressed: None,
+                digest: true,
+            };
+            match &summary.largest {
+                SuppressedMatch::Tx(tx) => alert.tx = Some(tx.clone()),
+                SuppressedMatch::Block(block) => alert.block = Some(block.clone()),
+                SuppressedMatch::Balance(change) => alert.balance = Some(change.clone()),
+                SuppressedMatch::Mev(finding) => alert.mev = Some(finding.clone()),
+            }
+            alert.suppressed = Some(summary);
+            alerts.push(alert);
+        }
+        alerts
+    }
+
+    fn count_hold(&mut self, rule_name: &str, hold: Hold) {
+        let stats = self.stats.entry(rule_name.to_string()).or_default();
+        match hold {
+            Hold::Cooldown => stats.suppressed_cooldown += 1,
+            Hold::RuleLimit => stats.suppressed_rule_limit += 1,
+            Hold::GlobalLimit => {
+                stats.suppressed_global_limit += 1;
+                debug!("Rule '{}' matched but the global rate limit is reached", rule_name);
+            }
+        }
+    }
+
+    /// Whether rule `i` is held back right now by its cooldown or a rate limit.
+    fn hold(&mut self, i: usize, now_ms: u64) -> Option<Hold> {
+        let rule = &self.config.rules[i];
+
+        let cooldown = rule.cooldown_secs.unwrap_or(self.config.global.cooldown_secs);
+        if let Some(last) = self.last_fired.get(&rule.name)
+            && now_ms.saturating_sub(*last) < cooldown.saturating_mul(1000)
+        {
+            return Some(Hold::Cooldown);
+        }
+
+        let rule_fires = self.rule_fires.entry(rule.name.clone()).or_default();
+        rule_fires.retain(|t| now_ms.saturating_sub(*t) < 60_000);
+        if let Some(max) = rule.max_per_minute
+            && rule_fires.len() as u64 >= max
+        {
+            return Some(Hold::RuleLimit);
+        }
+
+        // Critical rules still fire past the global limit, but count against it.
+        if rule.severity != Severity::Critical
+            && self.fires_this_minute.len() as u64 >= self.config.global.max_per_minute
+        {
+            return Some(Hold::GlobalLimit);
+        }
+        None
+    }
+
+    fn record_fire(&mut self, rule_name: &str, now_ms: u64) {
+        self.last_fired.insert(rule_name.to_string(), now_ms);
+        self.rule_fires.entry(rule_name.to_string()).or_default().push(now_ms);
+        self.fires_this_minute.push(now_ms);
+    }
+}
+
+fn snooze_key(kind: SnoozeKind, target: &str) -> String {
+    match kind {
+        SnoozeKind::Rule => target.to_string(),
+        SnoozeKind::Address => target.to_lowercase(),
+    }
+}
+
+fn evaluation_order(config: &RulesConfig) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..config.rules.len()).collect();
+    // Stable sort keeps file order among equals.
+    order.sort_by_key(|&i| {
+        let r = &config.rules[i];
+        (std::cmp::Reverse(r.severity), std::cmp::Reverse(r.priority))
+    });
+    order
+}
+
+/// Severity of a `rules check` finding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LintLevel {
+    /// The rule can never fire as written.
+    Error,
+    /// The rule loads and fires, but probably not as intended.
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct LintFinding {
+    pub level: LintLevel,
+    /// Rule name, or `None` for file-level findings.
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+impl RulesConfig {
+    /// Strict checks beyond `validate()`: names and categories are checked against the
+    /// decoder's tables, and rules that are duplicated or can never match are flagged.
+    pub fn lint(&self) -> Vec<LintFinding> {
+        let mut out = Vec::new();
+        let mut push = |level, rule: Option<&str>, message: String| {
+            out.push(LintFinding { level, rule: rule.map(String::from), message });
+        };
+
+        if let Err(e) = self.validate() {
+            push(LintLevel::Error, None, e.to_string());
+        }
+
+        let known = crate::decode::known_addresses();
+        let selectors = crate::decode::known_selectors();
+        let label_category = |name: &str| {
+            known.values().find(|l| l.name.eq_ignore_ascii_case(name)).map(|l| l.category)
+        };
+
+        if self.global.max_per_minute == 0 {
+            if self.rules.iter().any(|r| r.severity == Severity::Critical) {
+                push(LintLevel::Warning, None, "global.max_per_minute is 0 — only critical rules can fire".into());
+            } else {
+                push(LintLevel::Error, None, "global.max_per_minute is 0 — no alert can ever fire".into());
+            }
+        }
+        if !self.rules.iter().any(|r| r.enabled) {
+            push(LintLevel::Warning, None, "no enabled rules".into());
+        }
+        for addr in self.labels.keys() {
+            if addr != &addr.to_lowercase() {
+                push(LintLevel::Warning, None, format!("label key '{addr}' is not lowercase and will never be looked up"));
+            } else if !is_hex_of_len(addr, 20) {
+                push(LintLevel::Warning, None, format!("label key '{addr}' is not a 20-byte hex address"));
+            }
+        }
+
+        for (i, rule) in self.rules.iter().enumerate() {
+            let name = Some(rule.name.as_str());
+
+            if let Some(ref url) = rule.webhook {
+                if SecretRef::is_reference(url) {
+                    if let Err(e) = SecretRef::parse(url) {
+                        push(LintLevel::Error, name, format!("webhook: {e}"));
+                    }
+                } else if rule.sink == SinkKind::Email {
+                    match crate::email::MailTarget::parse(url) {
+                        Err(e) => push(LintLevel::Error, name, format!("webhook: {e}")),
+                        // With auth, `validate` already refuses this.
+                        Ok(mail) if mail.tls == crate::email::TlsMode::None && rule.auth.is_none() && !crate::sink::is_loopback(url) => {
+                            push(LintLevel::Warning, name, "tls=none sends mail in the clear to another host".into());
+                        }
+                        Ok(_) => {}
+                    }
+                } else if rule.sink == SinkKind::Exec {
+                    // The command is checked by validate().
+                } else if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    push(LintLevel::Error, name, format!("webhook '{url}' is not an http(s) URL"));
+                }
+            }
+            if rule.max_per_minute == Some(0) {
+                push(LintLevel::Error, name, "max_per_minute is 0 — rule can never fire".into());
+            }
+            if rule.batch_secs.is_some_and(|b| b > 0) && rule.webhook.is_none() {
+                push(LintLevel::Warning, name, "batch_secs has no effect without a webhook".into());
+            }
+            if rule.webhook.is_none()
+                && (rule.sink != SinkKind::default() || !rule.headers.is_empty() || rule.auth.is_some() || rule.signing_secret.is_some())
+            {
+                push(LintLevel::Warning, name, "sink, headers, auth and signing_secret have no effect without a webhook".into());
+            }
+            if rule.template.is_some() && !matches!(rule.sink, SinkKind::Openclaw | SinkKind::Email) {
+                push(LintLevel::Warning, name, format!("template only applies to the openclaw and email sinks, not {}", rule.sink.as_str()));
+            }
+            if rule.sink == SinkKind::Email {
+                if !rule.headers.is_empty() || rule.signing_secret.is_some() {
+                    push(LintLevel::Warning, name, "headers and signing_secret have no effect on the email sink".into());
+                }
+                if matches!(rule.auth, Some(WebhookAuth::Bearer(_))) {
+                    push(LintLevel::Error, name, "email auth must be basic (SMTP username and password)".into());
+                }
+            }
+            if rule.sink == SinkKind::Exec && (!rule.headers.is_empty() || rule.auth.is_some() || rule.signing_secret.is_some()) {
+                push(LintLevel::Warning, name, "headers, auth and signing_secret have no effect on the exec sink".into());
+            }
+            if rule.auth.is_some() && rule.headers.keys().any(|k| k.eq_ignore_ascii_case("authorization")) {
+                push(LintLevel::Warning, name, "auth replaces the Authorization header".into());
+            }
+
+            match &rule.trigger {
+                Trigger::Protocol { names, categories, min_eth } => {
+                    let mut cats = Vec::new();
+                    for c in categories {
+                        match Category::ALL.iter().find(|k| k.name().eq_ignore_ascii_case(c)) {
+                            Some(k) => cats.push(*k),
+                            None => {
+                                let valid: Vec<_> = Category::ALL.iter().map(|k| k.name()).collect();
+                                push(LintLevel::Error, name, format!("unknown category '{c}' (expected one of: {})", valid.join(", ")));
+                            }
+                        }
+                    }
+                    let mut reachable_names = 0;
+                    for n in names {
+                        match label_category(n) {
+                            None if self.labels.values().any(|l| l.eq_ignore_ascii_case(n)) => push(
+                                LintLevel::Error, name,
+                                format!("protocol '{n}' is only a [labels] entry — protocol rules match the decoder's built-in labels"),
+                            ),
+                            None => push(LintLevel::Error, name, format!("protocol '{n}' matches no known label")),
+                            Some(cat) if !cats.is_empty() && !cats.contains(&cat) => push(
+                                LintLevel::Error, name,
+                                format!("protocol '{n}' is category '{}', excluded by categories filter", cat.name()),
+                            ),
+                            Some(_) => reachable_names += 1,
+                        }
+                    }
+                    if !names.is_empty() && reachable_names == 0 {
+                        push(LintLevel::Error, name, "no listed protocol can ever match — rule is unreachable".into());
+                    }
+                    if names.is_empty() && categories.is_empty() && *min_eth <= 0.0 {
+                        push(LintLevel::Warning, name, "no names, categories or min_eth — matches every transaction".into());
+                    }
+                }
+                Trigger::FunctionCall { actions, .. } => {
+                    if actions.is_empty() {
+                        push(LintLevel::Error, name, "no actions listed — rule can never match".into());
+                    }
+                    for a in actions {
+                        let decodable = selectors.values().any(|s| s.contains(a.as_str()))
+                            || "ETH transfer".contains(a.as_str())
+                            || "contract creation".contains(a.as_str());
+                        if !decodable {
+                            push(LintLevel::Error, name, format!("action '{a}' matches no decoded function name"));
+                        }
+                    }
+                }
+                Trigger::LargeValue { min_eth } | Trigger::EthTransfer { min_eth } if *min_eth <= 0.0 => {
+                    push(LintLevel::Warning, name, "min_eth is 0 — matches every transaction of this kind".into());
+                }
+                Trigger::FlashblockTxs { min_txs: 0 } | Trigger::FlashblockCount { min_flashblocks: 0 } => {
+                    push(LintLevel::Error, name, "threshold is 0 — rule can never match".into());
+                }
+                Trigger::FlashblockCount { min_flashblocks: 1 } => {
+                    push(LintLevel::Warning, name, "min_flashblocks 1 — matches every block".into());
+                }
+                Trigger::Approval { min_risk: 0, owners, .. } if owners.is_empty() => {
+                    push(LintLevel::Warning, name, "min_risk 0 and no owners — matches every approval on the chain".into());
+                }
+                Trigger::Gas { min_tip_multiple: Some(m), .. } if *m <= 1.0 => {
+                    push(LintLevel::Warning, name, format!("min_tip_multiple {m} — matches about half of every flashblock"));
+                }
+                _ => {}
+            }
+
+            if rule.enabled
+                && let Some(earlier) = self.rules[..i].iter().find(|r| r.enabled && r.trigger == rule.trigger)
+            {
+                push(
+                    LintLevel::Warning, name,
+                    format!("same trigger as '{}' — every match alerts twice", earlier.name),
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// One condition of a trigger and whether a transaction met it — used by `rules test`.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Evaluate a trigger against a transaction condition by condition.
+/// Mirrors `matches_rule` — a trigger matches iff every condition is `ok`.
+pub fn explain_match(trigger: &Trigger, tx: &DecodedTx, labels: &HashMap<String, String>) -> Vec<Condition> {
+    let cond = |ok: bool, detail: String| Condition { ok, detail };
+    let value = |min_eth: f64| cond(
+        tx.value_eth >= min_eth,
+        format!("value {:.4} ETH {} min {} ETH", tx.value_eth, if tx.value_eth >= min_eth { ">=" } else { "<" }, min_eth),
+    );
+    let list = |items: &[String]| items.join(", ");
+
+    match trigger {
+        Trigger::EthTransfer { min_eth } => vec![
+            value(*min_eth),
+            cond(
+                tx.action.as_deref() == Some("ETH transfer"),
+                format!("action is {:?} (need plain ETH transfer)", tx.action.as_deref().unwrap_or("none")),
+            ),
+        ],
+        Trigger::Protocol { names, categories, min_eth } => {
+            let mut out = vec![value(*min_eth)];
+            let label = tx.to_label.as_ref().map(|l| l.name);
+            if !names.is_empty() {
+                out.push(cond(
+                    label.is_some_and(|l| names.iter().any(|n| l.eq_ignore_ascii_case(n))),
+                    format!("to label {:?} in [{}]", label.unwrap_or("none"), list(names)),
+                ));
+            }
+            if !categories.is_empty() {
+                let cat = tx.category.name();
+                out.push(cond(
+                    categories.iter().any(|c| c.to_lowercase() == cat),
+                    format!("category '{}' in [{}]", cat, list(categories)),
+                ));
+            }
+            out
+        }
+        Trigger::FunctionCall { actions, min_eth } => vec![
+            value(*min_eth),
+            cond(
+                tx.action.as_ref().is_some_and(|a| actions.iter().any(|act| a.contains(act))),
+                format!("action {:?} contains one of [{}]", tx.action.as_deref().unwrap_or("none"), list(actions)),
+            ),
+        ],
+        Trigger::LargeValue { min_eth } => vec![value(*min_eth)],
+        Trigger::Address { address, min_eth } => vec![
+            value(*min_eth),
+            cond(
+                tx.to.as_ref().is_some_and(|to| to.eq_ignore_ascii_case(address)),
+                format!("to {} is {}", tx.to.as_deref().unwrap_or("none"), address),
+            ),
+        ],
+        Trigger::ContractCreation { deployers, code_hashes } => {
+            let Some(ref creation) = tx.contract_creation else {
+                return vec![cond(false, "not a contract creation".into())];
+            };
+            let mut out = vec![cond(true, "contract creation".into())];
+            if !deployers.is_empty() {
+                out.push(cond(
+                    creation.deployer.as_ref().is_some_and(|d| deployers.iter().any(|w| w.eq_ignore_ascii_case(d))),
+                    format!("deployer {} in [{}]", creation.deployer.as_deref().unwrap_or("unknown"), list(deployers)),
+                ));
+            }
+            if !code_hashes.is_empty() {
+                out.push(cond(
+                    code_hashes.iter().any(|h| h.eq_ignore_ascii_case(&creation.init_code_hash)),
+                    format!("init code hash {} in [{}]", creation.init_code_hash, list(code_hashes)),
+                ));
+            }
+            out
+        }
+        Trigger::Gas { min_priority_fee_gwei, min_max_fee_gwei, min_gas_limit, min_tip_multiple, min_eth } => {
+            let mut out = vec![value(*min_eth)];
+            if let Some(min) = min_priority_fee_gwei {
+                let tip = tx.priority_fee_gwei();
+                out.push(cond(tip >= *min, format!("priority fee {tip:.4} gwei >= {min} gwei")));
+            }
+            if let Some(min) = min_max_fee_gwei {
+                let fee = tx.max_fee_gwei();
+                out.push(cond(fee >= *min, format!("max fee {fee:.4} gwei >= {min} gwei")));
+            }
+            if let Some(min) = min_gas_limit {
+                out.push(cond(tx.gas_limit >= *min, format!("gas limit {} >= {}", tx.gas_limit, min)));
+            }
+            if let Some(min) = min_tip_multiple {
+                out.push(match tx.tip_multiple() {
+                    Some(m) => cond(m >= *min, format!("tip {m:.1}x flashblock median >= {min}x")),
+                    None => cond(false, "no flashblock median tip to compare against".into()),
+                });
+            }
+            out
+        }
+        Trigger::BlockGas { .. }
+        | Trigger::FlashblockTxs { .. }
+        | Trigger::BaseFeeChange { .. }
+        | Trigger::FlashblockCount { .. } => {
+            vec![cond(false, "block-scope trigger — evaluated per flashblock, not per transaction".into())]
+        }
+        Trigger::BalanceChange { .. } => {
+            vec![cond(false, "balance trigger — evaluated on account balance changes, not per transaction".into())]
+        }
+        Trigger::Mev { .. } => {
+            vec![cond(false, "mev trigger — evaluated on patterns across transactions, not a single one".into())]
+        }
+        Trigger::Approval { min_risk, kinds, owners } => {
+            let Some(f) = crate::security::assess(tx, labels, &AddressActivity::default()) else {
+                return vec![cond(false, "no approval or Permit2 transfer in calldata".into())];
+            };
+            let mut out = vec![cond(true, format!("{:?} to {}", f.kind, f.spender))];
+            if !kinds.is_empty() {
+                out.push(cond(kinds.contains(&f.kind), format!("kind {:?} in {:?}", f.kind, kinds)));
+            }
+            if !owners.is_empty() {
+                out.push(cond(
+                    f.owner.as_ref().is_some_and(|o| owners.iter().any(|w| w.eq_ignore_ascii_case(o))),
+                    format!("owner {} in [{}]", f.owner.as_deref().unwrap_or("unknown"), list(owners)),
+                ));
+            }
+            out.push(cond(
+                f.risk >= *min_risk,
+                format!("risk {} >= {} (without feed history: {})", f.risk, min_risk, f.reasons.join(", ")),
+            ));
+            out
+        }
+    }
+}
+
+/// Whether a trigger matches a transaction, ignoring cooldowns and rate limits.
+/// `labels` are the rules file's `[labels]`, which approval scoring treats as known spenders.
+pub fn matches_rule(trigger: &Trigger, tx: &DecodedTx, labels: &HashMap<String, String>) -> bool {
+    match trigger {
+        Trigger::EthTransfer { min_eth } => {
+            tx.value_eth >= *min_eth
+                && tx.action.as_deref() == Some("ETH transfer")
+        }
+        Trigger::Protocol { names, categories, min_eth } => {
+            if tx.value_eth < *min_eth {
+                return false;
+            }
+            let label_match = if names.is_empty() {
                 true
             } else {
-                tx.to_label.as_ref().map_or(false, |l| {
+                tx.to_label.as_ref().is_some_and(|l| {
                     names.iter().any(|n| l.name.eq_ignore_ascii_case(n))
                 })
             };
@@ -231,7 +1933,7 @@ fn matches_rule(trigger: &Trigger, tx: &DecodedTx) -> bool {
             if tx.value_eth < *min_eth {
                 return false;
             }
-            tx.action.as_ref().map_or(false, |a| {
+            tx.action.as_ref().is_some_and(|a| {
                 actions.iter().any(|act| a.contains(act))
             })
         }
@@ -242,13 +1944,108 @@ fn matches_rule(trigger: &Trigger, tx: &DecodedTx) -> bool {
             if tx.value_eth < *min_eth {
                 return false;
             }
-            tx.to.as_ref().map_or(false, |to| {
+            tx.to.as_ref().is_some_and(|to| {
                 to.eq_ignore_ascii_case(address)
             })
         }
+        Trigger::ContractCreation { deployers, code_hashes } => {
+            let Some(ref creation) = tx.contract_creation else {
+                return false;
+            };
+            let deployer_match = deployers.is_empty()
+                || creation.deployer.as_ref().is_some_and(|d| {
+                    deployers.iter().any(|w| w.eq_ignore_ascii_case(d))
+                });
+            let code_match = code_hashes.is_empty()
+                || code_hashes.iter().any(|h| h.eq_ignore_ascii_case(&creation.init_code_hash));
+            deployer_match && code_match
+        }
+        Trigger::Gas { min_priority_fee_gwei, min_max_fee_gwei, min_gas_limit, min_tip_multiple, min_eth } => {
+            tx.value_eth >= *min_eth
+                && min_priority_fee_gwei.is_none_or(|min| tx.priority_fee_gwei() >= min)
+                && min_max_fee_gwei.is_none_or(|min| tx.max_fee_gwei() >= min)
+                && min_gas_limit.is_none_or(|min| tx.gas_limit >= min)
+                && min_tip_multiple.is_none_or(|min| tx.tip_multiple().is_some_and(|m| m >= min))
+        }
+        Trigger::BlockGas { .. }
+        | Trigger::FlashblockTxs { .. }
+        | Trigger::BaseFeeChange { .. }
+        | Trigger::FlashblockCount { .. }
+        | Trigger::BalanceChange { .. }
+        | Trigger::Mev { .. } => false,
+        // Scored without feed history; the engine scores with it (see `RuleEngine::check_at`).
+        Trigger::Approval { .. } => crate::security::assess(tx, labels, &AddressActivity::default())
+            .is_some_and(|f| matches_approval(trigger, &f)),
+    }
+}
+
+/// Whether an `approval` trigger matches a scored approval.
+pub fn matches_approval(trigger: &Trigger, finding: &SecurityFinding) -> bool {
+    let Trigger::Approval { min_risk, kinds, owners } = trigger else {
+        return false;
+    };
+    finding.risk >= *min_risk
+        && (kinds.is_empty() || kinds.contains(&finding.kind))
+        && (owners.is_empty()
+            || finding.owner.as_ref().is_some_and(|o| owners.iter().any(|w| w.eq_ignore_ascii_case(o))))
+}
+
+/// Whether an `mev` trigger matches a finding.
+pub fn matches_mev(trigger: &Trigger, finding: &MevFinding) -> bool {
+    let Trigger::Mev { patterns, min_profit_eth } = trigger else {
+        return false;
+    };
+    (patterns.is_empty() || patterns.contains(&finding.kind))
+        && (*min_profit_eth <= 0.0 || finding.profit_eth.is_some_and(|p| p >= *min_profit_eth))
+}
+
+/// Whether a `balance_change` trigger matches a signed balance change in ETH.
+pub fn matches_balance(trigger: &Trigger, delta_eth: f64) -> bool {
+    let Trigger::BalanceChange { min_delta_eth, direction } = trigger else {
+        return false;
+    };
+    match direction {
+        BalanceDirection::Any => delta_eth.abs() >= *min_delta_eth,
+        BalanceDirection::Increase => delta_eth >= *min_delta_eth,
+        BalanceDirection::Decrease => -delta_eth >= *min_delta_eth,
     }
 }
 
+/// Evaluate a block-scope trigger against the block being built, ignoring cooldowns
+/// and rate limits. Transaction triggers never match.
+pub fn matches_block(trigger: &Trigger, block: &BlockState) -> Option<BlockAlert> {
+    let (kind, value, threshold) = match trigger {
+        Trigger::BlockGas { min_gas_used_pct } => {
+            let pct = block.gas_used_pct()?;
+            (pct >= *min_gas_used_pct).then_some(("block_gas", pct, *min_gas_used_pct))?
+        }
+        Trigger::FlashblockTxs { min_txs } => {
+            let n = block.flashblock_tx_count;
+            (n >= *min_txs && *min_txs > 0).then_some(("flashblock_txs", n as f64, *min_txs as f64))?
+        }
+        Trigger::BaseFeeChange { min_change_pct } => {
+            let pct = block.base_fee_change_pct()?;
+            (pct.abs() >= *min_change_pct).then_some(("base_fee_change", pct, *min_change_pct))?
+        }
+        Trigger::FlashblockCount { min_flashblocks } => {
+            let n = block.flashblock_count;
+            (n >= *min_flashblocks && *min_flashblocks > 0)
+                .then_some(("flashblock_count", n as f64, *min_flashblocks as f64))?
+        }
+        _ => return None,
+    };
+    Some(BlockAlert {
+        kind: kind.to_string(),
+        value,
+        threshold,
+        gas_used: block.gas_used,
+        gas_limit: block.gas_limit,
+        tx_count: block.total_tx_count,
+        flashblock_count: block.flashblock_count,
+        base_fee_gwei: block.base_fee_gwei,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -265,6 +2062,13 @@ mod tests {
             action: action.map(String::from),
             category,
             gas_used: None,
+            contract_creation: None,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_per_gas: 0,
+            max_priority_fee_per_gas: 0,
+            median_tip_wei: None,
+            approval: None,
         }
     }
 
@@ -272,17 +2076,17 @@ mod tests {
     fn test_eth_transfer_trigger() {
         let trigger = Trigger::EthTransfer { min_eth: 5.0 };
         let tx = make_tx(10.0, Some("ETH transfer"), Category::Unknown, None);
-        assert!(matches_rule(&trigger, &tx));
+        assert!(matches_rule(&trigger, &tx, &HashMap::new()));
 
         let small = make_tx(1.0, Some("ETH transfer"), Category::Unknown, None);
-        assert!(!matches_rule(&trigger, &small));
+        assert!(!matches_rule(&trigger, &small, &HashMap::new()));
     }
 
     #[test]
     fn test_large_value_trigger() {
         let trigger = Trigger::LargeValue { min_eth: 1.0 };
         let tx = make_tx(2.5, Some("swap"), Category::Dex, None);
-        assert!(matches_rule(&trigger, &tx));
+        assert!(matches_rule(&trigger, &tx, &HashMap::new()));
     }
 
     #[test]
@@ -293,9 +2097,684 @@ mod tests {
             min_eth: 0.0,
         };
         let tx = make_tx(0.1, Some("swap"), Category::Dex, Some("Uniswap V3 Router"));
-        assert!(matches_rule(&trigger, &tx));
+        assert!(matches_rule(&trigger, &tx, &HashMap::new()));
 
         let other = make_tx(0.1, Some("swap"), Category::Dex, Some("Aerodrome Router"));
-        assert!(!matches_rule(&trigger, &other));
+        assert!(!matches_rule(&trigger, &other, &HashMap::new()));
+    }
+
+    #[test]
+    fn test_contract_creation_trigger() {
+        let mut tx = make_tx(0.0, Some("contract creation"), Category::Unknown, None);
+        tx.to = None;
+        tx.contract_creation = Some(ContractCreation {
+            address: Some("0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d".into()),
+            deployer: Some("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".into()),
+            nonce: 0,
+            init_code_size: 2,
+            init_code_hash: "0xabcd".into(),
+        });
+
+        let any = Trigger::ContractCreation { deployers: vec![], code_hashes: vec![] };
+        assert!(matches_rule(&any, &tx, &HashMap::new()));
+
+        let watched = Trigger::ContractCreation {
+            deployers: vec!["0x6AC7EA33F8831EA9DCC53393AAA88B25A785DBF0".into()],
+            code_hashes: vec![],
+        };
+        assert!(matches_rule(&watched, &tx, &HashMap::new()));
+
+        let template = Trigger::ContractCreation { deployers: vec![], code_hashes: vec!["0xffff".into()] };
+        assert!(!matches_rule(&template, &tx, &HashMap::new()));
+
+        let transfer = make_tx(1.0, Some("ETH transfer"), Category::Unknown, None);
+        assert!(!matches_rule(&any, &transfer, &HashMap::new()));
+    }
+
+    #[test]
+    fn test_gas_trigger() {
+        let mut tx = make_tx(0.0, Some("swap"), Category::Dex, None);
+        tx.max_priority_fee_per_gas = 20_000_000_000;
+        tx.gas_limit = 2_000_000;
+
+        let urgent = Trigger::Gas {
+            min_priority_fee_gwei: Some(10.0),
+            min_max_fee_gwei: None,
+            min_gas_limit: Some(1_000_000),
+            min_tip_multiple: None,
+            min_eth: 0.0,
+        };
+        assert!(matches_rule(&urgent, &tx, &HashMap::new()));
+
+        let outlier = Trigger::Gas {
+            min_priority_fee_gwei: None,
+            min_max_fee_gwei: None,
+            min_gas_limit: None,
+            min_tip_multiple: Some(10.0),
+            min_eth: 0.0,
+        };
+        // No median known: never matches.
+        assert!(!matches_rule(&outlier, &tx, &HashMap::new()));
+        tx.median_tip_wei = Some(1_000_000_000);
+        assert!(matches_rule(&outlier, &tx, &HashMap::new()));
+        tx.median_tip_wei = Some(5_000_000_000);
+        assert!(!matches_rule(&outlier, &tx, &HashMap::new()));
+
+        let empty = RulesConfig::from_toml(r#"
+            [[rules]]
+            name = "gas"
+            [rules.trigger]
+            kind = "gas"
+        "#);
+        assert!(empty.is_err());
+    }
+
+    #[test]
+    fn test_block_triggers_fire_once_per_block() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+
+            [[rules]]
+            name = "full"
+            [rules.trigger]
+            kind = "block_gas"
+            min_gas_used_pct = 90.0
+
+            [[rules]]
+            name = "long"
+            [rules.trigger]
+            kind = "flashblock_count"
+            min_flashblocks = 3
+
+            [[rules]]
+            name = "fee-jump"
+            [rules.trigger]
+            kind = "base_fee_change"
+            min_change_pct = 10.0
+        "#).unwrap();
+        let fb = |payload: &str, index: u64, base_fee: &str, gas_used: u64| {
+            let base = (index == 0).then(|| serde_json::json!({
+                "block_number": "0x10", "gas_limit": "0x64", "base_fee_per_gas": base_fee,
+            }));
+            serde_json::from_value::<crate::types::FlashblockMessage>(serde_json::json!({
+                "payload_id": payload, "index": index, "base": base,
+                "diff": {"gas_used": format!("0x{:x}", gas_used)},
+            })).unwrap()
+        };
+        let mut block = BlockState::default();
+        let mut fired = |block: &mut BlockState, msg, t: u64| -> Vec<String> {
+            block.update(&msg);
+            engine.check_block_at(block, msg.index, 1_000_000 + t).into_iter().map(|a| a.rule_name).collect()
+        };
+
+        assert!(fired(&mut block, fb("a", 0, "0x3b9aca00", 50), 0).is_empty());
+        assert_eq!(fired(&mut block, fb("a", 1, "", 95), 200), ["full"]);
+        // Still over the threshold, but each rule matches once per block.
+        assert_eq!(fired(&mut block, fb("a", 2, "", 97), 400), ["long"]);
+        // Next block: base fee 1 → 1.2 gwei.
+        assert_eq!(fired(&mut block, fb("b", 0, "0x47868c00", 10), 2000), ["fee-jump"]);
+
+        let alert = engine.check_block_at(&block, 0, 1_003_000);
+        assert!(alert.is_empty());
+        let detail = matches_block(&engine.config.rules[2].trigger, &block).unwrap();
+        assert_eq!(detail.summary(), "base fee +20.0% vs previous block (threshold 10%)");
+        assert!(!matches_rule(&engine.config.rules[0].trigger, &make_tx(1.0, None, Category::Unknown, None), &HashMap::new()));
+    }
+
+    #[test]
+    fn test_balance_change_tracks_previous_balances() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+
+            [[rules]]
+            name = "withdrawal"
+            [rules.trigger]
+            kind = "balance_change"
+            min_delta_eth = 10.0
+            direction = "increase"
+
+            [labels]
+            "0x00000000000000000000000000000000000000aa" = "Treasury"
+        "#).unwrap();
+        let fb = |index: u64, balances: serde_json::Value| {
+            serde_json::from_value::<crate::types::FlashblockMessage>(serde_json::json!({
+                "payload_id": "p", "index": index, "diff": {},
+                "metadata": {"new_account_balances": balances},
+            })).unwrap()
+        };
+        let wallet = "0x00000000000000000000000000000000000000AA";
+        let eth = |n: u128| format!("0x{:x}", n * 1_000_000_000_000_000_000);
+
+        // First sighting only records the balance.
+        assert!(engine.check_balances_at(&fb(0, serde_json::json!({wallet: eth(3)})), Some(1), 0).is_empty());
+        let alerts = engine.check_balances_at(&fb(1, serde_json::json!({wallet: eth(15)})), Some(1), 1000);
+        assert_eq!(alerts.len(), 1);
+        let change = alerts[0].balance.as_ref().unwrap();
+        assert_eq!((change.previous_eth, change.balance_eth, change.delta_eth), (3.0, 15.0, 12.0));
+        assert_eq!(change.summary(), "Treasury +12.00 ETH (3.00 → 15.00 ETH)");
+        // A decrease doesn't match an increase rule.
+        assert!(engine.check_balances_at(&fb(2, serde_json::json!({wallet: eth(1)})), Some(1), 2000).is_empty());
+
+        let any = Trigger::BalanceChange { min_delta_eth: 5.0, direction: BalanceDirection::Any };
+        assert!(matches_balance(&any, -6.0) && !matches_balance(&any, 4.0));
+        assert!(!matches_rule(&any, &make_tx(100.0, None, Category::Unknown, None), &HashMap::new()));
+    }
+
+    #[test]
+    fn test_approval_risk_scoring() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+            max_per_minute = 100
+
+            [[rules]]
+            name = "phishing"
+            [rules.trigger]
+            kind = "approval"
+            min_risk = 70
+
+            [labels]
+            "0x00000000000000000000000000000000000000dd" = "Our vault"
+        "#).unwrap();
+        let approve = |spender: &str, unlimited: bool| {
+            let mut tx = make_tx(0.0, Some("approve"), Category::Token, None);
+            tx.hash = Some(format!("0x{spender}{unlimited}"));
+            tx.from = Some("0x00000000000000000000000000000000000000aa".into());
+            tx.approval = Some(crate::decode::Approval {
+                kind: ApprovalKind::Approve,
+                token: tx.to.clone(),
+                owner: tx.from.clone(),
+                spender: spender.into(),
+                amount: None,
+                unlimited,
+            });
+            tx
+        };
+        let drainer = "0x00000000000000000000000000000000000000bb";
+
+        // Unlimited, unlabelled and never seen: 40 + 30 + 10.
+        let alerts = engine.check_at(&approve(drainer, true), Some(1), 0, 0);
+        assert_eq!(alerts.len(), 1);
+        let finding = alerts[0].security.as_ref().unwrap();
+        assert_eq!(finding.risk, 80);
+        assert_eq!(alerts[0].tx.as_ref().unwrap().category, "security");
+        // Limited amounts and known spenders stay under the threshold.
+        assert!(engine.check_at(&approve("0x00000000000000000000000000000000000000cc", false), Some(1), 0, 1000).is_empty());
+        assert!(engine.check_at(&approve("0x2626664c2603336e57b271c5c0b26f421741e481", true), Some(1), 0, 2000).is_empty());
+        assert!(engine.check_at(&approve("0x00000000000000000000000000000000000000dd", true), Some(1), 0, 3000).is_empty());
+
+        // Once the spender has sent a tx it scores as an EOA.
+        let mut sent = make_tx(0.1, Some("ETH transfer"), Category::Unknown, None);
+        sent.from = Some(drainer.into());
+        engine.check_at(&sent, Some(1), 0, 4000);
+        let mut again = approve(drainer, true);
+        again.hash = Some("0xagain".into());
+        let alerts = engine.check_at(&again, Some(1), 0, 5000);
+        assert_eq!(alerts[0].security.as_ref().unwrap().risk, 90);
+        assert!(alerts[0].security.as_ref().unwrap().summary().starts_with("risk 90: approve of 0x1234 to 0x"));
+
+        let mine = Trigger::Approval { min_risk: 0, kinds: vec![], owners: vec!["0x00000000000000000000000000000000000000ee".into()] };
+        let labels = &engine.config.labels;
+        assert!(!matches_rule(&mine, &approve(drainer, true), labels));
+        assert!(matches_rule(&engine.config.rules[0].trigger, &approve(drainer, true), labels));
+
+        // `rules test` and `template render` see the same `[labels]` as the engine.
+        let vault = approve("0x00000000000000000000000000000000000000dd", true);
+        assert!(!matches_rule(&engine.config.rules[0].trigger, &vault, labels));
+        assert!(!explain_match(&engine.config.rules[0].trigger, &vault, labels).iter().all(|c| c.ok));
+        assert!(matches_rule(&engine.config.rules[0].trigger, &vault, &HashMap::new()));
+    }
+
+    #[test]
+    fn test_reload_keeps_cooldowns_and_validates() {
+        let base = r#"
+            [[rules]]
+            name = "big"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 10.0
+
+            [[rules]]
+            name = "gone"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#;
+        let mut engine = RuleEngine::from_toml(base).unwrap();
+        engine.last_fired.insert("big".into(), 1_000);
+        engine.last_fired.insert("gone".into(), 1_000);
+
+        let next = RulesConfig::from_toml(r#"
+            [[rules]]
+            name = "big"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 50.0
+        "#).unwrap();
+        let diff = engine.config.diff(&next);
+        assert!(diff.iter().any(|l| l.starts_with("~ rule big")));
+        assert!(diff.iter().any(|l| l == "- rule gone"));
+
+        engine.reload(next);
+        assert!(engine.last_fired.contains_key("big"));
+        assert!(!engine.last_fired.contains_key("gone"));
+
+        let bad = RulesConfig::from_toml(r#"
+            [[rules]]
+            name = "watch"
+            [rules.trigger]
+            kind = "address"
+            address = "0x1234"
+        "#);
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_inline_credentials_are_refused() {
+        let webhook = |extra: &str| RulesConfig::from_toml(&format!(r#"
+            [global]
+            max_per_minute = 30
+
+            [[rules]]
+            name = "hook"
+            {extra}
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#));
+        let err = |extra: &str| webhook(extra).unwrap_err().to_string();
+        assert!(err(r#"webhook = "https://example.com/hook"
+            headers = { "X-Api-Key" = "abc123" }"#).contains("header 'X-Api-Key' looks like a credential"));
+        assert!(err(r#"webhook = "https://hooks.slack.com/services/T0/B0/xyz"
+            sink = "slack""#).contains("webhook URL contains a token"));
+        assert!(err(r#"webhook = "https://api.telegram.org/bot123:abc/sendMessage?chat_id=1"
+            sink = "telegram""#).contains("webhook URL contains a token"));
+        assert!(err(r#"webhook = "https://example.com/hook"
+            auth = { bearer = "token" }"#).contains("not written inline"));
+        assert!(err(r#"webhook = "https://example.com/hook"
+            auth = { basic = { username = "fw", password = "hunter2" } }"#).contains("not written inline"));
+
+        // References are fine.
+        assert!(webhook(r#"webhook = "env:SLACK_WEBHOOK_URL"
+            sink = "slack"
+            headers = { "X-Api-Key" = "file:/run/secrets/key", "X-Team" = "risk" }"#).is_ok());
+        assert!(webhook(r#"webhook = "https://example.com/hook"
+            auth = { bearer = "env:HOOK_TOKEN" }"#).is_ok());
+    }
+
+    #[test]
+    fn test_cooldown_and_rate_limit_in_simulated_time() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+            max_per_minute = 2
+
+            [[rules]]
+            name = "big"
+            cooldown_secs = 10
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+
+            [[rules]]
+            name = "any"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+        "#).unwrap();
+        let tx = make_tx(5.0, Some("swap"), Category::Dex, None);
+        let fired = |alerts: Vec<Alert>| alerts.into_iter().map(|a| a.rule_name).collect::<Vec<_>>();
+
+        let t0 = 1_700_000_000_000;
+        assert_eq!(fired(engine.check_at(&tx, Some(1), 0, t0)), ["big", "any"]);
+        // Rate limit reached for this minute.
+        assert!(engine.check_at(&tx, Some(2), 0, t0 + 30_000).is_empty());
+        // A minute later: "big" is out of cooldown again, and so is the rate limit.
+        let alerts = engine.check_at(&tx, Some(3), 0, t0 + 60_000);
+        assert_eq!(alerts[0].timestamp, 1_700_000_060);
+        assert_eq!(fired(alerts), ["big", "any"]);
+    }
+
+    #[test]
+    fn test_severity_priority_and_per_rule_limits() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+            max_per_minute = 2
+
+            [[rules]]
+            name = "noisy"
+            severity = "info"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+
+            [[rules]]
+            name = "capped"
+            priority = 5
+            max_per_minute = 1
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 0.0
+
+            [[rules]]
+            name = "whale"
+            severity = "critical"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#).unwrap();
+        let tx = make_tx(5.0, Some("swap"), Category::Dex, None);
+        let fired = |alerts: Vec<Alert>| alerts.into_iter().map(|a| a.rule_name).collect::<Vec<_>>();
+
+        let t0 = 1_700_000_000_000;
+        // Critical goes first; "capped" outranks "noisy" by priority and takes the last slot.
+        assert_eq!(fired(engine.check_at(&tx, Some(1), 0, t0)), ["whale", "capped"]);
+        // Budget spent, but critical still fires.
+        assert_eq!(fired(engine.check_at(&tx, Some(2), 0, t0 + 1_000)), ["whale"]);
+
+        let capped = engine.stats("capped").unwrap();
+        assert_eq!((capped.matched, capped.fired, capped.suppressed_rule_limit), (2, 1, 1));
+        let noisy = engine.stats("noisy").unwrap();
+        assert_eq!((noisy.matched, noisy.suppressed_global_limit), (2, 2));
+        assert_eq!(engine.stats("whale").unwrap().suppressed(), 0);
+    }
+
+    #[test]
+    fn test_suppressed_matches_reported_on_next_fire_or_digest() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            max_per_minute = 10
+
+            [[rules]]
+            name = "big"
+            cooldown_secs = 10
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#).unwrap();
+        let t0 = 1_700_000_000_000;
+        let small = make_tx(0.5, Some("swap"), Category::Dex, None);
+
+        assert!(engine.check_at(&make_tx(2.0, None, Category::Dex, None), Some(1), 0, t0)[0].suppressed.is_none());
+        assert!(engine.check_at(&make_tx(3.0, None, Category::Dex, None), Some(1), 1, t0 + 1_000).is_empty());
+        assert!(engine.check_at(&make_tx(7.0, None, Category::Dex, None), Some(1), 2, t0 + 2_000).is_empty());
+
+        // Cooldown over and the next match fires: it carries the summary.
+        let alerts = engine.check_at(&make_tx(4.0, None, Category::Dex, None), Some(2), 0, t0 + 10_000);
+        let s = alerts[0].suppressed.as_ref().unwrap();
+        assert!(matches!(s.largest, SuppressedMatch::Tx(ref tx) if tx.value_eth == 7.0));
+        assert_eq!((s.count, s.total_eth), (2, 10.0));
+        assert_eq!(alerts[0].suppressed_note().unwrap(), "plus 2 similar matches totalling 10 ETH suppressed");
+
+        // Suppressed, then no further match: a digest goes out once the cooldown ends.
+        assert!(engine.check_at(&make_tx(5.0, None, Category::Dex, None), Some(2), 1, t0 + 11_000).is_empty());
+        assert!(engine.check_at(&small, Some(3), 0, t0 + 15_000).is_empty());
+        let digest = engine.check_at(&small, Some(4), 0, t0 + 20_000);
+        assert!(digest[0].digest);
+        assert_eq!(digest[0].tx.as_ref().unwrap().value_eth, 5.0);
+        assert!(engine.check_at(&small, Some(5), 0, t0 + 40_000).is_empty());
+    }
+
+    #[test]
+    fn test_block_and_balance_matches_are_summarised() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            max_per_minute = 10
+
+            [[rules]]
+            name = "withdrawal"
+            cooldown_secs = 10
+            [rules.trigger]
+            kind = "balance_change"
+            min_delta_eth = 10.0
+
+            [[rules]]
+            name = "full"
+            cooldown_secs = 10
+            [rules.trigger]
+            kind = "block_gas"
+            min_gas_used_pct = 90.0
+        "#).unwrap();
+        let t0 = 1_700_000_000_000;
+        let balances = |index: u64, wei: u128| {
+            serde_json::from_value::<crate::types::FlashblockMessage>(serde_json::json!({
+                "payload_id": "p", "index": index, "diff": {},
+                "metadata": {"new_account_balances": {"0x00000000000000000000000000000000000000aa": format!("0x{:x}", wei * 1_000_000_000_000_000_000)}},
+            })).unwrap()
+        };
+        engine.check_balances_at(&balances(0, 1), Some(1), t0);
+        assert_eq!(engine.check_balances_at(&balances(1, 20), Some(1), t0 + 1_000).len(), 1);
+        assert!(engine.check_balances_at(&balances(2, 50), Some(1), t0 + 2_000).is_empty());
+        assert!(engine.check_balances_at(&balances(3, 35), Some(1), t0 + 3_000).is_empty());
+
+        // Cooldown over with no further change: a digest carrying the largest change.
+        let digest = engine.check_balances_at(&balances(4, 35), Some(2), t0 + 11_000);
+        assert!(digest[0].digest);
+        assert_eq!(digest[0].balance.as_ref().unwrap().delta_eth, 30.0);
+        assert_eq!(digest[0].suppressed_note().unwrap(), "plus 2 similar matches totalling 45 ETH suppressed");
+
+        let block = |payload: &str| {
+            let mut block = BlockState::default();
+            block.update(&serde_json::from_value(serde_json::json!({
+                "payload_id": payload, "index": 0,
+                "base": {"block_number": "0x10", "gas_limit": "0x64", "base_fee_per_gas": "0x1"},
+                "diff": {"gas_used": "0x5f"},
+            })).unwrap());
+            block
+        };
+        assert!(engine.check_block_at(&block("a"), 0, t0)[0].suppressed.is_none());
+        assert!(engine.check_block_at(&block("b"), 0, t0 + 5_000).is_empty());
+        let alerts = engine.check_block_at(&block("c"), 0, t0 + 20_000);
+        assert!(!alerts[0].digest);
+        assert_eq!(alerts[0].suppressed_note().unwrap(), "plus 1 similar match suppressed");
+    }
+
+    #[test]
+    fn test_dedup_merges_rules_and_skips_replays() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+            dedup = true
+
+            [[rules]]
+            name = "large-value"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 10.0
+
+            [[rules]]
+            name = "whale-transfer"
+            severity = "critical"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 100.0
+        "#).unwrap();
+        let mut tx = make_tx(150.0, Some("ETH transfer"), Category::Unknown, None);
+        tx.hash = Some("0xaa".into());
+        let t0 = 1_700_000_000_000;
+
+        let alerts = engine.check_at(&tx, Some(1), 0, t0);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].rule_name, "whale-transfer");
+        assert_eq!(alerts[0].matched_rules, ["whale-transfer", "large-value"]);
+        assert_eq!(alerts[0].severity, Severity::Critical);
+
+        // The same tx replayed after a reconnect.
+        assert!(engine.check_at(&tx, Some(1), 0, t0 + 5_000).is_empty());
+
+        // Hashes seeded from a previous run are skipped too.
+        tx.hash = Some("0xbb".into());
+        engine.mark_seen(["0xbb".to_string()]);
+        assert!(engine.check_at(&tx, Some(2), 0, t0 + 10_000).is_empty());
+
+        // Merged rules don't fire, so their cooldown doesn't start.
+        engine.config.global.cooldown_secs = 10;
+        let hashed = |eth: f64, hash: &str| {
+            let mut tx = make_tx(eth, None, Category::Unknown, None);
+            tx.hash = Some(hash.into());
+            tx
+        };
+        assert_eq!(engine.check_at(&hashed(150.0, "0x01"), Some(3), 0, t0 + 20_000)[0].rule_name, "whale-transfer");
+        assert_eq!(engine.check_at(&hashed(20.0, "0x02"), Some(3), 1, t0 + 21_000)[0].rule_name, "large-value");
+        let merged = engine.stats("large-value").unwrap();
+        // Every match is accounted for: one fire, two merges into whale-transfer's alerts.
+        assert_eq!((merged.matched, merged.fired, merged.merged, merged.suppressed()), (3, 1, 2, 0));
+
+        // A held match alerts nobody, so its tx isn't marked seen.
+        assert!(engine.check_at(&hashed(30.0, "0x03"), Some(3), 2, t0 + 22_000).is_empty());
+        assert!(!engine.seen.contains("0x03"));
+
+        // A replay still lets the digest out.
+        let digest = engine.check_at(&hashed(150.0, "0x01"), Some(4), 0, t0 + 32_000);
+        assert_eq!(digest.len(), 1);
+        assert!(digest[0].digest && digest[0].rule_name == "large-value");
+    }
+
+    #[test]
+    fn test_schedule_and_snoozes() {
+        let mut engine = RuleEngine::from_toml(r#"
+            [global]
+            cooldown_secs = 0
+
+            [[rules]]
+            name = "night-weekdays"
+            [rules.schedule]
+            hours = [22, 6]
+            days = ["mon", "tue", "wed", "thu", "fri"]
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#).unwrap();
+        let mut tx = make_tx(5.0, None, Category::Unknown, None);
+        tx.from = Some("0xAbC0000000000000000000000000000000000001".into());
+
+        // 2023-11-13 is a Monday.
+        let monday_23h = 1_699_916_400_000;
+        let monday_noon = monday_23h - 11 * 3_600_000;
+        let saturday_23h = monday_23h + 5 * 86_400_000;
+        assert_eq!(engine.check_at(&tx, None, 0, monday_23h).len(), 1);
+        assert!(engine.check_at(&tx, None, 0, monday_noon).is_empty());
+        assert!(engine.check_at(&tx, None, 0, saturday_23h).is_empty());
+
+        let now = monday_23h / 1000;
+        engine.snooze(SnoozeKind::Rule, "night-weekdays", now + 600);
+        assert!(engine.check_at(&tx, None, 0, monday_23h + 60_000).is_empty());
+        assert_eq!(engine.snoozed_until(SnoozeKind::Rule, "night-weekdays", now), Some(now + 600));
+        assert_eq!(engine.check_at(&tx, None, 0, monday_23h + 600_000).len(), 1);
+
+        engine.snooze(SnoozeKind::Address, "0xabc0000000000000000000000000000000000001", now + 3_600);
+        assert!(engine.check_at(&tx, None, 0, monday_23h + 700_000).is_empty());
+        assert!(engine.unsnooze(SnoozeKind::Address, "0xABC0000000000000000000000000000000000001"));
+        assert_eq!(engine.check_at(&tx, None, 0, monday_23h + 800_000).len(), 1);
+
+        let bad = RulesConfig::from_toml(r#"
+            [[rules]]
+            name = "typo"
+            [rules.schedule]
+            days = ["monday"]
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 1.0
+        "#);
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_lint_flags_unreachable_and_duplicate_rules() {
+        let config = RulesConfig::from_toml(r#"
+            [global]
+            max_per_minute = 10
+
+            [[rules]]
+            name = "typo"
+            [rules.trigger]
+            kind = "protocol"
+            names = ["Uniswap V9 Router"]
+            categories = ["dexx"]
+
+            [[rules]]
+            name = "big"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 10.0
+
+            [[rules]]
+            name = "big-again"
+            [rules.trigger]
+            kind = "large_value"
+            min_eth = 10.0
+        "#).unwrap();
+        let findings = config.lint();
+        let about = |rule: &str, text: &str| {
+            findings.iter().any(|f| f.rule.as_deref() == Some(rule) && f.message.contains(text))
+        };
+        assert!(about("typo", "unknown category 'dexx'"));
+        assert!(about("typo", "'Uniswap V9 Router' matches no known label"));
+        assert!(about("big-again", "same trigger as 'big'"));
+        assert!(!findings.iter().any(|f| f.rule.as_deref() == Some("big")));
+    }
+
+    #[test]
+    fn test_explain_match_agrees_with_matches_rule() {
+        let triggers = [
+            Trigger::EthTransfer { min_eth: 5.0 },
+            Trigger::LargeValue { min_eth: 1.0 },
+            Trigger::Protocol { names: vec!["Aerodrome Router".into()], categories: vec!["dex".into()], min_eth: 0.0 },
+            Trigger::FunctionCall { actions: vec!["swap".into()], min_eth: 0.5 },
+            Trigger::Address { address: "0x1234".into(), min_eth: 0.0 },
+            Trigger::ContractCreation { deployers: vec![], code_hashes: vec![] },
+            Trigger::Gas {
+                min_priority_fee_gwei: Some(0.0),
+                min_max_fee_gwei: None,
+                min_gas_limit: Some(30_000),
+                min_tip_multiple: None,
+                min_eth: 0.0,
+            },
+        ];
+        let txs = [
+            make_tx(10.0, Some("ETH transfer"), Category::Unknown, None),
+            make_tx(0.1, Some("swap"), Category::Dex, Some("Aerodrome Router")),
+            make_tx(2.0, Some("swapExactTokensForTokens"), Category::Dex, Some("Uniswap V3 Router")),
+        ];
+        for trigger in &triggers {
+            for tx in &txs {
+                let explained = explain_match(trigger, tx, &HashMap::new()).iter().all(|c| c.ok);
+                assert_eq!(explained, matches_rule(trigger, tx, &HashMap::new()), "{trigger:?} vs {:?}", tx.action);
+            }
+        }
+    }
+
+    #[test]
+    fn test_update_toml_keeps_comments_and_untouched_rules() {
+        let source = include_str!("../rules.example.toml");
+        let config = RulesConfig::from_toml(source).unwrap();
+        let mut next = config.clone();
+        next.rules[0].enabled = false;
+        let removed = next.rules.remove(1).name;
+        let mut added = next.rules[0].clone();
+        added.name = "from-api".into();
+        next.rules.push(added);
+
+        let written = config.update_toml(source, &next).unwrap();
+        let again = RulesConfig::from_toml(&written).unwrap();
+        assert_eq!(again.rules, next.rules);
+        assert_eq!(again.global, config.global);
+        assert_eq!(again.labels, config.labels);
+
+        // Everything before the first rule is byte for byte the same, and so are the
+        // comments above the edited rule and the rules after the removed one.
+        let head = &source[..source.find("[[rules]]").unwrap()];
+        assert!(written.starts_with(head));
+        assert!(written.contains("# Whale ETH transfers (>100 ETH)\n# For OpenClaw integration"));
+        let last = &source[source.rfind("# Unlimited approvals").unwrap()..];
+        assert!(written.contains(last.trim_end()), "{written}");
+        assert!(!written.contains(&format!("name = \"{removed}\"")));
+        assert!(written.trim_end().ends_with("min_eth = 100.0"), "{written}");
+
+        // No change, no rewrite.
+        assert_eq!(config.update_toml(source, &config).unwrap(), source);
     }
 }
@@ -0,0 +1,574 @@
+//! Rules REST API — list, create, replace, patch and delete alert rules at runtime.
+//!
+//! Edits are validated, applied to the live `RuleEngine` (cooldown state is kept),
+//! and written back to the rules TOML file so they survive a restart. Writes
+//! require `Authorization: Bearer $FLASHWATCH_ADMIN_TOKEN`; without that env var
+//! the API is read-only.
+//!
+//!   GET    /api/rules           — all rules + global settings
+//!   GET    /api/rules/schema    — JSON schema for rule bodies
+//!   GET    /api/rules/{name}    — one rule
+//!   POST   /api/rules/{name}    — create (409 if it exists)
+//!   PUT    /api/rules/{name}    — create or replace
+//!   PATCH  /api/rules/{name}    — JSON merge patch (RFC 7396), e.g. {"enabled": false}
+//!   DELETE /api/rules/{name}    — remove
+//!   POST   /api/rules/{name}/snooze          — silence a rule, body {"minutes": 30}; 0 clears
+//!   DELETE /api/rules/{name}/snooze          — clear a rule snooze
+//!   POST   /api/addresses/{address}/snooze   — silence every alert from or to an address
+//!   DELETE /api/addresses/{address}/snooze   — clear an address snooze
+//!
+//! Snoozes are kept in SQLite rather than the rules file, and survive restarts.
+//!
+//! The API can't add an `exec` sink command that isn't already in the rules file,
+//! so the admin token is never enough to run a program on the host.
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use tracing::info;
+
+use crate::rules::{Rule, RuleEngine, RulesConfig, SnoozeKind};
+use crate::serve::AppState;
+use crate::sink::{SecretRef, SinkKind};
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn api_error(status: StatusCode, msg: impl std::fmt::Display) -> ApiError {
+    (status, Json(json!({ "error": msg.to_string() })))
+}
+
+/// Public view of a rule: the rule as written, with inline webhook URLs and header
+/// values redacted, plus its live state. PUT accepts it back unchanged — redacted
+/// values are swapped for the stored ones (see `keep_redacted`).
+fn rule_json(rule: &Rule, engine: &RuleEngine) -> serde_json::Value {
+    let now = now_secs();
+    let mut redacted = rule.clone();
+    redacted.webhook = rule.webhook.as_deref().map(|url| redact_webhook(rule.sink, url));
+    for value in redacted.headers.values_mut() {
+        *value = redact_header(value);
+    }
+    let mut view = serde_json::to_value(&redacted).unwrap_or_else(|_| json!({"name": rule.name}));
+    if let Some(obj) = view.as_object_mut() {
+        obj.insert("signed".into(), json!(rule.destination(&engine.config.global).is_some_and(|d| d.signing_secret.is_some())));
+        obj.insert("effective_cooldown_secs".into(), json!(rule.cooldown_secs.unwrap_or(engine.config.global.cooldown_secs)));
+        obj.insert("in_schedule".into(), json!(rule.schedule.as_ref().is_none_or(|s| s.active_at(now * 1000))));
+        obj.insert("snoozed_until".into(), json!(engine.snoozed_until(SnoozeKind::Rule, &rule.name, now)));
+        obj.insert("stats".into(), json!(engine.stats(&rule.name).cloned().unwrap_or_default()));
+    }
+    view
+}
+
+/// Shown in place of an inline header value.
+const REDACTED: &str = "<redacted>";
+/// Longest snooze accepted: one year.
+const MAX_SNOOZE_MINUTES: u64 = 365 * 24 * 60;
+
+/// An inline webhook URL cut down to `scheme://host[:port]/…`, since the path or
+/// query may hold a token. References and exec commands are shown as written.
+fn redact_webhook(sink: SinkKind, url: &str) -> String {
+    if sink == SinkKind::Exec || SecretRef::is_reference(url) {
+        return url.to_string();
+    }
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.host_str().is_some() => {
+            let port = parsed.port().map(|p| format!(":{p}")).unwrap_or_default();
+            format!("{}://{}{}/…", parsed.scheme(), parsed.host_str().unwrap_or_default(), port)
+        }
+        _ => REDACTED.to_string(),
+    }
+}
+
+fn redact_header(value: &str) -> String {
+    if SecretRef::is_reference(value) { value.to_string() } else { REDACTED.to_string() }
+}
+
+/// Put back the stored webhook URL and header values where a body carries their
+/// redacted form, so a rule fetched with GET can be sent back with PUT or PATCH.
+fn keep_redacted(rule: &mut Rule, existing: &Rule) {
+    if let (Some(url), Some(stored)) = (&rule.webhook, &existing.webhook)
+        && *url != *stored
+        && *url == redact_webhook(existing.sink, stored)
+    {
+        rule.webhook = Some(stored.clone());
+    }
+    for (name, value) in rule.headers.iter_mut() {
+        if let Some(stored) = existing.headers.get(name)
+            && *value != *stored
+            && *value == redact_header(stored)
+        {
+            *value = stored.clone();
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub async fn rules_handler(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let Some(ref rules) = state.rules else {
+        return Json(json!({"rules": [], "global": null}));
+    };
+    let engine = rules.lock().await;
+    let config = &engine.config;
+    let rules: Vec<serde_json::Value> = config.rules.iter().map(|r| rule_json(r, &engine)).collect();
+    let snoozed_addresses: Vec<serde_json::Value> = engine
+        .snoozes(now_secs())
+        .into_iter()
+        .filter(|(kind, _, _)| *kind == SnoozeKind::Address)
+        .map(|(_, address, until)| json!({"address": address, "until": until}))
+        .collect();
+    Json(json!({
+        "rules": rules,
+        "snoozed_addresses": snoozed_addresses,
+        "global": {
+            "cooldown_secs": config.global.cooldown_secs,
+            "max_per_minute": config.global.max_per_minute,
+            "retention_days": config.global.retention_days,
+        },
+        "editable": state.admin_token.is_some() && state.rules_path.is_some(),
+    }))
+}
+
+pub async fn rule_schema_handler() -> Json<serde_json::Value> {
+    Json(crate::rules::rule_schema())
+}
+
+pub async fn rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Response {
+    let Some(ref rules) = state.rules else {
+        return api_error(StatusCode::NOT_FOUND, "no rules loaded").into_response();
+    };
+    let engine = rules.lock().await;
+    match engine.config.rules.iter().find(|r| r.name == name) {
+        Some(rule) => Json(rule_json(rule, &engine)).into_response(),
+        None => api_error(StatusCode::NOT_FOUND, format!("no rule named '{name}'")).into_response(),
+    }
+}
+
+pub async fn create_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> Response {
+    let result = edit_rules(&state, &headers, |config| {
+        if config.rules.iter().any(|r| r.name == name) {
+            return Err(api_error(StatusCode::CONFLICT, format!("rule '{name}' already exists")));
+        }
+        config.rules.push(rule_from_body(&name, body)?);
+        Ok(StatusCode::CREATED)
+    }).await;
+    respond(result, &state, &name).await
+}
+
+pub async fn replace_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> Response {
+    let result = edit_rules(&state, &headers, |config| {
+        let mut rule = rule_from_body(&name, body)?;
+        match config.rules.iter_mut().find(|r| r.name == name) {
+            Some(existing) => {
+                keep_redacted(&mut rule, existing);
+                *existing = rule;
+                Ok(StatusCode::OK)
+            }
+            None => {
+                config.rules.push(rule);
+                Ok(StatusCode::CREATED)
+            }
+        }
+    }).await;
+    respond(result, &state, &name).await
+}
+
+pub async fn patch_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(patch): Json<serde_json::Value>,
+) -> Response {
+    let result = edit_rules(&state, &headers, |config| {
+        let Some(existing) = config.rules.iter_mut().find(|r| r.name == name) else {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("no rule named '{name}'")));
+        };
+        let mut merged = serde_json::to_value(&*existing)
+            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        merge_patch(&mut merged, &patch);
+        let mut rule = rule_from_body(&name, merged)?;
+        keep_redacted(&mut rule, existing);
+        *existing = rule;
+        Ok(StatusCode::OK)
+    }).await;
+    respond(result, &state, &name).await
+}
+
+pub async fn delete_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let result = edit_rules(&state, &headers, |config| {
+        let before = config.rules.len();
+        config.rules.retain(|r| r.name != name);
+        if config.rules.len() == before {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("no rule named '{name}'")));
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }).await;
+    match result {
+        Ok(status) => status.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct SnoozeBody {
+    minutes: u64,
+}
+
+pub async fn snooze_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<SnoozeBody>,
+) -> Response {
+    let result = set_snooze(&state, &headers, SnoozeKind::Rule, &name, body.minutes).await;
+    respond(result.map(|_| StatusCode::OK), &state, &name).await
+}
+
+pub async fn unsnooze_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let result = set_snooze(&state, &headers, SnoozeKind::Rule, &name, 0).await;
+    respond(result.map(|_| StatusCode::OK), &state, &name).await
+}
+
+pub async fn snooze_address_handler(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<SnoozeBody>,
+) -> Response {
+    address_snooze_response(set_snooze(&state, &headers, SnoozeKind::Address, &address, body.minutes).await, &address)
+}
+
+pub async fn unsnooze_address_handler(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    address_snooze_response(set_snooze(&state, &headers, SnoozeKind::Address, &address, 0).await, &address)
+}
+
+fn address_snooze_response(result: Result<Option<u64>, ApiError>, address: &str) -> Response {
+    match result {
+        Ok(until) => Json(json!({"address": address.to_lowercase(), "snoozed_until": until})).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Snooze `target` for `minutes` (0 clears) in the live engine and in SQLite.
+/// Returns when the snooze ends, or `None` if it was cleared.
+async fn set_snooze(
+    state: &AppState,
+    headers: &HeaderMap,
+    kind: SnoozeKind,
+    target: &str,
+    minutes: u64,
+) -> Result<Option<u64>, ApiError> {
+    check_admin(state, headers)?;
+    let Some(ref rules) = state.rules else {
+        return Err(api_error(StatusCode::NOT_FOUND, "serve was started without --rules"));
+    };
+    let until = minutes
+        .checked_mul(60)
+        .and_then(|secs| now_secs().checked_add(secs))
+        .filter(|_| minutes <= MAX_SNOOZE_MINUTES)
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, format!("minutes must be at most {MAX_SNOOZE_MINUTES} (one year)")))?;
+    let mut engine = rules.lock().await;
+    match kind {
+        SnoozeKind::Rule if !engine.config.rules.iter().any(|r| r.name == target) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("no rule named '{target}'")));
+        }
+        SnoozeKind::Address if !crate::rules::is_address(target) => {
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("'{target}' is not a 20-byte hex address")));
+        }
+        _ => {}
+    }
+
+    let db_error = |e: eyre::Report| api_error(StatusCode::INTERNAL_SERVER_ERROR, e);
+    let key = if kind == SnoozeKind::Address { target.to_lowercase() } else { target.to_string() };
+    if minutes == 0 {
+        if let Some(ref store) = state.store {
+            store.clear_snooze(kind, &key).map_err(db_error)?;
+        }
+        engine.unsnooze(kind, &key);
+        info!("Cleared snooze on {} {}", kind.as_str(), key);
+        return Ok(None);
+    }
+
+    if let Some(ref store) = state.store {
+        store.set_snooze(kind, &key, until).map_err(db_error)?;
+    }
+    engine.snooze(kind, &key, until);
+    info!("Snoozed {} {} for {}m", kind.as_str(), key, minutes);
+    Ok(Some(until))
+}
+
+async fn respond(result: Result<StatusCode, ApiError>, state: &AppState, name: &str) -> Response {
+    match result {
+        Ok(status) => {
+            let Some(ref rules) = state.rules else {
+                return status.into_response();
+            };
+            let engine = rules.lock().await;
+            match engine.config.rules.iter().find(|r| r.name == name) {
+                Some(rule) => (status, Json(rule_json(rule, &engine))).into_response(),
+                None => status.into_response(),
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Authorize, apply `edit` to a copy of the live config, validate it, write it to
+/// the rules file and swap it into the engine. Nothing changes unless every step succeeds.
+async fn edit_rules<F>(state: &AppState, headers: &HeaderMap, edit: F) -> Result<StatusCode, ApiError>
+where
+    F: FnOnce(&mut RulesConfig) -> Result<StatusCode, ApiError>,
+{
+    check_admin(state, headers)?;
+    let (Some(rules), Some(path)) = (&state.rules, &state.rules_path) else {
+        return Err(api_error(StatusCode::NOT_FOUND, "serve was started without --rules"));
+    };
+
+    let mut engine = rules.lock().await;
+    let mut next = engine.config.clone();
+    let status = edit(&mut next)?;
+    let current = crate::exec::commands(&engine.config);
+    if let Some(command) = crate::exec::commands(&next).into_iter().find(|c| !current.contains(c)) {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{command}' is not in the rules file — exec sinks can only be added there")));
+    }
+    next.validate().map_err(|e| api_error(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to read {}: {}", path.display(), e))
+    })?;
+    let toml = engine.config.update_toml(&source, &next).map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    write_atomic(path, &toml).map_err(|e| {
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to write {}: {}", path.display(), e))
+    })?;
+
+    let diff = engine.config.diff(&next);
+    crate::exec::retire_streams(&engine.config, &next);
+    engine.reload(next);
+    info!("Rules edited via API, saved to {}", path.display());
+    for line in &diff {
+        info!("  {}", line);
+    }
+    Ok(status)
+}
+
+pub(crate) fn check_admin(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(ref token) = state.admin_token else {
+        return Err(api_error(StatusCode::FORBIDDEN, "rule editing disabled — set FLASHWATCH_ADMIN_TOKEN"));
+    };
+    let supplied = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if !supplied.is_some_and(|supplied| crate::signing::token_matches(token, supplied)) {
+        return Err(api_error(StatusCode::UNAUTHORIZED, "missing or invalid admin token"));
+    }
+    Ok(())
+}
+
+/// Parse a rule body, taking the name from the URL.
+fn rule_from_body(name: &str, mut body: serde_json::Value) -> Result<Rule, ApiError> {
+    let Some(obj) = body.as_object_mut() else {
+        return Err(api_error(StatusCode::BAD_REQUEST, "rule body must be a JSON object"));
+    };
+    match obj.get("name").and_then(|n| n.as_str()) {
+        Some(n) if n != name => {
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("body name '{n}' does not match URL '{name}'")));
+        }
+        _ => {}
+    }
+    obj.insert("name".into(), json!(name));
+    serde_json::from_value(body).map_err(|e| api_error(StatusCode::UNPROCESSABLE_ENTITY, e))
+}
+
+/// RFC 7396 JSON merge patch: objects merge recursively, `null` deletes, anything else replaces.
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let Some(patch_obj) = patch.as_object() else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    if let Some(target_obj) = target.as_object_mut() {
+        for (key, value) in patch_obj {
+            if value.is_null() {
+                target_obj.remove(key);
+            } else {
+                merge_patch(target_obj.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Write via a temp file + rename so the file watcher never sees a half-written file.
+fn write_atomic(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("toml.tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"[global]
+max_per_minute = 30
+
+# Treasury outflows — paged to the risk channel
+[[rules]]
+name = "whale"
+webhook = "https://hooks.example.com/in/T000/s3cret?key=abc"
+sink = "json"
+headers = { "X-Team" = "risk" }
+
+[rules.trigger]
+kind = "large_value"
+min_eth = 100.0
+"#;
+
+    fn state(name: &str, admin_token: Option<&str>) -> Arc<AppState> {
+        let path = std::env::temp_dir().join(format!("flashwatch-rules-api-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, RULES).unwrap();
+        Arc::new(AppState::for_tests(Some(path), admin_token))
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {token}").parse().unwrap());
+        headers
+    }
+
+    async fn body(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target = json!({"a": "b", "c": {"d": "e", "f": "g"}, "h": [1]});
+        merge_patch(&mut target, &json!({"a": "z", "c": {"f": null}, "h": [2], "i": {"j": 1}}));
+        assert_eq!(target, json!({"a": "z", "c": {"d": "e"}, "h": [2], "i": {"j": 1}}));
+
+        let mut target = json!({"a": 1});
+        merge_patch(&mut target, &json!(["x"]));
+        assert_eq!(target, json!(["x"]));
+        let mut target = json!("scalar");
+        merge_patch(&mut target, &json!({"a": null, "b": 1}));
+        assert_eq!(target, json!({"b": 1}));
+    }
+
+    #[test]
+    fn test_check_admin() {
+        let read_only = AppState::for_tests(None, None);
+        assert_eq!(check_admin(&read_only, &bearer("t0ken")).unwrap_err().0, StatusCode::FORBIDDEN);
+
+        let state = AppState::for_tests(None, Some("t0ken"));
+        assert_eq!(check_admin(&state, &HeaderMap::new()).unwrap_err().0, StatusCode::UNAUTHORIZED);
+        assert_eq!(check_admin(&state, &bearer("t0ke")).unwrap_err().0, StatusCode::UNAUTHORIZED);
+        assert_eq!(check_admin(&state, &bearer("t0ken2")).unwrap_err().0, StatusCode::UNAUTHORIZED);
+        assert!(check_admin(&state, &bearer("t0ken")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_edit_errors() {
+        let state = state("errors", Some("t0ken"));
+        let rule = || Json(json!({"trigger": {"kind": "large_value", "min_eth": 5.0}}));
+
+        let response = create_rule_handler(State(state.clone()), Path("whale".into()), bearer("t0ken"), rule()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = create_rule_handler(State(state.clone()), Path("new".into()), bearer("wrong"), rule()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = patch_rule_handler(State(state.clone()), Path("nope".into()), bearer("t0ken"), Json(json!({"enabled": false}))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = delete_rule_handler(State(state.clone()), Path("nope".into()), bearer("t0ken")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Bodies that don't parse, and rules that don't validate.
+        let bad_kind = Json(json!({"trigger": {"kind": "no_such_trigger"}}));
+        let response = replace_rule_handler(State(state.clone()), Path("new".into()), bearer("t0ken"), bad_kind).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = replace_rule_handler(State(state.clone()), Path("schema".into()), bearer("t0ken"), rule()).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body(response).await["error"].as_str().unwrap().contains("reserved"));
+        let response = replace_rule_handler(State(state.clone()), Path("<img src=x onerror=alert(1)>".into()), bearer("t0ken"), rule()).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // None of it reached the file.
+        let path = state.rules_path.as_ref().unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), RULES);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snooze_length_is_capped() {
+        let state = state("snooze", Some("t0ken"));
+        for minutes in [u64::MAX, MAX_SNOOZE_MINUTES + 1] {
+            let response = snooze_rule_handler(State(state.clone()), Path("whale".into()), bearer("t0ken"), Json(SnoozeBody { minutes })).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let response = snooze_address_handler(State(state.clone()), Path(format!("0x{}", "ab".repeat(20))), bearer("t0ken"), Json(SnoozeBody { minutes })).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        let response = snooze_rule_handler(State(state.clone()), Path("whale".into()), bearer("t0ken"), Json(SnoozeBody { minutes: MAX_SNOOZE_MINUTES })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let until = body(response).await["snoozed_until"].as_u64().unwrap();
+        assert!(until >= now_secs() + MAX_SNOOZE_MINUTES * 60 - 5);
+        std::fs::remove_file(state.rules_path.as_ref().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_put_round_trip_keeps_redacted_values() {
+        let state = state("round-trip", Some("t0ken"));
+        let rule = body(rule_handler(State(state.clone()), Path("whale".into())).await).await;
+        assert_eq!(rule["webhook"], "https://hooks.example.com/…");
+        assert_eq!(rule["headers"]["X-Team"], REDACTED);
+        assert!(!rule.to_string().contains("s3cret"));
+
+        let mut edited = rule.clone();
+        edited["enabled"] = json!(false);
+        let response = replace_rule_handler(State(state.clone()), Path("whale".into()), bearer("t0ken"), Json(edited)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let written = std::fs::read_to_string(state.rules_path.as_ref().unwrap()).unwrap();
+        assert!(written.contains("# Treasury outflows — paged to the risk channel\n[[rules]]"), "{written}");
+        let config = RulesConfig::from_toml(&written).unwrap();
+        assert!(!config.rules[0].enabled);
+        assert_eq!(config.rules[0].webhook.as_deref(), Some("https://hooks.example.com/in/T000/s3cret?key=abc"));
+        assert_eq!(config.rules[0].headers["X-Team"], "risk");
+        std::fs::remove_file(state.rules_path.as_ref().unwrap()).unwrap();
+    }
+}
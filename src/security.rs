@@ -0,0 +1,156 @@
+//! Approval-phishing and drainer detection — scores approvals and Permit2 signature
+//! transfers by how likely they are to hand a victim's tokens to an attacker.
+//!
+//! Nothing here calls RPC: whether a spender is an EOA, or new, comes from what the
+//! engine has seen on the feed itself (`AddressActivity`).
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::decode::{ApprovalKind, DecodedTx};
+
+/// Addresses remembered by `AddressActivity`. The oldest are forgotten first.
+pub const ACTIVITY_CAPACITY: usize = 200_000;
+
+/// Addresses seen on the feed, and whether each has sent a transaction (so is an EOA).
+#[derive(Default)]
+pub struct AddressActivity {
+    sent: HashMap<String, bool>,
+    order: VecDeque<String>,
+}
+
+impl AddressActivity {
+    /// Remember a transaction's sender and recipient.
+    pub fn record(&mut self, tx: &DecodedTx) {
+        if let Some(ref from) = tx.from {
+            self.note(from, true);
+        }
+        if let Some(ref to) = tx.to {
+            self.note(to, false);
+        }
+    }
+
+    fn note(&mut self, address: &str, sent: bool) {
+        let key = address.to_lowercase();
+        match self.sent.get_mut(&key) {
+            Some(s) => *s |= sent,
+            None => {
+                self.sent.insert(key.clone(), sent);
+                self.order.push_back(key);
+                while self.order.len() > ACTIVITY_CAPACITY {
+                    if let Some(old) = self.order.pop_front() {
+                        self.sent.remove(&old);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the address has sent a transaction we saw.
+    pub fn is_eoa(&self, address: &str) -> bool {
+        self.sent.get(&address.to_lowercase()).copied().unwrap_or(false)
+    }
+
+    /// Whether the address has appeared at all.
+    pub fn seen(&self, address: &str) -> bool {
+        self.sent.contains_key(&address.to_lowercase())
+    }
+}
+
+/// A scored approval or signature transfer.
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityFinding {
+    pub kind: ApprovalKind,
+    /// 0–100; see `assess` for what adds to it.
+    pub risk: u8,
+    /// Why the score is what it is, one entry per factor.
+    pub reasons: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub spender: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    pub unlimited: bool,
+}
+
+impl SecurityFinding {
+    /// One-line description, e.g. "risk 90: approve of 0xtoken to 0xspender (unlimited)".
+    pub fn summary(&self) -> String {
+        let kind = match self.kind {
+            ApprovalKind::Approve => "approve",
+            ApprovalKind::ApprovalForAll => "setApprovalForAll",
+            ApprovalKind::Permit2Permit => "Permit2 permit",
+            ApprovalKind::Permit2Transfer => "Permit2 transfer",
+        };
+        let token = self.token.as_deref().map(|t| format!(" of {}", t)).unwrap_or_default();
+        let unlimited = if self.unlimited && self.kind != ApprovalKind::ApprovalForAll { " (unlimited)" } else { "" };
+        format!("risk {}: {}{} to {}{}", self.risk, kind, token, self.spender, unlimited)
+    }
+}
+
+/// Score a transaction's approval. `labels` are the rules file's `[labels]`; a spender
+/// there or in the decoder's known addresses counts as known. Returns `None` for
+/// transactions without an approval.
+pub fn assess(tx: &DecodedTx, labels: &HashMap<String, String>, activity: &AddressActivity) -> Option<SecurityFinding> {
+    let approval = tx.approval.as_ref()?;
+    let spender = approval.spender.to_lowercase();
+    let known = crate::decode::known_addresses().contains_key(spender.as_str()) || labels.contains_key(&spender);
+    let submitter = tx.from.as_deref().map(str::to_lowercase);
+    let owner = approval.owner.as_deref().map(str::to_lowercase);
+
+    let mut risk = 0u32;
+    let mut reasons = Vec::new();
+    let mut add = |points: u32, reason: &str| {
+        risk += points;
+        reasons.push(reason.to_string());
+    };
+
+    match approval.kind {
+        ApprovalKind::Approve | ApprovalKind::Permit2Permit => {
+            if approval.unlimited {
+                add(40, "unlimited amount");
+            }
+            if !known {
+                add(30, "unlabelled spender");
+            }
+            if approval.kind == ApprovalKind::Permit2Permit && owner.is_some() && owner != submitter {
+                add(20, "permit submitted by someone other than the owner");
+            }
+        }
+        ApprovalKind::ApprovalForAll => {
+            add(20, "operator gets every token in the collection");
+            if !known {
+                add(40, "unknown operator");
+            }
+        }
+        ApprovalKind::Permit2Transfer => {
+            if owner.is_some() && owner != submitter {
+                add(40, "tokens moved by someone other than the owner");
+            }
+            if !known {
+                add(20, "unlabelled recipient");
+            }
+            if submitter.as_deref().is_some_and(|s| s != spender) {
+                add(10, "recipient differs from the submitter");
+            }
+        }
+    }
+    if !known {
+        if activity.is_eoa(&spender) {
+            add(20, "spender is an EOA");
+        } else if !activity.seen(&spender) {
+            add(10, "spender never seen before");
+        }
+    }
+
+    Some(SecurityFinding {
+        kind: approval.kind,
+        risk: risk.min(100) as u8,
+        reasons,
+        owner,
+        spender,
+        token: approval.token.clone(),
+        unlimited: approval.unlimited,
+    })
+}
@@ -4,7 +4,7 @@
 use std::collections::HashMap;
 use std::io::Read;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use axum::{
@@ -17,26 +17,81 @@ use axum::{
     routing::{get, post},
 };
 use tower_http::services::ServeDir;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::StreamExt;
 use tokio::sync::broadcast;
 use tokio_tungstenite::tungstenite::Message as TungMessage;
 use tracing::info;
 
 use crate::ask::{ask_handler, X402Config};
 use crate::rules::RuleEngine;
+use crate::rules_api::{
+    create_rule_handler, delete_rule_handler, patch_rule_handler, replace_rule_handler,
+    rule_handler, rule_schema_handler, rules_handler, snooze_address_handler, snooze_rule_handler,
+    unsnooze_address_handler, unsnooze_rule_handler,
+};
 use crate::store::{AlertQuery, AlertStore};
 
 pub struct AppState {
     tx: broadcast::Sender<String>,
-    pub store: Option<AlertStore>,
+    pub store: Option<Arc<AlertStore>>,
     health: tokio::sync::RwLock<HealthInfo>,
-    pub rules_config: Option<crate::rules::RulesConfig>,
+    /// Live rule engine — shared with the upstream reader and swapped in place on reload.
+    pub rules: Option<Arc<tokio::sync::Mutex<RuleEngine>>>,
+    /// Rules file the engine was loaded from — API edits are written back here.
+    pub rules_path: Option<PathBuf>,
+    /// Bearer token required for rule edits (`FLASHWATCH_ADMIN_TOKEN`). Unset = read-only API.
+    pub admin_token: Option<String>,
     rpc_url: String,
     moltbook_api_key: Option<String>,
     moltbook_submolt: String,
     feed_cache: tokio::sync::RwLock<Option<(std::time::Instant, serde_json::Value)>>,
     pub openclaw_gateway_token: Option<String>,
     pub x402: X402Config,
+    /// Webhook circuit breakers, shared by the dispatcher and `/api/deliveries`.
+    pub circuits: Arc<std::sync::Mutex<crate::delivery::Circuits>>,
+    /// Sends webhooks off the feed's hot path; `None` without rules.
+    pub dispatcher: Option<Arc<crate::delivery::Dispatcher>>,
+    /// Wakes the outbox worker when a delivery is queued.
+    pub outbox_wake: tokio::sync::Notify,
+    /// Publishes flashblocks and alerts to a message broker (`FLASHWATCH_EXPORT_URL`).
+    pub exporter: Option<Arc<crate::export::Exporter>>,
+}
+
+impl AppState {
+    /// Address labels from the currently loaded rules config.
+    pub async fn labels(&self) -> HashMap<String, String> {
+        match &self.rules {
+            Some(rules) => rules.lock().await.config.labels.clone(),
+            None => HashMap::new(),
+        }
+    }
+
+    /// State for handler tests: rules from `rules_path` if given, no upstream, store or sinks.
+    #[cfg(test)]
+    pub fn for_tests(rules_path: Option<PathBuf>, admin_token: Option<&str>) -> Self {
+        let rules = rules_path.as_ref().map(|path| {
+            let config = crate::rules::RulesConfig::from_file(path).expect("test rules file");
+            Arc::new(tokio::sync::Mutex::new(RuleEngine::new(config)))
+        });
+        Self {
+            tx: broadcast::channel(1).0,
+            store: None,
+            health: Default::default(),
+            rules,
+            rules_path,
+            admin_token: admin_token.map(String::from),
+            rpc_url: String::new(),
+            moltbook_api_key: None,
+            moltbook_submolt: String::new(),
+            feed_cache: Default::default(),
+            openclaw_gateway_token: None,
+            x402: X402Config::from_env(),
+            circuits: Default::default(),
+            dispatcher: None,
+            outbox_wake: tokio::sync::Notify::new(),
+            exporter: None,
+        }
+    }
 }
 
 #[derive(Default, Clone, serde::Serialize)]
@@ -65,11 +120,10 @@ pub async fn run(
 
     // Load rules engine if config provided
     let rules_engine = if let Some(rp) = rules_path {
-        let rules_str = std::fs::read_to_string(rp)?;
-        let engine = RuleEngine::from_toml(&rules_str)?;
+        let engine = RuleEngine::new(crate::rules::RulesConfig::from_file(Path::new(rp))?);
         let rule_count = engine.config.rules.iter().filter(|r| r.enabled).count();
         info!("Loaded {} active alert rules from {}", rule_count, rp);
-        Some(tokio::sync::Mutex::new(engine))
+        Some(Arc::new(tokio::sync::Mutex::new(engine)))
     } else {
         None
     };
@@ -79,14 +133,25 @@ pub async fn run(
         let path = db_path.unwrap_or("flashwatch.db");
         let store = AlertStore::open(&PathBuf::from(path))?;
         info!("Alert store at {}", path);
-        Some(store)
+        Some(Arc::new(store))
     };
 
-    let rules_config = if let Some(ref re) = rules_engine {
-        Some(re.lock().await.config.clone())
-    } else {
-        None
-    };
+    // Carry snoozes and the dedup cache over from the previous run.
+    if let (Some(re), Some(store)) = (&rules_engine, &store) {
+        let mut engine = re.lock().await;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        for (kind, target, until) in store.active_snoozes(now)? {
+            engine.snooze(kind, &target, until);
+        }
+        if engine.config.global.dedup {
+            let mut hashes = store.recent_tx_hashes(crate::rules::SEEN_CAPACITY)?;
+            hashes.reverse();
+            engine.mark_seen(hashes);
+        }
+    }
 
     let now_epoch = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -124,6 +189,28 @@ pub async fn run(
         x402.network, x402.price, x402.pay_to, x402.facilitator_url
     );
 
+    // Webhook dispatcher — built whenever rules are loaded, since a reload can add
+    // webhooks that weren't there at startup.
+    let circuits = Arc::new(std::sync::Mutex::new(crate::delivery::Circuits::default()));
+    let dispatcher = if rules_engine.is_some() {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .build()?;
+        Some(crate::delivery::Dispatcher::new(Arc::new(client), store.clone(), circuits.clone()))
+    } else {
+        None
+    };
+
+    // Message-bus export, buffered in the store while the broker is down
+    let exporter = match (crate::export::ExportConfig::from_env(), &store) {
+        (Some(config), Some(store)) => {
+            let exporter = crate::export::Exporter::spawn(config, store.clone())?;
+            info!("Exporting flashblocks and alerts to {}", exporter.broker());
+            Some(exporter)
+        }
+        _ => None,
+    };
+
     let state = Arc::new(AppState {
         tx: tx.clone(),
         store,
@@ -131,48 +218,45 @@ pub async fn run(
             started_epoch: now_epoch,
             ..Default::default()
         }),
-        rules_config,
+        rules: rules_engine.clone(),
+        rules_path: rules_path.map(PathBuf::from),
+        admin_token: std::env::var("FLASHWATCH_ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
         rpc_url: _rpc_url.to_string(),
         moltbook_api_key,
         moltbook_submolt,
         feed_cache: tokio::sync::RwLock::new(None),
         openclaw_gateway_token,
         x402,
+        circuits,
+        dispatcher: dispatcher.clone(),
+        outbox_wake: tokio::sync::Notify::new(),
+        exporter,
     });
 
-    // HTTP client for webhook firing
-    let has_webhooks = rules_engine.as_ref()
-        .map(|re| re.try_lock().ok()
-            .map(|e| e.config.rules.iter().any(|r| r.webhook.is_some()))
-            .unwrap_or(false))
-        .unwrap_or(false);
-    let webhook_client: Option<Arc<reqwest::Client>> = if has_webhooks {
-        Some(Arc::new(reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(5))
-            .build()?))
-    } else {
-        None
-    };
+    // Deliver queued webhooks, including any left pending by the previous run
+    if let Some(ref dispatcher) = dispatcher {
+        tokio::spawn(crate::delivery::run(state.clone(), dispatcher.clone()));
+    }
 
     // Spawn the upstream flashblocks reader (with optional rule engine)
     let ws_url = ws_url.to_string();
     let reader_state = state.clone();
-    let rules_engine = rules_engine.map(|e| Arc::new(e));
     let rules_ref = rules_engine.clone();
-    let webhook_client_ref = webhook_client.clone();
     tokio::spawn(async move {
         // Fixed 2s pause between reconnects — just enough to avoid hammering
         // the upstream if it's temporarily down, short enough to recover fast.
         // No exponential backoff: this is a critical live data feed that must
         // reconnect quickly. If upstream is truly gone, the error log tells us.
         const RECONNECT_PAUSE: std::time::Duration = std::time::Duration::from_secs(2);
+        // Open webhook batches survive reconnects.
+        let mut batcher = crate::alert::Batcher::default();
 
         loop {
             {
                 let mut h = reader_state.health.write().await;
                 h.connected = false;
             }
-            match upstream_reader_with_health(&ws_url, &reader_state, rules_ref.as_ref(), webhook_client_ref.as_deref()).await {
+            match upstream_reader_with_health(&ws_url, &reader_state, rules_ref.as_ref(), &mut batcher).await {
                 Ok(()) => break,
                 Err(e) => {
                     {
@@ -187,13 +271,24 @@ pub async fn run(
         }
     });
 
+    // Watch the rules file for edits (and SIGHUP) and hot-swap the config
+    if let (Some(rp), Some(re)) = (rules_path, &rules_engine) {
+        tokio::spawn(watch_rules(PathBuf::from(rp), re.clone()));
+    }
+
+    // Queue email digests as their periods end
+    if let Some(ref re) = rules_engine {
+        tokio::spawn(crate::email::run_digests(state.clone(), re.clone()));
+    }
+
     // Spawn retention pruner (hourly)
     if let Some(ref re) = rules_engine {
-        let retention_days = re.lock().await.config.global.retention_days;
+        let re = re.clone();
         let prune_state = state.clone();
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                let retention_days = re.lock().await.config.global.retention_days;
                 if let Some(ref store) = prune_state.store {
                     match store.prune(retention_days) {
                         Ok(n) if n > 0 => info!("Pruned {} alerts older than {}d", n, retention_days),
@@ -219,6 +314,19 @@ pub async fn run(
         .route("/alerts/recent", get(recent_alerts_handler))
         .route("/api/health", get(health_handler))
         .route("/api/rules", get(rules_handler))
+        .route("/api/rules/schema", get(rule_schema_handler))
+        .route(
+            "/api/rules/{name}",
+            get(rule_handler)
+                .post(create_rule_handler)
+                .put(replace_rule_handler)
+                .patch(patch_rule_handler)
+                .delete(delete_rule_handler),
+        )
+        .route("/api/rules/{name}/snooze", post(snooze_rule_handler).delete(unsnooze_rule_handler))
+        .route("/api/addresses/{address}/snooze", post(snooze_address_handler).delete(unsnooze_address_handler))
+        .route("/api/deliveries", get(crate::delivery::deliveries_handler))
+        .route("/api/deliveries/{id}/redeliver", post(crate::delivery::redeliver_handler))
         .route("/api/track/{tx_hash}", get(track_handler))
         .route("/api/info", get(info_handler))
         .route("/api/feed", get(feed_handler))
@@ -316,36 +424,11 @@ async fn health_handler(
         "last_block": h.last_block,
         "last_message_ago_secs": if h.last_message_epoch > 0 { now.saturating_sub(h.last_message_epoch) } else { 0 },
         "started_epoch": h.started_epoch,
+        "webhook_queues": state.dispatcher.as_ref().map(|d| d.stats()).unwrap_or_default(),
+        "export": state.exporter.as_ref().map(|e| e.stats()),
     }))
 }
 
-async fn rules_handler(
-    State(state): State<Arc<AppState>>,
-) -> Json<serde_json::Value> {
-    match &state.rules_config {
-        Some(config) => {
-            let rules: Vec<serde_json::Value> = config.rules.iter().map(|r| {
-                serde_json::json!({
-                    "name": r.name,
-                    "enabled": r.enabled,
-                    "trigger": format!("{:?}", r.trigger),
-                    "webhook": r.webhook.is_some(),
-                    "cooldown_secs": r.cooldown_secs.unwrap_or(config.global.cooldown_secs),
-                })
-            }).collect();
-            Json(serde_json::json!({
-                "rules": rules,
-                "global": {
-                    "cooldown_secs": config.global.cooldown_secs,
-                    "max_per_minute": config.global.max_per_minute,
-                    "retention_days": config.global.retention_days,
-                }
-            }))
-        }
-        None => Json(serde_json::json!({"rules": [], "global": null})),
-    }
-}
-
 async fn track_handler(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(tx_hash): axum::extract::Path<String>,
@@ -400,43 +483,40 @@ async fn info_handler(
 
     // Get latest block
     let req = serde_json::json!({"jsonrpc":"2.0","id":1,"method":"eth_getBlockByNumber","params":["latest",false]});
-    if let Ok(resp) = client.post(&state.rpc_url).json(&req).send().await {
-        if let Ok(body) = resp.json::<serde_json::Value>().await {
-            if let Some(result) = body.get("result") {
-                let block_num = result.get("number").and_then(|n| n.as_str())
-                    .and_then(|n| u64::from_str_radix(n.trim_start_matches("0x"), 16).ok());
-                let gas_used = result.get("gasUsed").and_then(|g| g.as_str())
-                    .and_then(|g| u64::from_str_radix(g.trim_start_matches("0x"), 16).ok());
-                let base_fee = result.get("baseFeePerGas").and_then(|b| b.as_str())
-                    .and_then(|b| u64::from_str_radix(b.trim_start_matches("0x"), 16).ok())
-                    .map(|w| w as f64 / 1e9);
-                let tx_count = result.get("transactions").and_then(|t| t.as_array()).map(|a| a.len());
-                let timestamp = result.get("timestamp").and_then(|t| t.as_str())
-                    .and_then(|t| u64::from_str_radix(t.trim_start_matches("0x"), 16).ok());
-
-                info = serde_json::json!({
-                    "chain": "Base Mainnet",
-                    "chain_id": 8453,
-                    "block_number": block_num,
-                    "gas_used": gas_used,
-                    "base_fee_gwei": base_fee,
-                    "tx_count": tx_count,
-                    "timestamp": timestamp,
-                });
-            }
-        }
+    if let Ok(resp) = client.post(&state.rpc_url).json(&req).send().await
+        && let Ok(body) = resp.json::<serde_json::Value>().await
+        && let Some(result) = body.get("result")
+    {
+        let block_num = result.get("number").and_then(|n| n.as_str())
+            .and_then(|n| u64::from_str_radix(n.trim_start_matches("0x"), 16).ok());
+        let gas_used = result.get("gasUsed").and_then(|g| g.as_str())
+            .and_then(|g| u64::from_str_radix(g.trim_start_matches("0x"), 16).ok());
+        let base_fee = result.get("baseFeePerGas").and_then(|b| b.as_str())
+            .and_then(|b| u64::from_str_radix(b.trim_start_matches("0x"), 16).ok())
+            .map(|w| w as f64 / 1e9);
+        let tx_count = result.get("transactions").and_then(|t| t.as_array()).map(|a| a.len());
+        let timestamp = result.get("timestamp").and_then(|t| t.as_str())
+            .and_then(|t| u64::from_str_radix(t.trim_start_matches("0x"), 16).ok());
+
+        info = serde_json::json!({
+            "chain": "Base Mainnet",
+            "chain_id": 8453,
+            "block_number": block_num,
+            "gas_used": gas_used,
+            "base_fee_gwei": base_fee,
+            "tx_count": tx_count,
+            "timestamp": timestamp,
+        });
     }
 
     // Get chain ID
     let req = serde_json::json!({"jsonrpc":"2.0","id":2,"method":"eth_chainId","params":[]});
-    if let Ok(resp) = client.post(&state.rpc_url).json(&req).send().await {
-        if let Ok(body) = resp.json::<serde_json::Value>().await {
-            if let Some(result) = body.get("result").and_then(|r| r.as_str()) {
-                if let Ok(id) = u64::from_str_radix(result.trim_start_matches("0x"), 16) {
-                    info["chain_id"] = serde_json::json!(id);
-                }
-            }
-        }
+    if let Ok(resp) = client.post(&state.rpc_url).json(&req).send().await
+        && let Ok(body) = resp.json::<serde_json::Value>().await
+        && let Some(result) = body.get("result").and_then(|r| r.as_str())
+        && let Ok(id) = u64::from_str_radix(result.trim_start_matches("0x"), 16)
+    {
+        info["chain_id"] = serde_json::json!(id);
     }
 
     Json(info)
@@ -450,10 +530,10 @@ async fn feed_handler(
     // Serve from cache if still fresh
     {
         let cache = state.feed_cache.read().await;
-        if let Some((cached_at, ref value)) = *cache {
-            if cached_at.elapsed().as_secs() < FEED_CACHE_TTL_SECS {
-                return Json(value.clone());
-            }
+        if let Some((cached_at, ref value)) = *cache
+            && cached_at.elapsed().as_secs() < FEED_CACHE_TTL_SECS
+        {
+            return Json(value.clone());
         }
     }
 
@@ -537,7 +617,7 @@ async fn upstream_reader_with_health(
     ws_url: &str,
     state: &Arc<AppState>,
     rules: Option<&Arc<tokio::sync::Mutex<RuleEngine>>>,
-    http_client: Option<&reqwest::Client>,
+    batcher: &mut crate::alert::Batcher,
 ) -> eyre::Result<()> {
     let (mut ws, _) = tokio_tungstenite::connect_async(ws_url).await?;
 
@@ -562,6 +642,7 @@ async fn upstream_reader_with_health(
 
     let mut current_block: Option<u64> = None;
     let mut prev_payload: Option<String> = None;
+    let mut block_state = crate::types::BlockState::default();
 
     // Stale connection detection: Base flashblocks arrive every ~200ms.
     // If we receive nothing for 30s, the upstream connection is dead.
@@ -601,6 +682,9 @@ async fn upstream_reader_with_health(
 
         // Decode transactions and enrich the message
         let enriched = enrich_flashblock(&text);
+        if let Some(ref exporter) = state.exporter {
+            exporter.flashblock(&enriched);
+        }
         let _ = state.tx.send(enriched);
 
         // Parse for health tracking + rules
@@ -610,10 +694,11 @@ async fn upstream_reader_with_health(
                 current_block = fb.block_number();
             }
 
-            let now_epoch = std::time::SystemTime::now()
+            let now_epoch_ms = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
-                .as_secs();
+                .as_millis() as u64;
+            let now_epoch = now_epoch_ms / 1000;
 
             // Update health
             {
@@ -628,28 +713,41 @@ async fn upstream_reader_with_health(
                 }
             }
 
-            // Run rule engine
+            // Run rule engine: block-scope and balance rules, then each transaction
+            block_state.update(&fb);
             if let Some(rules_arc) = rules {
-                let mut engine = rules_arc.lock().await;
-                for tx_val in &fb.diff.transactions {
-                    if let Some(tx_hex) = tx_val.as_str() {
-                        if let Some(decoded) = crate::decode::decode_raw_tx(tx_hex) {
-                            let alerts = engine.check(&decoded, block_number, fb.index);
-                            for alert in &alerts {
-                                // Store to SQLite
-                                if let Some(ref store) = state.store {
-                                    if let Err(e) = store.insert(alert) {
-                                        tracing::debug!("Failed to store alert: {}", e);
-                                    }
-                                }
-                                // Fire webhook
-                                if let Some(client) = http_client {
-                                    crate::alert::fire_webhook_pub(client, &engine.config, alert).await;
-                                }
-                            }
-                        }
+                let alerts = {
+                    let mut engine = rules_arc.lock().await;
+                    let mut alerts = engine.check_block_at(&block_state, fb.index, now_epoch_ms);
+                    alerts.extend(engine.check_balances_at(&fb, block_number, now_epoch_ms));
+                    let mut txs: Vec<_> = fb.diff.transactions.iter()
+                        .filter_map(|t| t.as_str().and_then(crate::decode::decode_raw_tx))
+                        .collect();
+                    crate::decode::annotate_median_tip(&mut txs);
+                    for decoded in &txs {
+                        alerts.extend(engine.check(decoded, block_number, fb.index));
+                    }
+                    alerts.extend(engine.check_mev_at(&fb, &txs, block_number, now_epoch_ms));
+                    alerts
+                };
+                // SQLite work runs with the engine unlocked, so API handlers aren't held up.
+                let alerts = store_alerts(state, alerts).await;
+                if let Some(ref exporter) = state.exporter {
+                    for alert in &alerts {
+                        exporter.alert(alert);
                     }
                 }
+                // Queue the webhooks, or hold them for their rule's batch window
+                let batches: Vec<_> = {
+                    let engine = rules_arc.lock().await;
+                    let mut batches: Vec<_> = alerts
+                        .into_iter()
+                        .filter_map(|alert| batcher.push(&engine.config, alert, now_epoch_ms))
+                        .collect();
+                    batches.extend(batcher.due(now_epoch_ms));
+                    batches.iter().map(|batch| crate::delivery::render(&engine.config, batch)).collect()
+                };
+                queue_batches(state, batches, now_epoch_ms).await;
             }
         }
     }
@@ -657,6 +755,144 @@ async fn upstream_reader_with_health(
     Ok(())
 }
 
+/// Store alerts in SQLite on the blocking pool and return the ones to send on. An
+/// already-stored finding is a replay and is dropped; an alert that fails to store
+/// still goes out.
+async fn store_alerts(state: &AppState, alerts: Vec<crate::rules::Alert>) -> Vec<crate::rules::Alert> {
+    let Some(store) = state.store.clone() else { return alerts };
+    if alerts.is_empty() {
+        return alerts;
+    }
+    let stored = tokio::task::spawn_blocking(move || {
+        alerts
+            .into_iter()
+            .filter(|alert| match store.insert(alert) {
+                Ok(fresh) => fresh,
+                Err(e) => {
+                    tracing::debug!("Failed to store alert: {}", e);
+                    true
+                }
+            })
+            .collect()
+    })
+    .await;
+    stored.unwrap_or_else(|e| {
+        tracing::warn!("Storing alerts failed: {}", e);
+        Vec::new()
+    })
+}
+
+/// Put rendered webhook batches in the outbox and wake the delivery worker. Without a
+/// store (or if the insert fails) each is handed to the dispatcher for a single attempt.
+/// Never waits on the network.
+async fn queue_batches(state: &AppState, batches: Vec<crate::delivery::Rendered>, now_ms: u64) {
+    if batches.is_empty() {
+        return;
+    }
+    let unqueued = match state.store.clone() {
+        Some(store) => {
+            let queued = tokio::task::spawn_blocking(move || {
+                batches
+                    .into_iter()
+                    .filter(|batch| match crate::delivery::enqueue(&store, batch, now_ms) {
+                        Ok(_) => false,
+                        Err(e) => {
+                            tracing::warn!("Failed to queue webhook for {}: {}", batch.destination.url, e);
+                            true
+                        }
+                    })
+                    .collect()
+            })
+            .await;
+            state.outbox_wake.notify_one();
+            queued.unwrap_or_else(|e| {
+                tracing::warn!("Queueing webhooks failed: {}", e);
+                Vec::new()
+            })
+        }
+        None => batches,
+    };
+    if let Some(ref dispatcher) = state.dispatcher {
+        for batch in unqueued {
+            dispatcher.dispatch(crate::delivery::Job::Direct { destination: batch.destination, payload: batch.payload });
+        }
+    }
+}
+
+/// Poll the rules file for changes and listen for SIGHUP; on either, re-parse and
+/// validate the file and swap it into the running engine. A bad edit is logged
+/// and rejected — the rules already loaded keep running.
+async fn watch_rules(path: PathBuf, engine: Arc<tokio::sync::Mutex<RuleEngine>>) {
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+    let mtime = |p: &PathBuf| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let mut last_mtime = mtime(&path);
+
+    #[cfg(unix)]
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            tracing::warn!("Could not install SIGHUP handler: {}", e);
+            None
+        }
+    };
+
+    loop {
+        #[cfg(unix)]
+        let forced = tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => false,
+            Some(()) = async { match hangup.as_mut() { Some(s) => s.recv().await, None => None } } => true,
+        };
+        #[cfg(not(unix))]
+        let forced = {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            false
+        };
+
+        let current = mtime(&path);
+        if !forced && current == last_mtime {
+            continue;
+        }
+        last_mtime = current;
+
+        if forced {
+            info!("SIGHUP — reloading rules from {}", path.display());
+        }
+        reload_rules(&path, &engine).await;
+    }
+}
+
+async fn reload_rules(path: &Path, engine: &tokio::sync::Mutex<RuleEngine>) {
+    let next = match crate::rules::RulesConfig::load(path) {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::warn!("Rejected rules reload from {}: {} — keeping current rules", path.display(), e);
+            return;
+        }
+    };
+
+    let mut engine = engine.lock().await;
+    let diff = engine.config.diff(&next);
+    if let Err(e) = next.validate() {
+        tracing::warn!("Rejected rules reload from {}: {} — keeping current rules", path.display(), e);
+        for line in &diff {
+            tracing::warn!("  (rejected) {}", line);
+        }
+        return;
+    }
+    if diff.is_empty() {
+        return;
+    }
+
+    crate::exec::retire_streams(&engine.config, &next);
+    engine.reload(next);
+    let active = engine.config.rules.iter().filter(|r| r.enabled).count();
+    info!("Reloaded rules from {} — {} active", path.display(), active);
+    for line in &diff {
+        info!("  {}", line);
+    }
+}
+
 /// Enrich a flashblock JSON with decoded transaction data.
 fn enrich_flashblock(json_str: &str) -> String {
     let mut fb: serde_json::Value = match serde_json::from_str(json_str) {
@@ -668,12 +904,14 @@ fn enrich_flashblock(json_str: &str) -> String {
 
     // Decode transactions
     let mut decoded_txs = Vec::new();
+    let mut fee_txs = Vec::new();
     if let Some(txs) = fb.pointer("/diff/transactions").and_then(|t| t.as_array()) {
         for tx_val in txs {
             if let Some(tx_hex) = tx_val.as_str() {
-                if let Some(mut dtx) = crate::decode::decode_raw_tx(tx_hex) {
+                if let Some(dtx) = crate::decode::decode_raw_tx(tx_hex) {
                     // Try to get tx hash from receipts in metadata
                     decoded_txs.push(serde_json::to_value(&dtx).unwrap_or_default());
+                    fee_txs.push(dtx);
                 } else {
                     decoded_txs.push(serde_json::json!({"raw": &tx_hex[..tx_hex.len().min(40)]}));
                 }
@@ -686,7 +924,7 @@ fn enrich_flashblock(json_str: &str) -> String {
     if let Some(balances) = fb.pointer("/metadata/new_account_balances").and_then(|b| b.as_object()) {
         for (addr, val) in balances {
             let addr_lower = addr.to_lowercase();
-            if let Some(label) = addresses.get(addr_lower.as_str()) {
+            if addresses.contains_key(addr_lower.as_str()) {
                 // Skip system addresses
                 continue;
             }
@@ -708,6 +946,9 @@ fn enrich_flashblock(json_str: &str) -> String {
 
     // Inject decoded data
     fb["_decoded_txs"] = serde_json::Value::Array(decoded_txs);
+    if let Some(fees) = crate::decode::fee_summary(&fee_txs) {
+        fb["_fees"] = serde_json::to_value(fees).unwrap_or_default();
+    }
     if !whale_alerts.is_empty() {
         fb["_whale_alerts"] = serde_json::Value::Array(whale_alerts);
     }
@@ -716,10 +957,10 @@ fn enrich_flashblock(json_str: &str) -> String {
 }
 
 fn decode_message(data: &[u8]) -> Option<String> {
-    if let Ok(text) = std::str::from_utf8(data) {
-        if text.trim_start().starts_with('{') {
-            return Some(text.to_owned());
-        }
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.trim_start().starts_with('{')
+    {
+        return Some(text.to_owned());
     }
     let mut decompressor = brotli::Decompressor::new(data, 4096);
     let mut decompressed = Vec::new();
@@ -0,0 +1,117 @@
+//! Webhook signatures — every POST to a rule with a signing secret carries
+//!
+//!   X-Flashwatch-Signature: t=<unix seconds>,v1=<hex HMAC-SHA256>
+//!
+//! where the HMAC is keyed with the secret and taken over `"<t>.<body>"`, the
+//! body being the exact bytes sent. The timestamp is set on each attempt, so a
+//! retried delivery is signed afresh. Receivers recompute the HMAC, compare it in
+//! constant time and reject timestamps outside their replay window
+//! (`DEFAULT_TOLERANCE_SECS`). `flashwatch verify-signature` does all three.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub const SIGNATURE_HEADER: &str = "X-Flashwatch-Signature";
+/// Replay window suggested to receivers, and the default for `verify-signature`.
+pub const DEFAULT_TOLERANCE_SECS: u64 = 300;
+
+fn mac(secret: &str, timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// The `X-Flashwatch-Signature` value for a body sent at `timestamp`.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    format!("t={},v1={}", timestamp, hex::encode(mac(secret, timestamp, body).finalize().into_bytes()))
+}
+
+/// Whether a bearer token matches the expected one, compared in constant time.
+/// Both sides go through the HMAC first, so their lengths don't leak either.
+pub fn token_matches(expected: &str, supplied: &str) -> bool {
+    let digest = mac(expected, 0, supplied.as_bytes()).finalize().into_bytes();
+    mac(expected, 0, expected.as_bytes()).verify_slice(&digest).is_ok()
+}
+
+/// Check a signature header against a body. Fails if the signature doesn't match or
+/// the timestamp is more than `tolerance_secs` from `now`. Returns the timestamp.
+pub fn verify(secret: &str, header: &str, body: &[u8], now: u64, tolerance_secs: u64) -> Result<u64, String> {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", t)) => timestamp = t.parse::<u64>().ok(),
+            Some(("v1", sig)) => signature = Some(sig),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or("signature header has no valid t=<timestamp>")?;
+    let signature = signature.ok_or("signature header has no v1=<signature>")?;
+    let valid = hex::decode(signature).is_ok_and(|sig| mac(secret, timestamp, body).verify_slice(&sig).is_ok());
+    if !valid {
+        return Err("signature does not match the body".into());
+    }
+    if now.abs_diff(timestamp) > tolerance_secs {
+        return Err(format!("timestamp {} is {}s from now, outside the {}s window", timestamp, now.abs_diff(timestamp), tolerance_secs));
+    }
+    Ok(timestamp)
+}
+
+/// `flashwatch verify-signature`: check a signature against a body read from a
+/// file, or stdin. Exits non-zero if it doesn't verify.
+pub fn run_verify(secret: &str, header: &str, body_path: Option<&str>, tolerance_secs: u64) -> eyre::Result<()> {
+    let body = match body_path {
+        Some(path) => std::fs::read(path)?,
+        None => {
+            let mut body = Vec::new();
+            std::io::Read::read_to_end(&mut std::io::stdin(), &mut body)?;
+            body
+        }
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    match verify(secret, header, &body, now, tolerance_secs) {
+        Ok(timestamp) => {
+            println!("valid — signed at {} ({}s ago)", timestamp, now.saturating_sub(timestamp));
+            Ok(())
+        }
+        Err(e) => eyre::bail!("invalid signature: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let body = br#"{"rule_name":"whale"}"#;
+        let header = sign("s3cret", 1_700_000_000, body);
+        assert!(header.starts_with("t=1700000000,v1="));
+        assert_eq!(verify("s3cret", &header, body, 1_700_000_100, 300), Ok(1_700_000_000));
+
+        assert!(verify("other", &header, body, 1_700_000_000, 300).is_err());
+        assert!(verify("s3cret", &header, br#"{"rule_name":"whale2"}"#, 1_700_000_000, 300).is_err());
+        assert!(verify("s3cret", &header, body, 1_700_000_301, 300).unwrap_err().contains("outside"));
+        assert!(verify("s3cret", "v1=00", body, 1_700_000_000, 300).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_posted_webhooks_verify_at_the_receiver() {
+        let (url, seen) = crate::sink::test_receiver("/hook", vec![]).await;
+        let payload = serde_json::json!({"rule_name": "whale", "tx": {"value_eth": 250.0}});
+        let outcome = crate::delivery::post(&reqwest::Client::new(), &url, &[], Some("s3cret"), &payload).await;
+        assert!(matches!(outcome, crate::delivery::Outcome::Delivered));
+        let received = seen.lock().unwrap().pop().unwrap();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let signature = received.header(SIGNATURE_HEADER).unwrap();
+        assert!(verify("s3cret", signature, &received.body, now, DEFAULT_TOLERANCE_SECS).is_ok());
+
+        crate::delivery::post(&reqwest::Client::new(), &url, &[], None, &payload).await;
+        assert_eq!(seen.lock().unwrap().pop().unwrap().header(SIGNATURE_HEADER), None);
+    }
+}
@@ -0,0 +1,643 @@
+//! Alert sinks — how a rule's alerts are rendered and delivered to its webhook.
+//!
+//! A rule picks its sink with `sink = "..."` (default `openclaw`) and can add
+//! `headers` and `auth` for its destination. Sinks render a body when alerts are
+//! queued; the outbox stores it, and `deliver` sends it when due.
+//!
+//! Credentials are never inline: `auth`, `signing_secret` and any header or webhook
+//! URL that holds a secret are `env:NAME` or `file:/path` references (`SecretRef`).
+//! The outbox stores the references, and they are read on every attempt.
+//!
+//!   openclaw — the OpenClaw /hooks/agent body: an agent prompt describing the alerts
+//!   json     — the raw `Alert` as JSON (an array of them for a batch)
+//!   slack    — a Slack incoming-webhook message in Block Kit
+//!   discord  — a Discord webhook message with one embed per alert
+//!   telegram — a Bot API sendMessage call; `chat_id` goes in the webhook URL's query
+//!   email    — a mail over SMTP; the webhook is an `smtp://` URL (see `email`)
+//!   exec     — JSON lines on a local command's stdin; the webhook is `exec:/path` (see `exec`)
+//!
+//! The chat formats carry the same lines as the agent prompt — labels, basescan
+//! links, risk factors — without its instructions.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::alert::{alert_detail_lines, alert_headline, alert_note_lines};
+use crate::decode::Category;
+use crate::delivery::Outcome;
+use crate::exec::ProcessOutput;
+use crate::rules::{Alert, RulesConfig, Severity};
+
+/// Alerts shown in one chat message; the rest of a batch is counted in a closing line.
+const CHAT_MAX_ALERTS: usize = 10;
+/// Telegram's limit is 4096 characters; leave room for the closing line.
+const TELEGRAM_MAX_CHARS: usize = 3900;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    #[default]
+    Openclaw,
+    Json,
+    Slack,
+    Discord,
+    Telegram,
+    Email,
+    Exec,
+}
+
+impl SinkKind {
+    pub const ALL: [SinkKind; 7] = [Self::Openclaw, Self::Json, Self::Slack, Self::Discord, Self::Telegram, Self::Email, Self::Exec];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Openclaw => "openclaw",
+            Self::Json => "json",
+            Self::Slack => "slack",
+            Self::Discord => "discord",
+            Self::Telegram => "telegram",
+            Self::Email => "email",
+            Self::Exec => "exec",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == s)
+    }
+
+    pub fn sink(self) -> &'static dyn AlertSink {
+        match self {
+            Self::Openclaw => &OpenClawSink,
+            Self::Json => &JsonSink,
+            Self::Slack => &SlackSink,
+            Self::Discord => &DiscordSink,
+            Self::Telegram => &TelegramSink,
+            Self::Email => &crate::email::EmailSink,
+            Self::Exec => &crate::exec::ExecSink,
+        }
+    }
+}
+
+/// A credential in rules.toml: `env:NAME` (an environment variable) or
+/// `file:/path` (a file's contents, minus the trailing newline).
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum SecretRef {
+    Env(String),
+    File(String),
+}
+
+impl SecretRef {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.split_once(':') {
+            Some(("env", name)) if !name.is_empty() => Ok(Self::Env(name.to_string())),
+            Some(("file", path)) if !path.is_empty() => Ok(Self::File(path.to_string())),
+            _ => Err("credentials must be referenced as env:NAME or file:/path, not written inline".to_string()),
+        }
+    }
+
+    /// Read the secret. Errors name the reference, never a value.
+    pub fn resolve(&self) -> Result<String, String> {
+        match self {
+            Self::Env(name) => std::env::var(name).map_err(|_| format!("env:{} is not set", name)),
+            Self::File(path) => std::fs::read_to_string(path)
+                .map(|s| s.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|e| format!("cannot read file:{}: {}", path, e)),
+        }
+    }
+
+    /// Whether a header value or webhook URL is a reference rather than a literal.
+    pub fn is_reference(s: &str) -> bool {
+        s.starts_with("env:") || s.starts_with("file:")
+    }
+
+    /// A header value or webhook URL: resolved if it is a reference, as-is otherwise.
+    pub fn resolve_value(s: &str) -> Result<String, String> {
+        if Self::is_reference(s) { Self::parse(s)?.resolve() } else { Ok(s.to_string()) }
+    }
+}
+
+impl TryFrom<String> for SecretRef {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        Self::parse(&s)
+    }
+}
+
+impl From<SecretRef> for String {
+    fn from(secret: SecretRef) -> String {
+        match secret {
+            SecretRef::Env(name) => format!("env:{}", name),
+            SecretRef::File(path) => format!("file:{}", path),
+        }
+    }
+}
+
+/// Credentials sent with every request to a rule's webhook.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookAuth {
+    /// `Authorization: Bearer <token>`
+    Bearer(SecretRef),
+    /// `Authorization: Basic <base64(username:password)>`
+    Basic { username: String, password: SecretRef },
+}
+
+impl WebhookAuth {
+    pub fn header_value(&self) -> Result<String, String> {
+        Ok(match self {
+            Self::Bearer(token) => format!("Bearer {}", token.resolve()?),
+            Self::Basic { username, password } => {
+                let encoded = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password.resolve()?));
+                format!("Basic {}", encoded)
+            }
+        })
+    }
+}
+
+/// Where a rule's alerts go: one webhook with its sink, headers and credentials,
+/// all as configured — references unresolved. Alerts are batched per destination.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Destination {
+    /// A URL, or a `SecretRef` to one (Slack, Discord and Telegram URLs hold a token).
+    pub url: String,
+    pub sink: SinkKind,
+    /// Literal values, or `SecretRef`s.
+    pub headers: BTreeMap<String, String>,
+    pub auth: Option<WebhookAuth>,
+    /// Key for `X-Flashwatch-Signature` (see `signing`); unsigned if `None`.
+    pub signing_secret: Option<SecretRef>,
+    /// The rule's message template. Only used when rendering, so never stored in the outbox.
+    pub template: Option<String>,
+}
+
+/// A destination with its credentials read, ready to send to.
+pub struct Target {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub signing_secret: Option<String>,
+}
+
+impl Destination {
+    /// Read the destination's references. `defaults` gives the sink's own headers
+    /// for the URL, used when nothing sets `Authorization`.
+    pub fn resolve(&self, defaults: &dyn Fn(&str) -> Vec<(String, String)>) -> Result<Target, String> {
+        let url = SecretRef::resolve_value(&self.url)?;
+        let mut headers = Vec::new();
+        if self.auth.is_none() && !self.headers.keys().any(|k| k.eq_ignore_ascii_case("authorization")) {
+            headers.extend(defaults(&url));
+        }
+        for (name, value) in &self.headers {
+            if self.auth.is_some() && name.eq_ignore_ascii_case("authorization") {
+                continue;
+            }
+            headers.push((name.clone(), SecretRef::resolve_value(value)?));
+        }
+        if let Some(ref auth) = self.auth {
+            headers.push(("Authorization".to_string(), auth.header_value()?));
+        }
+        let signing_secret = self.signing_secret.as_ref().map(SecretRef::resolve).transpose()?;
+        Ok(Target { url, headers, signing_secret })
+    }
+}
+
+/// Whether a URL points at this machine.
+pub fn is_loopback(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|u| match u.host_str() {
+        Some("localhost") => true,
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    })
+}
+
+pub trait AlertSink: Send + Sync {
+    /// Body for the alerts bound for one destination — a single alert unless batched.
+    fn render(&self, alerts: &[Alert], config: &RulesConfig) -> serde_json::Value;
+
+    /// Headers sent to `url` when the destination sets no `Authorization` of its own.
+    fn default_headers(&self, _url: &str) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Send a rendered body. The default POSTs it as JSON with the destination's
+    /// headers, signed if it has a secret. A reference that can't be read is retried.
+    fn deliver<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        destination: &'a Destination,
+        body: &'a serde_json::Value,
+    ) -> BoxFuture<'a, Outcome> {
+        Box::pin(async move {
+            let target = match destination.resolve(&|url| self.default_headers(url)) {
+                Ok(target) => target,
+                Err(e) => return Outcome::Retry(e),
+            };
+            crate::delivery::post(client, &target.url, &target.headers, target.signing_secret.as_deref(), body).await
+        })
+    }
+
+    /// `deliver`, plus the exit status and stderr for sinks that run a process.
+    /// The outbox stores them with the delivery.
+    fn deliver_with_output<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        destination: &'a Destination,
+        body: &'a serde_json::Value,
+    ) -> BoxFuture<'a, (Outcome, Option<ProcessOutput>)> {
+        Box::pin(async move { (self.deliver(client, destination, body).await, None) })
+    }
+}
+
+/// The OpenClaw /hooks/agent body. Without `auth`, `OPENCLAW_HOOKS_TOKEN` is sent
+/// as a bearer token, but only to a gateway on this machine — a rule pointing
+/// elsewhere needs `auth = { bearer = "env:OPENCLAW_HOOKS_TOKEN" }`.
+pub struct OpenClawSink;
+
+impl AlertSink for OpenClawSink {
+    fn render(&self, alerts: &[Alert], config: &RulesConfig) -> serde_json::Value {
+        crate::alert::agent_payload(alerts, config)
+    }
+
+    fn default_headers(&self, url: &str) -> Vec<(String, String)> {
+        match std::env::var("OPENCLAW_HOOKS_TOKEN") {
+            Ok(token) if is_loopback(url) => vec![("Authorization".to_string(), format!("Bearer {}", token))],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The `Alert` struct as JSON, for services of your own.
+pub struct JsonSink;
+
+impl AlertSink for JsonSink {
+    fn render(&self, alerts: &[Alert], _config: &RulesConfig) -> serde_json::Value {
+        match alerts {
+            [alert] => serde_json::to_value(alert).unwrap_or_default(),
+            alerts => serde_json::to_value(alerts).unwrap_or_default(),
+        }
+    }
+}
+
+/// Headline of a chat message: the alert title for one alert, a count for a batch.
+pub(crate) fn chat_title(alerts: &[Alert]) -> String {
+    match alerts {
+        [alert] => {
+            let (title, _) = alert_headline(alert);
+            let emoji = alert.tx.as_ref()
+                .and_then(|tx| Category::ALL.iter().find(|c| c.name() == tx.category))
+                .map(|c| format!("{} ", c.emoji()))
+                .unwrap_or_default();
+            match alert.severity {
+                Severity::Critical => format!("{}{} — CRITICAL", emoji, title),
+                _ => format!("{}{}", emoji, title),
+            }
+        }
+        alerts => format!("FlashWatch — {} alerts on Base Mainnet", alerts.len()),
+    }
+}
+
+/// Summary line, notes and details (labelled addresses, basescan links) for one alert.
+pub(crate) fn chat_lines(alert: &Alert, labels: &std::collections::HashMap<String, String>) -> (String, Vec<String>) {
+    let (_, summary) = alert_headline(alert);
+    let mut lines = alert_note_lines(alert);
+    lines.extend(alert_detail_lines(alert, labels));
+    (summary, lines)
+}
+
+fn more_line(alerts: &[Alert], shown: usize) -> Option<String> {
+    (alerts.len() > shown).then(|| format!("…and {} more", alerts.len() - shown))
+}
+
+/// Cut `s` to at most `max` characters, marking the cut.
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Slack incoming webhook: a header block, then a section per alert.
+pub struct SlackSink;
+
+impl SlackSink {
+    /// Slack mrkdwn treats `&`, `<` and `>` as control characters.
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+}
+
+impl AlertSink for SlackSink {
+    fn render(&self, alerts: &[Alert], config: &RulesConfig) -> serde_json::Value {
+        let title = chat_title(alerts);
+        let mut blocks = vec![serde_json::json!({
+            "type": "header",
+            "text": {"type": "plain_text", "text": truncate(&title, 150), "emoji": true},
+        })];
+        for alert in alerts.iter().take(CHAT_MAX_ALERTS) {
+            let (summary, lines) = chat_lines(alert, &config.labels);
+            let mut text = format!("*{}*", Self::escape(&summary));
+            for line in lines {
+                text.push('\n');
+                text.push_str(&Self::escape(&line));
+            }
+            blocks.push(serde_json::json!({
+                "type": "section",
+                "text": {"type": "mrkdwn", "text": truncate(&text, 3000)},
+            }));
+        }
+        if let Some(more) = more_line(alerts, CHAT_MAX_ALERTS) {
+            blocks.push(serde_json::json!({
+                "type": "context",
+                "elements": [{"type": "mrkdwn", "text": more}],
+            }));
+        }
+        serde_json::json!({"text": title, "blocks": blocks})
+    }
+}
+
+/// Discord webhook: one embed per alert, coloured by its transaction category.
+pub struct DiscordSink;
+
+impl DiscordSink {
+    /// `Category::color` as the integer Discord expects.
+    fn color(alert: &Alert) -> u32 {
+        let category = alert.tx.as_ref()
+            .and_then(|tx| Category::ALL.into_iter().find(|c| c.name() == tx.category))
+            .unwrap_or(Category::Unknown);
+        u32::from_str_radix(category.color().trim_start_matches('#'), 16).unwrap_or(0)
+    }
+}
+
+impl AlertSink for DiscordSink {
+    fn render(&self, alerts: &[Alert], config: &RulesConfig) -> serde_json::Value {
+        let shown = alerts.len().min(CHAT_MAX_ALERTS);
+        // Discord caps a message's embeds at 6000 characters in total.
+        let budget = (5000 / shown.max(1)).min(4096);
+        let embeds: Vec<serde_json::Value> = alerts.iter().take(shown).map(|alert| {
+            let (summary, lines) = chat_lines(alert, &config.labels);
+            let title = match alert.severity {
+                Severity::Critical => format!("CRITICAL · {}", summary),
+                _ => summary,
+            };
+            let mut embed = serde_json::json!({
+                "title": truncate(&title, 256),
+                "description": truncate(&lines.join("\n"), budget),
+                "color": Self::color(alert),
+                "footer": {"text": format!("FlashWatch · {}", alert.rule_name)},
+            });
+            if let Some(hash) = alert.tx.as_ref().and_then(|tx| tx.hash.as_ref()) {
+                embed["url"] = format!("https://basescan.org/tx/{}", hash).into();
+            }
+            if let Some(ts) = chrono::DateTime::from_timestamp(alert.timestamp as i64, 0) {
+                embed["timestamp"] = ts.to_rfc3339().into();
+            }
+            embed
+        }).collect();
+        let mut content = chat_title(alerts);
+        if let Some(more) = more_line(alerts, shown) {
+            content = format!("{} ({})", content, more);
+        }
+        serde_json::json!({"username": "FlashWatch", "content": content, "embeds": embeds})
+    }
+}
+
+/// Telegram Bot API `sendMessage`, as HTML. The webhook is
+/// `https://api.telegram.org/bot<token>/sendMessage?chat_id=<chat>`; query
+/// parameters are moved into the JSON body when sending.
+pub struct TelegramSink;
+
+impl TelegramSink {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+}
+
+impl AlertSink for TelegramSink {
+    fn render(&self, alerts: &[Alert], config: &RulesConfig) -> serde_json::Value {
+        let mut text = format!("<b>{}</b>", Self::escape(&chat_title(alerts)));
+        let mut shown = 0;
+        for alert in alerts.iter().take(CHAT_MAX_ALERTS) {
+            let (summary, lines) = chat_lines(alert, &config.labels);
+            let mut part = format!("\n\n<b>{}</b>", Self::escape(&summary));
+            for line in lines {
+                part.push('\n');
+                part.push_str(&Self::escape(&line));
+            }
+            if shown > 0 && text.chars().count() + part.chars().count() > TELEGRAM_MAX_CHARS {
+                break;
+            }
+            text.push_str(&part);
+            shown += 1;
+        }
+        if let Some(more) = more_line(alerts, shown) {
+            text.push_str(&format!("\n\n{}", more));
+        }
+        serde_json::json!({
+            "text": truncate(&text, 4096),
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true,
+        })
+    }
+
+    fn deliver<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        destination: &'a Destination,
+        body: &'a serde_json::Value,
+    ) -> BoxFuture<'a, Outcome> {
+        Box::pin(async move {
+            let target = match destination.resolve(&|_| Vec::new()) {
+                Ok(target) => target,
+                Err(e) => return Outcome::Retry(e),
+            };
+            let Ok(mut endpoint) = reqwest::Url::parse(&target.url) else {
+                return Outcome::Rejected("invalid Telegram URL".to_string());
+            };
+            let mut body = body.clone();
+            if let Some(fields) = body.as_object_mut() {
+                for (name, value) in endpoint.query_pairs() {
+                    fields.insert(name.into_owned(), value.into_owned().into());
+                }
+            }
+            endpoint.set_query(None);
+            crate::delivery::post(client, endpoint.as_str(), &target.headers, target.signing_secret.as_deref(), &body).await
+        })
+    }
+}
+
+/// A request seen by `test_receiver`.
+#[cfg(test)]
+pub(crate) struct Received {
+    pub uri: axum::http::Uri,
+    pub headers: axum::http::HeaderMap,
+    pub body: axum::body::Bytes,
+}
+
+#[cfg(test)]
+impl Received {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+/// Serve `app` on an ephemeral loopback port for a test.
+#[cfg(test)]
+pub(crate) async fn serve_local(app: axum::Router) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+/// Local stand-in receiver for tests. POSTs to `path` are recorded and answered with
+/// each status in `statuses` in turn, then 200; a status of 0 never answers. Returns
+/// the URL of `path` and the requests seen so far.
+#[cfg(test)]
+pub(crate) async fn test_receiver(path: &str, statuses: Vec<u16>) -> (String, std::sync::Arc<std::sync::Mutex<Vec<Received>>>) {
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let statuses = std::sync::Arc::new(std::sync::Mutex::new(statuses.into_iter()));
+    let captured = seen.clone();
+    let app = axum::Router::new().route(path, axum::routing::post(
+        move |uri: axum::http::Uri, headers: axum::http::HeaderMap, body: axum::body::Bytes| {
+            captured.lock().unwrap().push(Received { uri, headers, body });
+            let status = statuses.lock().unwrap().next().unwrap_or(200);
+            async move {
+                if status == 0 {
+                    std::future::pending::<()>().await;
+                }
+                axum::http::StatusCode::from_u16(status).unwrap()
+            }
+        },
+    ));
+    let addr = serve_local(app).await;
+    (format!("http://{}{}", addr, path), seen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::AlertTx;
+
+    fn alert(rule: &str, value_eth: f64) -> Alert {
+        Alert {
+            rule_name: rule.into(),
+            matched_rules: vec![rule.into()],
+            severity: Severity::Warn,
+            block_number: Some(1),
+            flashblock_index: 0,
+            tx: Some(AlertTx {
+                hash: None,
+                from: None,
+                to: None,
+                to_label: None,
+                value_eth,
+                action: None,
+                category: "unknown".into(),
+                contract_creation: None,
+                nonce: 0,
+                gas_limit: 21_000,
+                max_fee_gwei: 0.0,
+                priority_fee_gwei: 0.0,
+            }),
+            block: None,
+            balance: None,
+            mev: None,
+            security: None,
+            timestamp: 0,
+            suppressed: None,
+            digest: false,
+        }
+    }
+
+    #[test]
+    fn test_secret_refs_and_openclaw_token_scope() {
+        assert!(SecretRef::parse("hunter2").is_err());
+        assert!(SecretRef::parse("env:").is_err());
+        assert_eq!(String::from(SecretRef::parse("file:/run/token").unwrap()), "file:/run/token");
+
+        let missing = SecretRef::Env("FLASHWATCH_TEST_UNSET_SECRET".into()).resolve().unwrap_err();
+        assert_eq!(missing, "env:FLASHWATCH_TEST_UNSET_SECRET is not set");
+        assert_eq!(SecretRef::resolve_value("https://hook").unwrap(), "https://hook");
+
+        let destination = Destination {
+            url: "https://example.com/hook".into(),
+            sink: SinkKind::Openclaw,
+            headers: BTreeMap::from([("Authorization".into(), "Bearer literal".into())]),
+            auth: Some(WebhookAuth::Basic { username: "fw".into(), password: SecretRef::Env("PATH".into()) }),
+            signing_secret: None,
+            template: None,
+        };
+        let target = destination.resolve(&|_| vec![("Authorization".into(), "default".into())]).unwrap();
+        let auth: Vec<_> = target.headers.iter().filter(|(k, _)| k.eq_ignore_ascii_case("authorization")).collect();
+        assert_eq!(auth.len(), 1);
+        assert!(auth[0].1.starts_with("Basic "));
+
+        assert!(is_loopback("http://127.0.0.1:18789/hooks/agent"));
+        assert!(is_loopback("http://localhost/hooks"));
+        assert!(is_loopback("http://[::1]:8080/"));
+        assert!(!is_loopback("https://hooks.example.com/agent"));
+        assert!(!is_loopback("http://127.0.0.1.example.com/"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_sinks_carry_labels_links_and_colors() {
+        let config: RulesConfig = toml::from_str(r#"
+            [labels]
+            "0x00000000000000000000000000000000000000aa" = "Treasury <ops>"
+        "#).unwrap();
+        let mut whale = alert("whale", 250.0);
+        whale.severity = Severity::Critical;
+        if let Some(ref mut tx) = whale.tx {
+            tx.hash = Some("0xfeed".into());
+            tx.from = Some("0x00000000000000000000000000000000000000aa".into());
+            tx.category = "dex".into();
+        }
+
+        let slack = SinkKind::Slack.sink().render(std::slice::from_ref(&whale), &config);
+        let section = slack["blocks"][1]["text"]["text"].as_str().unwrap();
+        assert!(slack["blocks"][0]["text"]["text"].as_str().unwrap().ends_with("CRITICAL"));
+        assert!(section.contains("*250.00 ETH | Rule: whale"));
+        assert!(section.contains("(Treasury &lt;ops&gt;)"));
+        assert!(section.contains("https://basescan.org/tx/0xfeed"));
+
+        let discord = SinkKind::Discord.sink().render(std::slice::from_ref(&whale), &config);
+        let embed = &discord["embeds"][0];
+        assert_eq!(embed["color"], 0x22d3ee);
+        assert_eq!(embed["url"], "https://basescan.org/tx/0xfeed");
+        assert!(embed["description"].as_str().unwrap().contains("(Treasury <ops>)"));
+        let many: Vec<Alert> = (0..12).map(|i| alert("whale", i as f64)).collect();
+        let discord = SinkKind::Discord.sink().render(&many, &config);
+        assert_eq!(discord["embeds"].as_array().unwrap().len(), 10);
+        assert!(discord["content"].as_str().unwrap().ends_with("(…and 2 more)"));
+
+        // Telegram moves the webhook's chat_id into the body, against a local stand-in.
+        let (url, seen) = test_receiver("/bot123:abc/sendMessage", vec![]).await;
+        let url = format!("{url}?chat_id=-1001");
+
+        let telegram = SinkKind::Telegram.sink();
+        let body = telegram.render(std::slice::from_ref(&whale), &config);
+        let destination = Destination { url, sink: SinkKind::Telegram, headers: Default::default(), auth: None, signing_secret: None, template: None };
+        let outcome = telegram.deliver(&reqwest::Client::new(), &destination, &body).await;
+        assert!(matches!(outcome, Outcome::Delivered));
+        let request = seen.lock().unwrap().pop().unwrap();
+        assert_eq!(request.uri.query(), None);
+        let received = request.json();
+        assert_eq!(received["chat_id"], "-1001");
+        assert_eq!(received["parse_mode"], "HTML");
+        let text = received["text"].as_str().unwrap();
+        assert!(text.starts_with("<b>🔄 FlashWatch Alert — Base Mainnet — CRITICAL</b>"));
+        assert!(text.contains("(Treasury &lt;ops&gt;)"));
+    }
+}
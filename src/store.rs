@@ -1,11 +1,15 @@
-//! SQLite alert storage — write matches, query history.
+//! SQLite alert storage — write matches, query history, and the webhook outbox.
 
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Mutex;
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
 
-use crate::rules::Alert;
+use crate::exec::ProcessOutput;
+use crate::rules::{Alert, SnoozeKind};
+use crate::sink::{Destination, SecretRef, SinkKind, WebhookAuth};
 
 pub struct AlertStore {
     conn: Mutex<Connection>,
@@ -31,6 +35,14 @@ impl AlertStore {
                 action      TEXT,
                 category    TEXT NOT NULL,
                 payload     TEXT NOT NULL,
+                suppressed_count INTEGER NOT NULL DEFAULT 0,
+                suppressed_eth   REAL NOT NULL DEFAULT 0,
+                tx_hash     TEXT,
+                block_kind  TEXT,
+                victim      TEXT,
+                attacker    TEXT,
+                profit_eth  REAL,
+                risk        INTEGER,
                 created_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
             );
 
@@ -49,31 +61,142 @@ impl AlertStore {
                 created_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
             );
             CREATE INDEX IF NOT EXISTS idx_queries_created ON queries(created_at);
+
+            CREATE TABLE IF NOT EXISTS snoozes (
+                kind        TEXT NOT NULL,
+                target      TEXT NOT NULL,
+                until_ts    INTEGER NOT NULL,
+                PRIMARY KEY (kind, target)
+            );
+
+            CREATE TABLE IF NOT EXISTS deliveries (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                url         TEXT NOT NULL,
+                sink        TEXT NOT NULL DEFAULT 'openclaw',
+                headers     TEXT NOT NULL DEFAULT '{}',
+                signing_secret TEXT,
+                auth        TEXT,
+                rules       TEXT NOT NULL,
+                payload     TEXT NOT NULL,
+                status      TEXT NOT NULL DEFAULT 'pending',
+                attempts    INTEGER NOT NULL DEFAULT 0,
+                next_attempt_ms INTEGER NOT NULL,
+                last_error  TEXT,
+                exit_status INTEGER,
+                stderr      TEXT,
+                created_ms  INTEGER NOT NULL,
+                updated_ms  INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_deliveries_due ON deliveries(status, next_attempt_ms);
+
+            CREATE TABLE IF NOT EXISTS exports (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                topic       TEXT NOT NULL,
+                payload     TEXT NOT NULL,
+                created_ms  INTEGER NOT NULL
+            );
+        ")?;
+
+        // Databases from older versions lack these columns.
+        for (table, column, decl) in [
+            ("alerts", "suppressed_count", "INTEGER NOT NULL DEFAULT 0"),
+            ("alerts", "suppressed_eth", "REAL NOT NULL DEFAULT 0"),
+            ("alerts", "tx_hash", "TEXT"),
+            ("alerts", "block_kind", "TEXT"),
+            ("alerts", "victim", "TEXT"),
+            ("alerts", "attacker", "TEXT"),
+            ("alerts", "profit_eth", "REAL"),
+            ("alerts", "risk", "INTEGER"),
+            ("deliveries", "sink", "TEXT NOT NULL DEFAULT 'openclaw'"),
+            ("deliveries", "headers", "TEXT NOT NULL DEFAULT '{}'"),
+            ("deliveries", "signing_secret", "TEXT"),
+            ("deliveries", "auth", "TEXT"),
+            ("deliveries", "exit_status", "INTEGER"),
+            ("deliveries", "stderr", "TEXT"),
+        ] {
+            let exists = conn
+                .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")?
+                .exists(params![table, column])?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl};"))?;
+            }
+        }
+        // A finding is stored at most once per rule, so replayed flashblocks can't duplicate
+        // rows. MEV alerts key on the victim's tx, so the pattern and searcher are part of
+        // the key: a sandwich and a backrun on the same victim are separate findings.
+        conn.execute_batch("
+            DROP INDEX IF EXISTS idx_alerts_tx_rule;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_alerts_finding
+                ON alerts(tx_hash, rule_name, COALESCE(action, ''), COALESCE(attacker, ''));
         ")?;
 
         Ok(Self { conn: Mutex::new(conn) })
     }
 
-    pub fn insert(&self, alert: &Alert) -> eyre::Result<()> {
+    /// Store an alert. Returns `false` if this tx (or, for MEV, this finding on the
+    /// victim's tx) was already stored for the rule.
+    /// Block-scope alerts have no tx columns; their trigger kind goes in `block_kind`
+    /// and their category is `block`. Balance alerts store the account as `to_addr`,
+    /// the absolute change as `value_eth` and category `balance`. MEV alerts store the
+    /// pool as `to_addr`, the pattern as `action`, the victim's tx as `tx_hash` and
+    /// fill `victim`, `attacker` and `profit_eth`, with category `mev`. Approval alerts
+    /// keep their tx columns with category `security`, the owner as `victim`, the
+    /// spender as `attacker` and the score in `risk`.
+    pub fn insert(&self, alert: &Alert) -> eyre::Result<bool> {
         let payload = serde_json::to_string(alert)?;
+        let tx = alert.tx.as_ref();
+        let balance = alert.balance.as_ref();
+        let mev = alert.mev.as_ref();
+        let security = alert.security.as_ref();
+        let category = match (tx, balance, mev) {
+            (Some(t), _, _) => t.category.as_str(),
+            (None, Some(_), _) => "balance",
+            (None, None, Some(_)) => "mev",
+            (None, None, None) => "block",
+        };
         let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
-        conn.execute(
-            "INSERT INTO alerts (rule_name, block_number, fb_index, timestamp, to_addr, to_label, value_eth, action, category, payload)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO alerts (rule_name, block_number, fb_index, timestamp, to_addr, to_label, value_eth, action, category, payload, suppressed_count, suppressed_eth, tx_hash, block_kind, victim, attacker, profit_eth, risk)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
             params![
                 alert.rule_name,
                 alert.block_number.map(|n| n as i64),
                 alert.flashblock_index as i64,
                 alert.timestamp as i64,
-                alert.tx.to,
-                alert.tx.to_label,
-                alert.tx.value_eth,
-                alert.tx.action,
-                alert.tx.category,
+                tx.and_then(|t| t.to.as_deref())
+                    .or(balance.map(|b| b.address.as_str()))
+                    .or(mev.map(|m| m.pool.as_str())),
+                tx.and_then(|t| t.to_label.as_deref()).or(balance.and_then(|b| b.label.as_deref())),
+                tx.map(|t| t.value_eth)
+                    .or(balance.map(|b| b.delta_eth.abs()))
+                    .or(mev.and_then(|m| m.profit_eth))
+                    .unwrap_or(0.0),
+                tx.and_then(|t| t.action.as_deref()).or(mev.map(|m| m.kind.name())),
+                category,
                 payload,
+                alert.suppressed.as_ref().map_or(0, |s| s.count as i64),
+                alert.suppressed.as_ref().map_or(0.0, |s| s.total_eth),
+                tx.and_then(|t| t.hash.as_deref()).or(mev.map(|m| m.victim_tx.as_str())),
+                alert.block.as_ref().map(|b| b.kind.as_str()),
+                mev.map(|m| m.victim.as_str()).or(security.and_then(|f| f.owner.as_deref())),
+                mev.map(|m| m.attacker.as_str()).or(security.map(|f| f.spender.as_str())),
+                mev.and_then(|m| m.profit_eth),
+                security.map(|f| f.risk),
             ],
         )?;
-        Ok(())
+        Ok(inserted > 0)
+    }
+
+    /// Tx hashes of the most recent transaction alerts, newest first. MEV alerts are
+    /// left out: their hash is the victim's, which tx rules may still need to see.
+    pub fn recent_tx_hashes(&self, limit: usize) -> eyre::Result<Vec<String>> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let mut stmt = conn.prepare(
+            "SELECT tx_hash FROM alerts WHERE tx_hash IS NOT NULL AND category != 'mev' ORDER BY id DESC LIMIT ?1"
+        )?;
+        let hashes = stmt.query_map(params![limit as i64], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(hashes)
     }
 
     /// Query alerts with optional filters.
@@ -103,6 +226,11 @@ impl AlertStore {
             bind_values.push(Box::new(since as i64));
         }
 
+        if let Some(until) = params.until_ts {
+            where_clauses.push(format!("timestamp < ?{}", bind_values.len() + 1));
+            bind_values.push(Box::new(until as i64));
+        }
+
         let where_sql = if where_clauses.is_empty() {
             String::new()
         } else {
@@ -111,9 +239,10 @@ impl AlertStore {
 
         let limit = params.limit.unwrap_or(100).min(1000);
 
+        let order = if params.by_value { "value_eth DESC, id DESC" } else { "id DESC" };
         let sql = format!(
-            "SELECT payload FROM alerts {} ORDER BY id DESC LIMIT {}",
-            where_sql, limit
+            "SELECT payload FROM alerts {} ORDER BY {} LIMIT {}",
+            where_sql, order, limit
         );
 
         let refs: Vec<&dyn rusqlite::types::ToSql> = bind_values.iter().map(|b| b.as_ref()).collect();
@@ -125,11 +254,9 @@ impl AlertStore {
         })?;
 
         let mut results = Vec::new();
-        for row in rows {
-            if let Ok(payload) = row {
-                if let Ok(val) = serde_json::from_str(&payload) {
-                    results.push(val);
-                }
+        for payload in rows.flatten() {
+            if let Ok(val) = serde_json::from_str(&payload) {
+                results.push(val);
             }
         }
 
@@ -147,13 +274,16 @@ impl AlertStore {
             [], |r| r.get(0),
         )?;
 
+        let suppressed: i64 = conn.query_row("SELECT COALESCE(SUM(suppressed_count), 0) FROM alerts", [], |r| r.get(0))?;
+
         let mut stmt = conn.prepare(
-            "SELECT rule_name, COUNT(*) as cnt FROM alerts GROUP BY rule_name ORDER BY cnt DESC LIMIT 10"
+            "SELECT rule_name, COUNT(*) as cnt, SUM(suppressed_count) FROM alerts GROUP BY rule_name ORDER BY cnt DESC LIMIT 10"
         )?;
         let by_rule: Vec<serde_json::Value> = stmt.query_map([], |row| {
             let name: String = row.get(0)?;
             let count: i64 = row.get(1)?;
-            Ok(serde_json::json!({"rule": name, "count": count}))
+            let suppressed: i64 = row.get(2)?;
+            Ok(serde_json::json!({"rule": name, "count": count, "suppressed": suppressed}))
         })?.filter_map(|r| r.ok()).collect();
 
         let mut stmt = conn.prepare(
@@ -168,24 +298,260 @@ impl AlertStore {
         Ok(serde_json::json!({
             "total_alerts": total,
             "last_hour": last_hour,
+            "suppressed": suppressed,
+            "by_rule": by_rule,
+            "by_category": by_category,
+        }))
+    }
+
+    /// Summary stats for alerts with `since_ts <= timestamp < until_ts`, limited to
+    /// `rules` unless it is empty. Shaped like `stats`, plus `total_eth`.
+    pub fn window_stats(&self, since_ts: u64, until_ts: u64, rules: &[String]) -> eyre::Result<serde_json::Value> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+
+        let mut where_sql = "WHERE timestamp >= ?1 AND timestamp < ?2".to_string();
+        let mut bind_values: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(since_ts as i64), Box::new(until_ts as i64)];
+        if !rules.is_empty() {
+            let list: Vec<String> = (0..rules.len()).map(|i| format!("?{}", i + 3)).collect();
+            where_sql.push_str(&format!(" AND rule_name IN ({})", list.join(", ")));
+            bind_values.extend(rules.iter().map(|r| Box::new(r.clone()) as Box<dyn rusqlite::types::ToSql>));
+        }
+        let refs: Vec<&dyn rusqlite::types::ToSql> = bind_values.iter().map(|b| b.as_ref()).collect();
+
+        let (total, suppressed, total_eth): (i64, i64, f64) = conn.query_row(
+            &format!("SELECT COUNT(*), COALESCE(SUM(suppressed_count), 0), COALESCE(SUM(value_eth), 0) FROM alerts {where_sql}"),
+            refs.as_slice(), |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rule_name, COUNT(*) as cnt, SUM(suppressed_count), SUM(value_eth) FROM alerts {where_sql} GROUP BY rule_name ORDER BY cnt DESC"
+        ))?;
+        let by_rule: Vec<serde_json::Value> = stmt.query_map(refs.as_slice(), |row| {
+            let name: String = row.get(0)?;
+            let count: i64 = row.get(1)?;
+            let suppressed: i64 = row.get(2)?;
+            let eth: f64 = row.get(3)?;
+            Ok(serde_json::json!({"rule": name, "count": count, "suppressed": suppressed, "total_eth": eth}))
+        })?.filter_map(|r| r.ok()).collect();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT category, COUNT(*) as cnt FROM alerts {where_sql} GROUP BY category ORDER BY cnt DESC"
+        ))?;
+        let by_category: Vec<serde_json::Value> = stmt.query_map(refs.as_slice(), |row| {
+            let cat: String = row.get(0)?;
+            let count: i64 = row.get(1)?;
+            Ok(serde_json::json!({"category": cat, "count": count}))
+        })?.filter_map(|r| r.ok()).collect();
+
+        Ok(serde_json::json!({
+            "total_alerts": total,
+            "suppressed": suppressed,
+            "total_eth": total_eth,
             "by_rule": by_rule,
             "by_category": by_category,
         }))
     }
 
-    /// Prune alerts older than the given number of days. Returns count deleted.
+    /// Prune alerts, and finished deliveries, older than the given number of days.
+    /// Returns the number of alerts deleted.
     pub fn prune(&self, retention_days: u64) -> eyre::Result<usize> {
         let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
         let deleted = conn.execute(
             "DELETE FROM alerts WHERE timestamp < unixepoch() - ?1",
             params![retention_days * 86400],
         )?;
+        conn.execute(
+            "DELETE FROM deliveries WHERE status != 'pending' AND updated_ms < (unixepoch() - ?1) * 1000",
+            params![retention_days * 86400],
+        )?;
         if deleted > 0 {
             let _ = conn.execute_batch("PRAGMA incremental_vacuum;");
         }
         Ok(deleted)
     }
 
+    /// Record a snooze, replacing any existing one for the same target.
+    pub fn set_snooze(&self, kind: SnoozeKind, target: &str, until: u64) -> eyre::Result<()> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO snoozes (kind, target, until_ts) VALUES (?1, ?2, ?3)",
+            params![kind.as_str(), target, i64::try_from(until)?],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_snooze(&self, kind: SnoozeKind, target: &str) -> eyre::Result<()> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute("DELETE FROM snoozes WHERE kind = ?1 AND target = ?2", params![kind.as_str(), target])?;
+        Ok(())
+    }
+
+    /// Snoozes still in effect at `now` (epoch seconds). Expired rows are deleted.
+    pub fn active_snoozes(&self, now: u64) -> eyre::Result<Vec<(SnoozeKind, String, u64)>> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute("DELETE FROM snoozes WHERE until_ts <= ?1", params![now as i64])?;
+        let mut stmt = conn.prepare("SELECT kind, target, until_ts FROM snoozes")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+        })?;
+        let mut out = Vec::new();
+        for (kind, target, until) in rows.flatten() {
+            if let Some(kind) = SnoozeKind::parse(&kind) {
+                out.push((kind, target, until as u64));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Add a webhook delivery to the outbox, due immediately. Returns its id.
+    pub fn enqueue_delivery(&self, destination: &Destination, rules: &[String], payload: &serde_json::Value, now_ms: u64) -> eyre::Result<i64> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute(
+            "INSERT INTO deliveries (url, sink, headers, signing_secret, auth, rules, payload, next_attempt_ms, created_ms, updated_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, ?8)",
+            params![
+                destination.url,
+                destination.sink.as_str(),
+                serde_json::to_string(&destination.headers)?,
+                destination.signing_secret.clone().map(String::from),
+                destination.auth.as_ref().map(serde_json::to_string).transpose()?,
+                serde_json::to_string(rules)?,
+                payload.to_string(),
+                now_ms as i64,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Pending deliveries due at `now_ms`, oldest first.
+    pub fn due_deliveries(&self, now_ms: u64, limit: usize) -> eyre::Result<Vec<Delivery>> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM deliveries WHERE status = 'pending' AND next_attempt_ms <= ?1 ORDER BY id LIMIT ?2"
+        ))?;
+        let rows = stmt.query_map(params![now_ms as i64, limit as i64], Delivery::from_row)?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Record the outcome of an attempt. `next_attempt_ms` of `None` leaves the delivery
+    /// in `status` for good (`delivered` or `dead`); otherwise it stays pending.
+    pub fn record_attempt(
+        &self,
+        id: i64,
+        status: DeliveryStatus,
+        error: Option<&str>,
+        next_attempt_ms: Option<u64>,
+        now_ms: u64,
+    ) -> eyre::Result<()> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute(
+            "UPDATE deliveries SET status = ?2, attempts = attempts + 1, last_error = ?3,
+                next_attempt_ms = COALESCE(?4, next_attempt_ms), updated_ms = ?5 WHERE id = ?1",
+            params![id, status.as_str(), error, next_attempt_ms.map(|t| t as i64), now_ms as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Store what an `exec` sink's command reported for a delivery's latest attempt.
+    pub fn record_process_output(&self, id: i64, output: &ProcessOutput) -> eyre::Result<()> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute(
+            "UPDATE deliveries SET exit_status = ?2, stderr = ?3 WHERE id = ?1",
+            params![id, output.exit_status, Some(output.stderr.as_str()).filter(|s| !s.is_empty())],
+        )?;
+        Ok(())
+    }
+
+    /// Push a pending delivery back without counting an attempt — used while its
+    /// destination's circuit is open.
+    pub fn defer_delivery(&self, id: i64, until_ms: u64) -> eyre::Result<()> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute(
+            "UPDATE deliveries SET next_attempt_ms = MAX(next_attempt_ms, ?2) WHERE id = ?1 AND status = 'pending'",
+            params![id, until_ms as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Queue a delivery again from scratch, whatever its state. Returns `false` if there is no such delivery.
+    pub fn redeliver(&self, id: i64, now_ms: u64) -> eyre::Result<bool> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let updated = conn.execute(
+            "UPDATE deliveries SET status = 'pending', attempts = 0, next_attempt_ms = ?2, updated_ms = ?2 WHERE id = ?1",
+            params![id, now_ms as i64],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn delivery(&self, id: i64) -> eyre::Result<Option<Delivery>> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        Ok(conn
+            .query_row(&format!("SELECT {DELIVERY_COLUMNS} FROM deliveries WHERE id = ?1"), params![id], Delivery::from_row)
+            .optional()?)
+    }
+
+    /// Deliveries in any of `statuses`, newest first.
+    pub fn deliveries(&self, statuses: &[DeliveryStatus], limit: usize) -> eyre::Result<Vec<Delivery>> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let list = statuses.iter().map(|s| format!("'{}'", s.as_str())).collect::<Vec<_>>().join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {DELIVERY_COLUMNS} FROM deliveries WHERE status IN ({list}) ORDER BY id DESC LIMIT ?1"
+        ))?;
+        let rows = stmt.query_map(params![limit as i64], Delivery::from_row)?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Number of deliveries per status.
+    pub fn delivery_counts(&self) -> eyre::Result<serde_json::Value> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let mut counts = serde_json::json!({"pending": 0, "delivered": 0, "dead": 0});
+        let mut stmt = conn.prepare("SELECT status, COUNT(*) FROM deliveries GROUP BY status")?;
+        for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?.flatten() {
+            counts[row.0] = row.1.into();
+        }
+        Ok(counts)
+    }
+
+    /// Buffer records for the message-bus exporter, then drop the oldest beyond
+    /// `max`. Returns how many were dropped.
+    pub fn buffer_exports(&self, records: &[(String, String)], now_ms: u64, max: usize) -> eyre::Result<usize> {
+        let mut conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare("INSERT INTO exports (topic, payload, created_ms) VALUES (?1, ?2, ?3)")?;
+            for (topic, payload) in records {
+                stmt.execute(params![topic, payload, now_ms as i64])?;
+            }
+        }
+        let dropped = tx.execute(
+            "DELETE FROM exports WHERE id <= (SELECT MAX(id) FROM exports) - ?1",
+            params![max as i64],
+        )?;
+        tx.commit()?;
+        Ok(dropped)
+    }
+
+    /// The oldest buffered export records, as `(id, topic, payload)`.
+    pub fn pending_exports(&self, limit: usize) -> eyre::Result<Vec<(i64, String, String)>> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        let mut stmt = conn.prepare("SELECT id, topic, payload FROM exports ORDER BY id LIMIT ?1")?;
+        let rows = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Forget export records up to and including `id`, once the broker has them.
+    pub fn ack_exports(&self, id: i64) -> eyre::Result<()> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        conn.execute("DELETE FROM exports WHERE id <= ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Export records waiting for the broker.
+    pub fn export_backlog(&self) -> eyre::Result<i64> {
+        let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
+        Ok(conn.query_row("SELECT COUNT(*) FROM exports", [], |r| r.get(0))?)
+    }
+
     /// Insert a query record (from /api/ask).
     pub fn insert_query(&self, question: &str, answer: Option<&str>, payment_tx: Option<&str>, payer: Option<&str>, network: Option<&str>) -> eyre::Result<()> {
         let conn = self.conn.lock().map_err(|e| eyre::eyre!("DB lock poisoned: {e}"))?;
@@ -224,6 +590,10 @@ pub struct AlertQuery {
     pub category: Option<String>,
     pub min_eth: Option<f64>,
     pub since_ts: Option<u64>,
+    /// Only alerts before this time (exclusive).
+    pub until_ts: Option<u64>,
+    /// Largest `value_eth` first, instead of newest first.
+    pub by_value: bool,
     pub limit: Option<usize>,
 }
 
@@ -243,6 +613,8 @@ impl AlertQuery {
             category: params.get("category").cloned(),
             min_eth: params.get("min_eth").and_then(|v| v.parse().ok()),
             since_ts,
+            until_ts: params.get("until").and_then(|v| v.parse().ok()),
+            by_value: params.get("sort").is_some_and(|v| v == "value"),
             limit: params.get("limit").and_then(|v| v.parse().ok()),
         }
     }
@@ -264,3 +636,169 @@ fn parse_duration_secs(s: &str) -> Option<u64> {
         _ => None,
     }
 }
+
+const DELIVERY_COLUMNS: &str = "id, url, rules, payload, status, attempts, next_attempt_ms, last_error, created_ms, updated_ms, sink, headers, signing_secret, auth, exit_status, stderr";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt or a retry.
+    Pending,
+    Delivered,
+    /// Out of attempts, or rejected by the receiver. Only a manual redelivery retries it.
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Dead => "dead",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(Self::Pending),
+            "delivered" => Some(Self::Delivered),
+            "dead" => Some(Self::Dead),
+            _ => None,
+        }
+    }
+}
+
+/// One webhook POST in the outbox. Times are epoch milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub id: i64,
+    pub url: String,
+    pub sink: SinkKind,
+    /// Sent with the payload. Credentials are stored as `env:`/`file:` references and
+    /// resolved at send time; none of these are serialized.
+    #[serde(skip)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub auth: Option<WebhookAuth>,
+    #[serde(skip)]
+    pub signing_secret: Option<SecretRef>,
+    /// Rules whose alerts the payload carries.
+    pub rules: Vec<String>,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_ms: u64,
+    pub last_error: Option<String>,
+    /// What an `exec` sink's command last reported: its exit status (`None` if it
+    /// was killed) and the end of its stderr.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_status: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+    pub created_ms: u64,
+    pub updated_ms: u64,
+}
+
+impl Delivery {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let rules: String = row.get(2)?;
+        let payload: String = row.get(3)?;
+        let status: String = row.get(4)?;
+        let sink: String = row.get(10)?;
+        let headers: String = row.get(11)?;
+        let signing_secret: Option<String> = row.get(12)?;
+        let auth: Option<String> = row.get(13)?;
+        Ok(Self {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            sink: SinkKind::parse(&sink).unwrap_or_default(),
+            headers: serde_json::from_str(&headers).unwrap_or_default(),
+            auth: auth.and_then(|a| serde_json::from_str(&a).ok()),
+            signing_secret: signing_secret.and_then(|s| SecretRef::parse(&s).ok()),
+            rules: serde_json::from_str(&rules).unwrap_or_default(),
+            payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+            status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Pending),
+            attempts: row.get::<_, i64>(5)? as u32,
+            next_attempt_ms: row.get::<_, i64>(6)? as u64,
+            last_error: row.get(7)?,
+            exit_status: row.get(14)?,
+            stderr: row.get(15)?,
+            created_ms: row.get::<_, i64>(8)? as u64,
+            updated_ms: row.get::<_, i64>(9)? as u64,
+        })
+    }
+
+    /// Where this delivery goes, as queued.
+    pub fn destination(&self) -> Destination {
+        Destination {
+            url: self.url.clone(),
+            sink: self.sink,
+            headers: self.headers.clone(),
+            auth: self.auth.clone(),
+            signing_secret: self.signing_secret.clone(),
+            template: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mev::{MevFinding, MevKind};
+    use crate::rules::{AlertTx, Severity};
+
+    fn alert(tx: Option<AlertTx>, mev: Option<MevFinding>) -> Alert {
+        Alert {
+            rule_name: "watch".into(),
+            matched_rules: vec!["watch".into()],
+            severity: Severity::Warn,
+            block_number: Some(1),
+            flashblock_index: 0,
+            tx,
+            block: None,
+            balance: None,
+            mev,
+            security: None,
+            timestamp: 1_700_000_000,
+            suppressed: None,
+            digest: false,
+        }
+    }
+
+    #[test]
+    fn test_replays_are_ignored_but_distinct_findings_are_kept() {
+        let store = AlertStore::open(Path::new(":memory:")).unwrap();
+        let tx = AlertTx {
+            hash: Some("0xaa".into()),
+            from: None,
+            to: None,
+            to_label: None,
+            value_eth: 150.0,
+            action: None,
+            category: "transfer".into(),
+            contract_creation: None,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_gwei: 0.0,
+            priority_fee_gwei: 0.0,
+        };
+        assert!(store.insert(&alert(Some(tx.clone()), None)).unwrap());
+        assert!(!store.insert(&alert(Some(tx), None)).unwrap());
+
+        let finding = |kind, attacker: &str| MevFinding {
+            kind,
+            pool: "0xpool".into(),
+            attacker: attacker.into(),
+            bot: None,
+            victim: "0xvictim".into(),
+            victim_tx: "0xbb".into(),
+            attacker_txs: vec![],
+            profit_eth: Some(0.1),
+        };
+        assert!(store.insert(&alert(None, Some(finding(MevKind::Sandwich, "0xs1")))).unwrap());
+        assert!(!store.insert(&alert(None, Some(finding(MevKind::Sandwich, "0xs1")))).unwrap());
+        assert!(store.insert(&alert(None, Some(finding(MevKind::Backrun, "0xs1")))).unwrap());
+        assert!(store.insert(&alert(None, Some(finding(MevKind::Backrun, "0xs2")))).unwrap());
+        assert_eq!(store.stats().unwrap()["total_alerts"], 4);
+    }
+}
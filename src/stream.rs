@@ -14,10 +14,10 @@ use crate::types::FlashblockMessage;
 /// Decode a WebSocket message — may be plain JSON text or brotli-compressed binary.
 fn decode_message(data: &[u8]) -> Option<String> {
     // Try plain text first
-    if let Ok(text) = std::str::from_utf8(data) {
-        if text.trim_start().starts_with('{') {
-            return Some(text.to_owned());
-        }
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.trim_start().starts_with('{')
+    {
+        return Some(text.to_owned());
     }
     // Try brotli decompression
     let mut decompressor = brotli::Decompressor::new(data, 4096);
@@ -165,10 +165,10 @@ pub async fn logs(
                         .unwrap_or_default();
 
                     // Apply filters
-                    if let Some(ref af) = addr_filter {
-                        if log_addr != *af {
-                            continue;
-                        }
+                    if let Some(ref af) = addr_filter
+                        && log_addr != *af
+                    {
+                        continue;
                     }
                     if let Some(ref tf) = topic_filter {
                         let matches = topics.iter().any(|t| t.to_lowercase() == *tf);
@@ -0,0 +1,319 @@
+//! Message templates — a rule's `template` replaces the built-in OpenClaw agent
+//! prompt, e.g. to give the agent a team's own instructions instead of posting to
+//! Moltbook. Templates live under `[templates]` in the rules file, inline or in a
+//! file next to it:
+//!
+//!   [templates.triage]
+//!   file = "templates/triage.txt"
+//!
+//! The syntax is a small subset of Handlebars:
+//!
+//!   {{ tx.value_eth }}                 — a field; missing fields render empty
+//!   {{#if security}}…{{else}}…{{/if}}  — null, false, 0, "" and [] are false
+//!   {{#each alerts}}{{ this.rule_name }}{{/each}}
+//!
+//! Names are looked up in the innermost `#each` item first, then outwards. See
+//! `alert_context` for what a template can use. `flashwatch template render`
+//! previews one against a transaction.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::alert::{alert_detail_lines, alert_headline, alert_note_lines, alert_report, batch_report};
+use crate::rules::{Alert, AlertTx, RulesConfig, Trigger};
+
+/// A `[templates.<name>]` entry: exactly one of `file` (relative to the rules file) or `text`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct TemplateConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Parsed from `file` or `text` when the rules file is loaded.
+    #[serde(skip)]
+    pub compiled: Template,
+}
+
+impl TemplateConfig {
+    /// Read and parse the template. `base` is the rules file's directory.
+    pub fn load(&mut self, name: &str, base: &Path) -> eyre::Result<()> {
+        let source = match (&self.file, &self.text) {
+            (Some(file), None) => std::fs::read_to_string(base.join(file))
+                .map_err(|e| eyre::eyre!("template '{}': cannot read {}: {}", name, file, e))?,
+            (None, Some(text)) => text.clone(),
+            _ => eyre::bail!("template '{}' needs exactly one of file or text", name),
+        };
+        self.compiled = Template::parse(&source).map_err(|e| eyre::eyre!("template '{}': {}", name, e))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Field(String),
+    If { path: String, then: Vec<Node>, otherwise: Vec<Node> },
+    Each { path: String, body: Vec<Node> },
+}
+
+/// A block being parsed.
+#[derive(Default)]
+struct Block {
+    /// `("if" | "each", path)`; `None` for the top level.
+    open: Option<(String, String)>,
+    nodes: Vec<Node>,
+    /// An `#if`'s nodes before its `else`, once the `else` is reached.
+    then: Option<Vec<Node>>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut stack = vec![Block::default()];
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                return Err(format!("unclosed '{{{{' at byte {}", source.len() - rest.len() + start));
+            };
+            let nodes = &mut stack.last_mut().expect("top level is never popped").nodes;
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            let tag = rest[start + 2..start + 2 + len].trim();
+            rest = &rest[start + 2 + len + 2..];
+
+            if let Some(open) = tag.strip_prefix('#') {
+                let (kind, path) = open.split_once(char::is_whitespace).unwrap_or((open, ""));
+                if !matches!(kind, "if" | "each") {
+                    return Err(format!("unknown block '#{}'", kind));
+                }
+                if path.trim().is_empty() {
+                    return Err(format!("'#{}' needs a field", kind));
+                }
+                stack.push(Block { open: Some((kind.to_string(), path.trim().to_string())), ..Block::default() });
+            } else if let Some(close) = tag.strip_prefix('/') {
+                let Block { open: Some((kind, path)), nodes, then } = stack.pop().expect("top level is never popped") else {
+                    return Err(format!("'/{}' without an open block", close));
+                };
+                if close.trim() != kind {
+                    return Err(format!("'/{}' closes '#{}'", close.trim(), kind));
+                }
+                let node = match (kind.as_str(), then) {
+                    ("if", Some(then)) => Node::If { path, then, otherwise: nodes },
+                    ("if", None) => Node::If { path, then: nodes, otherwise: Vec::new() },
+                    _ => Node::Each { path, body: nodes },
+                };
+                stack.last_mut().expect("an open block has a parent").nodes.push(node);
+            } else if tag == "else" {
+                let top = stack.last_mut().expect("top level is never popped");
+                if !matches!(top.open, Some((ref kind, _)) if kind == "if") || top.then.is_some() {
+                    return Err("'else' outside an '#if'".to_string());
+                }
+                top.then = Some(std::mem::take(&mut top.nodes));
+            } else if tag.is_empty() {
+                return Err("empty '{{}}'".to_string());
+            } else {
+                stack.last_mut().expect("top level is never popped").nodes.push(Node::Field(tag.to_string()));
+            }
+        }
+        if let Some(Block { open: Some((kind, _)), .. }) = stack.last() {
+            return Err(format!("'#{}' is never closed", kind));
+        }
+        let mut nodes = stack.pop().expect("top level is never popped").nodes;
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &mut vec![context], &mut out);
+        out
+    }
+}
+
+fn render_nodes(nodes: &[Node], scopes: &mut Vec<&Value>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field(path) => match lookup(scopes, path) {
+                Some(Value::String(s)) => out.push_str(s),
+                Some(Value::Null) | None => {}
+                Some(v) => out.push_str(&v.to_string()),
+            },
+            Node::If { path, then, otherwise } => {
+                let branch = if lookup(scopes, path).is_some_and(truthy) { then } else { otherwise };
+                render_nodes(branch, scopes, out);
+            }
+            Node::Each { path, body } => {
+                let Some(Value::Array(items)) = lookup(scopes, path) else { continue };
+                for item in items {
+                    scopes.push(item);
+                    render_nodes(body, scopes, out);
+                    scopes.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Resolve `a.b.0.c`, innermost scope first. `this` is the innermost scope itself.
+fn lookup<'a>(scopes: &[&'a Value], path: &str) -> Option<&'a Value> {
+    let walk = |root: &'a Value, parts: &[&str]| {
+        parts.iter().try_fold(root, |v, part| match v {
+            Value::Object(map) => map.get(*part),
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+    };
+    let parts: Vec<&str> = path.split('.').collect();
+    if parts[0] == "this" {
+        return scopes.last().and_then(|root| walk(root, &parts[1..]));
+    }
+    scopes.iter().rev().find_map(|root| walk(root, &parts))
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+/// What a template sees for one alert: every `Alert` field (`rule_name`, `severity`,
+/// `tx.value_eth`, `tx.from`, `security.risk`, …) plus
+///
+///   title, summary  — the headline and its "value | rule | block" line
+///   notes, details  — suppression notes; labelled addresses and basescan links, one per line
+///   message         — all of the above: the built-in prompt without its instructions
+///   links.tx / links.from / links.to / links.block — basescan URLs, where known
+///   labels.from / labels.to — address labels, where known
+///   critical        — whether the severity is critical
+///   alerts, count   — this alert in a list, so batch templates work for single alerts too
+pub fn alert_context(alert: &Alert, labels: &HashMap<String, String>) -> Value {
+    let mut context = serde_json::to_value(alert).unwrap_or_else(|_| json!({}));
+    let (title, summary) = alert_headline(alert);
+    let label = |addr: Option<&str>| addr.and_then(|a| labels.get(&a.to_lowercase()).cloned());
+    let tx = alert.tx.as_ref();
+    let address_link = |addr: &String| format!("https://basescan.org/address/{}", addr);
+    let extra = json!({
+        "title": title,
+        "summary": summary,
+        "notes": alert_note_lines(alert).join("\n"),
+        "details": alert_detail_lines(alert, labels).join("\n"),
+        "message": alert_report(alert, labels),
+        "critical": alert.severity == crate::rules::Severity::Critical,
+        "links": {
+            "tx": tx.and_then(|t| t.hash.as_ref()).map(|h| format!("https://basescan.org/tx/{}", h)),
+            "from": tx.and_then(|t| t.from.as_ref()).map(address_link),
+            "to": tx.and_then(|t| t.to.as_ref()).map(address_link),
+            "block": alert.block_number.map(|n| format!("https://basescan.org/block/{}", n)),
+        },
+        "labels": {
+            "from": label(tx.and_then(|t| t.from.as_deref())),
+            "to": tx.and_then(|t| t.to_label.clone()).or_else(|| label(tx.and_then(|t| t.to.as_deref()))),
+        },
+    });
+    if let (Value::Object(context), Value::Object(extra)) = (&mut context, extra) {
+        context.extend(extra);
+    }
+    let single = context.clone();
+    context["alerts"] = json!([single]);
+    context["count"] = json!(1);
+    context
+}
+
+/// What a template sees for a batch: `alerts` (each as in `alert_context`), `count`,
+/// `title` and `message` — the built-in batch prompt without its instructions.
+pub fn batch_context(alerts: &[Alert], labels: &HashMap<String, String>) -> Value {
+    match alerts {
+        [alert] => alert_context(alert, labels),
+        alerts => json!({
+            "title": "FlashWatch Alert Batch — Base Mainnet",
+            "message": batch_report(alerts, labels),
+            "count": alerts.len(),
+            "alerts": alerts.iter().map(|a| alert_context(a, labels)).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// The rule's template applied to alerts bound for one destination, if it has one.
+pub fn render_for(alerts: &[Alert], config: &RulesConfig) -> Option<String> {
+    let rule = config.rules.iter().find(|r| Some(&r.name) == alerts.first().map(|a| &a.rule_name))?;
+    let template = config.templates.get(rule.template.as_ref()?)?;
+    Some(template.compiled.render(&batch_context(alerts, &config.labels)))
+}
+
+/// `flashwatch template render`: the message a rule would send for a transaction.
+pub async fn render_cmd(rules_path: &str, rpc_url: &str, rule_name: &str, tx: &str) -> eyre::Result<()> {
+    let config = RulesConfig::from_file(Path::new(rules_path))?;
+    let rule = config.rules.iter().find(|r| r.name == rule_name)
+        .ok_or_else(|| eyre::eyre!("no rule named '{}' in {}", rule_name, rules_path))?;
+    let decoded = crate::check::load_tx(rpc_url, tx).await?;
+
+    if !crate::rules::explain_match(&rule.trigger, &decoded, &config.labels).iter().all(|c| c.ok) {
+        eprintln!("{} rule '{}' does not match this transaction — rendering anyway", "note:".yellow().bold(), rule.name);
+    }
+    let mut alert_tx = AlertTx::from(&decoded);
+    let security = matches!(rule.trigger, Trigger::Approval { .. })
+        .then(|| crate::security::assess(&decoded, &config.labels, &Default::default()))
+        .flatten();
+    if security.is_some() {
+        alert_tx.category = "security".to_string();
+    }
+    let alert = Alert {
+        rule_name: rule.name.clone(),
+        matched_rules: vec![rule.name.clone()],
+        severity: rule.severity,
+        block_number: None,
+        flashblock_index: 0,
+        tx: Some(alert_tx),
+        block: None,
+        balance: None,
+        mev: None,
+        security,
+        timestamp: chrono::Utc::now().timestamp() as u64,
+        suppressed: None,
+        digest: false,
+    };
+    if rule.template.is_none() {
+        eprintln!("{} rule '{}' has no template — showing the built-in message", "note:".yellow().bold(), rule.name);
+    }
+    let payload = crate::alert::agent_payload(std::slice::from_ref(&alert), &config);
+    println!("{}", payload["message"].as_str().unwrap_or_default());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_syntax() {
+        let t = Template::parse("{{ rule }}: {{#if risk}}risk {{risk}}{{else}}no risk{{/if}}{{#each tags}} #{{this}}{{/each}}{{missing}}").unwrap();
+        assert_eq!(t.render(&json!({"rule": "r", "risk": 90, "tags": ["a", "b"]})), "r: risk 90 #a #b");
+        assert_eq!(t.render(&json!({"rule": "r", "risk": 0})), "r: no risk");
+
+        let nested = Template::parse("{{#each alerts}}{{rule_name}}@{{this.tx.0}}/{{count}};{{/each}}").unwrap();
+        assert_eq!(nested.render(&json!({"count": 2, "alerts": [{"rule_name": "a", "tx": [1]}, {"rule_name": "b"}]})), "a@1/2;b@/2;");
+
+        assert!(Template::parse("{{#if x}}open").unwrap_err().contains("never closed"));
+        assert!(Template::parse("{{#each x}}{{/if}}").unwrap_err().contains("closes"));
+        assert!(Template::parse("{{else}}").is_err());
+        assert!(Template::parse("{{#with x}}{{/with}}").is_err());
+        assert!(Template::parse("{{ x").is_err());
+    }
+}
@@ -42,6 +42,16 @@ pub struct FlashblockMessage {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// An event log from a transaction receipt.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Log {
+    pub address: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub data: String,
+}
+
 /// Block header fields sent with the initial flashblock (index 0).
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct FlashblockBase {
@@ -109,6 +119,57 @@ impl FlashblockMessage {
         })
     }
 
+    /// Post-flashblock ETH balances in wei from `metadata.new_account_balances`,
+    /// keyed by lowercase address.
+    pub fn new_account_balances(&self) -> Vec<(String, u128)> {
+        let Some(balances) = self.metadata.as_ref()
+            .and_then(|m| m.get("new_account_balances"))
+            .and_then(|b| b.as_object())
+        else {
+            return Vec::new();
+        };
+        balances
+            .iter()
+            .filter_map(|(addr, val)| {
+                let wei = u128::from_str_radix(val.as_str()?.trim_start_matches("0x"), 16).ok()?;
+                Some((addr.to_lowercase(), wei))
+            })
+            .collect()
+    }
+
+    /// Receipt logs per tx hash (lowercase), from `metadata.receipts` (keyed by hash,
+    /// each receipt possibly wrapped in its tx type, e.g. `{"Eip1559": {...}}`) or
+    /// from a `diff.receipts` array whose logs carry `transactionHash`.
+    pub fn receipt_logs(&self) -> std::collections::HashMap<String, Vec<Log>> {
+        let mut out: std::collections::HashMap<String, Vec<Log>> = std::collections::HashMap::new();
+        let parse_logs = |receipt: &serde_json::Value| -> Vec<Log> {
+            let receipt = match receipt.get("logs") {
+                Some(_) => receipt,
+                // Typed envelope: the single value inside holds the receipt.
+                None => receipt.as_object().and_then(|o| o.values().next()).unwrap_or(receipt),
+            };
+            receipt.get("logs").and_then(|l| l.as_array())
+                .map(|logs| logs.iter().filter_map(|l| serde_json::from_value(l.clone()).ok()).collect())
+                .unwrap_or_default()
+        };
+
+        if let Some(receipts) = self.metadata.as_ref().and_then(|m| m.get("receipts")).and_then(|r| r.as_object()) {
+            for (hash, receipt) in receipts {
+                out.insert(hash.to_lowercase(), parse_logs(receipt));
+            }
+        }
+        if let Some(serde_json::Value::Array(receipts)) = &self.diff.receipts {
+            for receipt in receipts {
+                let hash = receipt.get("transactionHash").and_then(|h| h.as_str())
+                    .or_else(|| receipt.pointer("/logs/0/transactionHash").and_then(|h| h.as_str()));
+                if let Some(hash) = hash {
+                    out.entry(hash.to_lowercase()).or_insert_with(|| parse_logs(receipt));
+                }
+            }
+        }
+        out
+    }
+
     /// Parse timestamp from base header.
     pub fn timestamp(&self) -> Option<u64> {
         self.base
@@ -129,6 +190,12 @@ pub struct BlockState {
     pub flashblock_count: u64,
     pub total_gas_used: u64,
     pub total_tx_count: usize,
+    /// Gas used by the block so far — `diff.gas_used` is cumulative within a block.
+    pub gas_used: u64,
+    /// Transactions in the most recent flashblock.
+    pub flashblock_tx_count: usize,
+    /// Base fee of the previous block, for change detection.
+    pub prev_base_fee_gwei: Option<f64>,
 }
 
 impl BlockState {
@@ -139,6 +206,8 @@ impl BlockState {
             self.flashblock_count = 0;
             self.total_gas_used = 0;
             self.total_tx_count = 0;
+            self.gas_used = 0;
+            self.prev_base_fee_gwei = self.base_fee_gwei;
             self.block_number = msg.block_number();
             self.gas_limit = msg.gas_limit();
             self.base_fee_gwei = msg.base_fee_gwei();
@@ -147,8 +216,23 @@ impl BlockState {
         self.flashblock_count += 1;
         if let Some(gas) = msg.gas_used() {
             self.total_gas_used += gas;
+            self.gas_used = self.gas_used.max(gas);
         }
         self.total_tx_count += msg.tx_count();
+        self.flashblock_tx_count = msg.tx_count();
+    }
+
+    /// Gas used so far as a percentage of the block gas limit.
+    pub fn gas_used_pct(&self) -> Option<f64> {
+        self.gas_limit.filter(|l| *l > 0).map(|l| self.gas_used as f64 / l as f64 * 100.0)
+    }
+
+    /// Signed base fee change from the previous block, in percent.
+    pub fn base_fee_change_pct(&self) -> Option<f64> {
+        match (self.prev_base_fee_gwei, self.base_fee_gwei) {
+            (Some(prev), Some(cur)) if prev > 0.0 => Some((cur - prev) / prev * 100.0),
+            _ => None,
+        }
     }
 }
 